@@ -0,0 +1,317 @@
+use cubelib::cube::{CanonicalTransformation, Cube333, CubeAxis};
+use cubelib::defs::{NissSwitchType, StepKind, StepVariant};
+use cubelib::solver::solution::{Solution, SolutionStep};
+use cubelib::steps::step::StepConfig;
+use log::error;
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use serde_json::Value;
+
+use crate::db;
+
+// Parameters that mean the same in every orientation. Steps with other parameters aren't shared between orientations
+const SYMMETRIC_PARAMS: &[&str] = &["subsets", "htr-breaking", "max-use", "step-limit"];
+
+/// Identifies the results of a solve in the cache.
+///
+/// Equivalent states share results. Solutions and settings are stored in the orientation of the canonical state,
+/// so e.g. `EO[fb]` on a state becomes `EO[ud]` if the canonical state is rotated by `x`.
+pub struct CacheKey {
+    pub canonical_scramble_id: String,
+    pub settings: String,
+}
+
+impl CacheKey {
+    /// Returns the key and the transformation from the cube onto the state the key refers to
+    pub fn new(cube: &Cube333, steps: &[StepConfig]) -> (Self, CanonicalTransformation) {
+        // Inverting the state swaps the sides of all solutions, which is only fine if the first step may start on either side
+        let allow_inversion = steps.first().is_some_and(|step| matches!(step.niss, Some(NissSwitchType::Before | NissSwitchType::Always)));
+        let (canonical, transformation) = cube.canonical_with(allow_inversion);
+        match transform_settings(steps, &transformation) {
+            Some(steps) => (Self {
+                canonical_scramble_id: db::serialize_cube_to_base64(&canonical),
+                settings: normalize_settings(&steps),
+            }, transformation),
+            None => (Self {
+                canonical_scramble_id: db::serialize_cube_to_base64(cube),
+                settings: normalize_settings(steps),
+            }, CanonicalTransformation::IDENTITY),
+        }
+    }
+}
+
+// Returns None if the steps have settings that can't be transformed
+fn transform_settings(steps: &[StepConfig], transformation: &CanonicalTransformation) -> Option<Vec<StepConfig>> {
+    steps.iter()
+        .map(|step| {
+            // VR solutions have slice insertions, which depend on the orientation in ways we don't track here
+            if matches!(step.kind, StepKind::VR | StepKind::Other(_)) || step.params.keys().any(|key| !SYMMETRIC_PARAMS.contains(&key.as_str())) {
+                return None;
+            }
+            Some(StepConfig {
+                substeps: step.substeps.as_ref().map(|substeps| substeps.iter()
+                    .map(|substep| transform_axis_names(substep, transformation))
+                    .collect()),
+                excluded: step.excluded.iter()
+                    .map(|alg| transformation.map_to_canonical(alg.clone()))
+                    .collect(),
+                ..step.clone()
+            })
+        })
+        .collect()
+}
+
+// Substeps name their axes, like `drud-eofb` or `fb`
+fn transform_axis_names(substep: &str, transformation: &CanonicalTransformation) -> String {
+    let substep = substep.to_lowercase();
+    let mut transformed = String::new();
+    let mut rest = substep.as_str();
+    while !rest.is_empty() {
+        let axis = [CubeAxis::UD, CubeAxis::FB, CubeAxis::LR].into_iter()
+            .find(|axis| rest.starts_with(axis.name()));
+        match axis {
+            Some(axis) => {
+                transformed.push_str(transformation.map_axis_to_canonical(axis).name());
+                rest = &rest[2..];
+            },
+            None => {
+                let c = rest.chars().next().unwrap();
+                transformed.push(c);
+                rest = &rest[c.len_utf8()..];
+            },
+        }
+    }
+    transformed
+}
+
+// The quality and step limit are overwritten for every search round, and hash maps and sets are serialized in random order
+fn normalize_settings(steps: &[StepConfig]) -> String {
+    let steps: Vec<Value> = steps.iter()
+        .map(|step| {
+            let step = StepConfig {
+                quality: 0,
+                step_limit: None,
+                ..step.clone()
+            };
+            let mut value = serde_json::to_value(&step).unwrap();
+            if let Some(Value::Array(excluded)) = value.get_mut("excluded") {
+                excluded.sort_by_key(|alg| alg.to_string());
+            }
+            value
+        })
+        .collect();
+    serde_json::to_string(&steps).unwrap()
+}
+
+fn transform_solution(solution: Solution, transformation: &CanonicalTransformation, to_canonical: bool) -> Solution {
+    let map_axis = |axis| if to_canonical {
+        transformation.map_axis_to_canonical(axis)
+    } else {
+        transformation.map_axis_from_canonical(axis)
+    };
+    Solution {
+        steps: solution.steps.into_iter()
+            .map(|step| SolutionStep {
+                variant: transform_variant(step.variant, map_axis),
+                alg: if to_canonical {
+                    transformation.map_to_canonical(step.alg)
+                } else {
+                    transformation.map_from_canonical(step.alg)
+                },
+                ..step
+            })
+            .collect(),
+        ends_on_normal: solution.ends_on_normal != transformation.inverted,
+        vr: solution.vr,
+    }
+}
+
+fn transform_variant<A: Fn(CubeAxis) -> CubeAxis>(variant: StepVariant, map_axis: A) -> StepVariant {
+    match variant {
+        StepVariant::EO(axis) => StepVariant::EO(map_axis(axis)),
+        StepVariant::RZP { eo_axis, dr_axis } => StepVariant::RZP { eo_axis: map_axis(eo_axis), dr_axis: map_axis(dr_axis) },
+        StepVariant::AR { eo_axis, dr_axis } => StepVariant::AR { eo_axis: map_axis(eo_axis), dr_axis: map_axis(dr_axis) },
+        StepVariant::DR { eo_axis, dr_axis } => StepVariant::DR { eo_axis: map_axis(eo_axis), dr_axis: map_axis(dr_axis) },
+        StepVariant::HTR(axis) => StepVariant::HTR(map_axis(axis)),
+        StepVariant::FR(axis) => StepVariant::FR(map_axis(axis)),
+        StepVariant::FRLS(axis) => StepVariant::FRLS(map_axis(axis)),
+        StepVariant::FRFIN(axis) => StepVariant::FRFIN(map_axis(axis)),
+        StepVariant::FRFINLS(axis) => StepVariant::FRFINLS(map_axis(axis)),
+        StepVariant::HTRFINLS(axis) => StepVariant::HTRFINLS(map_axis(axis)),
+        StepVariant::DRFIN(axis) => StepVariant::DRFIN(map_axis(axis)),
+        StepVariant::DRFINLS(axis) => StepVariant::DRFINLS(map_axis(axis)),
+        variant @ (StepVariant::HTRFIN | StepVariant::GEN | StepVariant::Custom(_)) => variant,
+    }
+}
+
+/// Solutions of earlier requests for the same cube state and steps. Database errors are logged and otherwise ignored,
+/// as the cache only saves time.
+pub struct SolutionCache {
+    pool: Pool<SqliteConnectionManager>,
+    key: CacheKey,
+    transformation: CanonicalTransformation,
+    ranking: String,
+}
+
+impl SolutionCache {
+    pub fn new(pool: Pool<SqliteConnectionManager>, cube: &Cube333, steps: &[StepConfig], rank_by: Option<&str>) -> Self {
+        let (key, transformation) = CacheKey::new(cube, steps);
+        Self {
+            pool,
+            key,
+            transformation,
+            ranking: rank_by.unwrap_or_default().to_string(),
+        }
+    }
+
+    /// Shortest first
+    pub fn solutions(&self) -> Vec<Solution> {
+        self.with_connection(|conn| db::get_cached_solutions(conn, &self.key))
+            .unwrap_or_default()
+            .into_iter()
+            .map(|solution| transform_solution(solution, &self.transformation, false))
+            .collect()
+    }
+
+    /// The highest quality an earlier request searched completely with the same ranking
+    pub fn searched_quality(&self) -> Option<usize> {
+        self.with_connection(|conn| db::get_cached_quality(conn, &self.key, &self.ranking))
+            .flatten()
+    }
+
+    pub fn add_solution(&self, solution: &Solution) {
+        let solution = transform_solution(solution.clone(), &self.transformation, true);
+        self.with_connection(|conn| db::cache_solution(conn, &self.key, &solution));
+    }
+
+    pub fn add_searched_quality(&self, quality: usize) {
+        self.with_connection(|conn| db::cache_quality(conn, &self.key, &self.ranking, quality));
+    }
+
+    fn with_connection<T, F: FnOnce(&db::Connection) -> rusqlite::Result<T>>(&self, f: F) -> Option<T> {
+        let result = match self.pool.get() {
+            Ok(conn) => f(&conn).map_err(|err| err.to_string()),
+            Err(err) => Err(err.to_string()),
+        };
+        result.inspect_err(|err| error!("Solution cache: {err}")).ok()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use cubelib::algs::Algorithm;
+    use cubelib::cube::{Cube333, CubeAxis, CubeFace, Transformation333};
+    use cubelib::cube::turn::{InvertibleMut, TransformableMut};
+    use cubelib::defs::{NissSwitchType, StepKind};
+    use cubelib::solver::backend::{SolveRequest, Solver};
+    use cubelib::solver::solution::Solution;
+    use cubelib::solver_new::backend::MultiPathChannelSolver;
+    use cubelib::steps::eo::coords::BadEdgeCount;
+    use cubelib::steps::step::StepConfig;
+
+    use crate::cache::{CacheKey, SolutionCache};
+    use crate::db;
+
+    const SCRAMBLE: &str = "R' U' F L' B L' B2 U B2 D U2 L2 F2 L2 R2 D' L2 B' F' R' F L' F' U' R' U' F";
+
+    fn eo(axis: &str, niss: NissSwitchType) -> Vec<StepConfig> {
+        vec![StepConfig {
+            substeps: Some(vec![axis.to_string()]),
+            niss: Some(niss),
+            ..StepConfig::new(StepKind::EO)
+        }]
+    }
+
+    // The scramble rotated by y, and the axis FB becomes
+    fn rotated() -> (Algorithm, &'static str) {
+        let mut scramble = Algorithm::from_str(SCRAMBLE).unwrap();
+        scramble.transform(Transformation333::Y);
+        (scramble, CubeFace::Front.transform(Transformation333::Y).get_axis().name())
+    }
+
+    // Moves on the inverse act as premoves, so they are applied before the scramble
+    fn apply_solution(scramble: &Algorithm, solution: Solution) -> Cube333 {
+        let solution: Algorithm = solution.into();
+        let mut premoves = Algorithm { normal_moves: solution.inverse_moves, inverse_moves: vec![] };
+        premoves.invert();
+        let moves = [premoves.normal_moves, scramble.normal_moves.clone(), solution.normal_moves].concat();
+        (&Algorithm { normal_moves: moves, inverse_moves: vec![] }).into()
+    }
+
+    #[test]
+    fn test_rotated_states_share_key() {
+        let cube: Cube333 = Algorithm::from_str(SCRAMBLE).unwrap().into();
+        let (rotated, axis) = rotated();
+        for niss in [NissSwitchType::Never, NissSwitchType::Always] {
+            let (key, _) = CacheKey::new(&cube, &eo("fb", niss));
+            let (rotated_key, _) = CacheKey::new(&(&rotated).into(), &eo(axis, niss));
+            assert_eq!(key.canonical_scramble_id, rotated_key.canonical_scramble_id);
+            assert_eq!(key.settings, rotated_key.settings);
+            // Solving EO on another axis is a different search
+            let (other_key, _) = CacheKey::new(&(&rotated).into(), &eo("fb", niss));
+            assert_ne!(key.settings, other_key.settings);
+        }
+    }
+
+    #[test]
+    fn test_solutions_are_replayed_in_the_orientation_of_the_request() {
+        let pool = db::test_pool();
+        let cube: Cube333 = Algorithm::from_str(SCRAMBLE).unwrap().into();
+        let (rotated, axis) = rotated();
+
+        for niss in [NissSwitchType::Never, NissSwitchType::Always] {
+            let request = SolveRequest {
+                solution_count: Some(5),
+                ..SolveRequest::new(cube, eo("fb", niss))
+            };
+            let cache = SolutionCache::new(pool.clone(), &cube, &eo("fb", niss), None);
+            for solution in MultiPathChannelSolver::new().solve(request).unwrap() {
+                cache.add_solution(&solution);
+            }
+
+            let replayed = SolutionCache::new(pool.clone(), &(&rotated).into(), &eo(axis, niss), None).solutions();
+            assert_eq!(5, replayed.len());
+            for solution in replayed {
+                let cube = apply_solution(&rotated, solution);
+                let bad_edges = match axis {
+                    "ud" => cube.count_bad_edges_ud(),
+                    "fb" => cube.count_bad_edges_fb(),
+                    _ => cube.count_bad_edges_lr(),
+                };
+                assert_eq!(0, bad_edges);
+            }
+        }
+    }
+
+    #[test]
+    fn test_solutions_are_replayed_for_mirrored_and_inverted_states() {
+        let pool = db::test_pool();
+        let scramble = Algorithm::from_str(SCRAMBLE).unwrap();
+        let cube: Cube333 = (&scramble).into();
+        let request = SolveRequest {
+            solution_count: Some(5),
+            ..SolveRequest::new(cube, eo("fb", NissSwitchType::Always))
+        };
+        let cache = SolutionCache::new(pool.clone(), &cube, &eo("fb", NissSwitchType::Always), None);
+        for solution in MultiPathChannelSolver::new().solve(request).unwrap() {
+            cache.add_solution(&solution);
+        }
+
+        // Mirroring on LR and inverting both keep the EO axis
+        let mut mirrored = scramble.clone();
+        mirrored.mirror(CubeAxis::LR);
+        let mut inverted = scramble.clone();
+        inverted.invert();
+        let mut both = mirrored.clone();
+        both.invert();
+        for scramble in [mirrored, inverted, both] {
+            let replayed = SolutionCache::new(pool.clone(), &(&scramble).into(), &eo("fb", NissSwitchType::Always), None).solutions();
+            assert_eq!(5, replayed.len(), "{scramble}");
+            for solution in replayed {
+                assert_eq!(0, apply_solution(&scramble, solution).count_bad_edges_fb(), "{scramble}");
+            }
+        }
+    }
+}
@@ -0,0 +1,270 @@
+use std::fs;
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+use clap::{Parser, Subcommand};
+use log::LevelFilter;
+use serde::Deserialize;
+
+use crate::scheduler::SchedulerConfig;
+use crate::tables::Table;
+
+/// Every setting can also be given in the config file.
+/// Flags take precedence over environment variables, which take precedence over the config file
+#[derive(Parser, Debug)]
+#[command(about = "Web backend for the cubelib solver")]
+pub struct Args {
+    /// Path to the config file
+    #[arg(long, short, env = "CUBELIB_BACKEND_CONFIG")]
+    pub config: Option<PathBuf>,
+    /// Address to listen on, e.g. 127.0.0.1:8049
+    #[arg(long, env = "CUBELIB_BACKEND_LISTEN")]
+    pub listen: Option<String>,
+    /// Path to the SQLite database
+    #[arg(long, env = "CUBELIB_BACKEND_DATABASE")]
+    pub database: Option<PathBuf>,
+    /// Origins allowed to make cross-origin requests, separated by commas. '*' allows all origins
+    #[arg(long, env = "CUBELIB_BACKEND_CORS_ORIGINS", value_delimiter = ',')]
+    pub cors_origins: Option<Vec<String>>,
+    /// Tables to load at startup, separated by commas. All other tables are loaded when a request first needs them
+    #[arg(long, env = "CUBELIB_BACKEND_PRELOAD_TABLES", value_delimiter = ',')]
+    pub preload_tables: Option<Vec<String>>,
+    /// Solves running at the same time, across all clients
+    #[arg(long, env = "CUBELIB_BACKEND_MAX_CONCURRENT")]
+    pub max_concurrent: Option<usize>,
+    /// Solves running at the same time for one client
+    #[arg(long, env = "CUBELIB_BACKEND_MAX_PER_CLIENT")]
+    pub max_per_client: Option<usize>,
+    /// Requests are rejected once this many are waiting
+    #[arg(long, env = "CUBELIB_BACKEND_MAX_QUEUED")]
+    pub max_queued: Option<usize>,
+    /// Requests of one client are rejected once this many of them are waiting
+    #[arg(long, env = "CUBELIB_BACKEND_MAX_QUEUED_PER_CLIENT")]
+    pub max_queued_per_client: Option<usize>,
+    /// Solves are cancelled after running this many seconds
+    #[arg(long, env = "CUBELIB_BACKEND_TIME_LIMIT")]
+    pub time_limit: Option<u64>,
+    /// One of off, error, warn, info, debug, trace
+    #[arg(long, env = "CUBELIB_BACKEND_LOG")]
+    pub log: Option<String>,
+    /// Token for the admin endpoints. They are disabled without one
+    #[arg(long, env = "CUBELIB_BACKEND_ADMIN_TOKEN", hide_env_values = true)]
+    pub admin_token: Option<String>,
+    /// Jobs and their results are deleted this many seconds after they were submitted
+    #[arg(long, env = "CUBELIB_BACKEND_JOB_EXPIRY")]
+    pub job_expiry: Option<u64>,
+    /// Addresses of reverse proxies, separated by commas. Only their Forwarded and X-Forwarded-For headers are used to identify clients
+    #[arg(long, env = "CUBELIB_BACKEND_TRUSTED_PROXIES", value_delimiter = ',')]
+    pub trusted_proxies: Option<Vec<String>>,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// Without a command the server is started
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Prints statistics over the recorded requests
+    Stats {
+        /// First day to include, YYYY-MM-DD
+        #[arg(long)]
+        from: Option<String>,
+        /// Last day to include, YYYY-MM-DD
+        #[arg(long)]
+        to: Option<String>,
+        /// Number of scrambles and step settings to list
+        #[arg(long, default_value_t = 10)]
+        limit: usize,
+        /// Print the statistics as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    listen: Option<String>,
+    database: Option<PathBuf>,
+    cors_origins: Option<Vec<String>>,
+    preload_tables: Option<Vec<String>>,
+    log: Option<String>,
+    admin_token: Option<String>,
+    job_expiry: Option<u64>,
+    trusted_proxies: Option<Vec<String>>,
+    #[serde(default)]
+    limits: LimitsConfig,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LimitsConfig {
+    max_concurrent: Option<usize>,
+    max_per_client: Option<usize>,
+    max_queued: Option<usize>,
+    max_queued_per_client: Option<usize>,
+    time_limit: Option<u64>,
+}
+
+/// The validated settings of the backend
+#[derive(Debug)]
+pub struct BackendConfig {
+    pub listen: SocketAddr,
+    pub database: PathBuf,
+    /// Empty if all origins are allowed
+    pub cors_origins: Vec<String>,
+    pub preload_tables: Vec<Table>,
+    pub scheduler: SchedulerConfig,
+    pub log: LevelFilter,
+    pub admin_token: Option<String>,
+    pub job_expiry: Duration,
+    /// Empty if the backend isn't run behind a reverse proxy
+    pub trusted_proxies: Vec<IpAddr>,
+}
+
+impl BackendConfig {
+    pub fn load(args: Args) -> Result<Self, String> {
+        let file = match &args.config {
+            Some(path) => {
+                let content = fs::read_to_string(path)
+                    .map_err(|err| format!("Failed to read config file {}. {err}", path.display()))?;
+                toml::from_str::<ConfigFile>(&content)
+                    .map_err(|err| format!("Failed to parse config file {}. {err}", path.display()))?
+            },
+            None => ConfigFile::default(),
+        };
+
+        let listen = args.listen.or(file.listen).unwrap_or_else(|| "127.0.0.1:8049".to_string());
+        let listen = SocketAddr::from_str(&listen)
+            .map_err(|_| format!("Invalid listen address '{listen}'. Expected an address like 127.0.0.1:8049"))?;
+
+        let mut cors_origins = args.cors_origins.or(file.cors_origins).unwrap_or_else(|| vec!["*".to_string()]);
+        cors_origins.retain(|origin| !origin.is_empty());
+        if cors_origins.is_empty() {
+            return Err("At least one CORS origin is required. Use '*' to allow all origins".to_string());
+        }
+        if cors_origins.iter().any(|origin| origin == "*") {
+            cors_origins.clear();
+        } else if let Some(origin) = cors_origins.iter().find(|origin| !origin.starts_with("http://") && !origin.starts_with("https://")) {
+            return Err(format!("Invalid CORS origin '{origin}'. Expected an origin like https://example.com"));
+        }
+
+        let preload_tables = match args.preload_tables.or(file.preload_tables) {
+            Some(names) => names.iter()
+                .filter(|name| !name.is_empty())
+                .map(|name| Table::from_str(name))
+                .collect::<Result<Vec<_>, _>>()?,
+            None => Table::ALL.to_vec(),
+        };
+
+        let defaults = SchedulerConfig::default();
+        let scheduler = SchedulerConfig {
+            max_concurrent: positive("max_concurrent", args.max_concurrent.or(file.limits.max_concurrent))?.unwrap_or(defaults.max_concurrent),
+            max_per_client: positive("max_per_client", args.max_per_client.or(file.limits.max_per_client))?.unwrap_or(defaults.max_per_client),
+            max_queued: positive("max_queued", args.max_queued.or(file.limits.max_queued))?.unwrap_or(defaults.max_queued),
+            max_queued_per_client: positive("max_queued_per_client", args.max_queued_per_client.or(file.limits.max_queued_per_client))?.unwrap_or(defaults.max_queued_per_client),
+            time_limit: positive("time_limit", args.time_limit.or(file.limits.time_limit))?
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.time_limit),
+        };
+
+        let log = args.log.or(file.log).unwrap_or_else(|| "info".to_string());
+        let log = LevelFilter::from_str(&log)
+            .map_err(|_| format!("Invalid log level '{log}'. Expected one of off, error, warn, info, debug, trace"))?;
+
+        let admin_token = args.admin_token.or(file.admin_token);
+        if admin_token.as_ref().is_some_and(|token| token.trim().is_empty()) {
+            return Err("The admin token must not be empty".to_string());
+        }
+
+        let job_expiry = positive("job_expiry", args.job_expiry.or(file.job_expiry))?.unwrap_or(24 * 60 * 60);
+
+        let trusted_proxies = args.trusted_proxies.or(file.trusted_proxies).unwrap_or_default().iter()
+            .filter(|proxy| !proxy.is_empty())
+            .map(|proxy| IpAddr::from_str(proxy).map_err(|_| format!("Invalid trusted proxy '{proxy}'. Expected an IP address like 127.0.0.1")))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            listen,
+            database: args.database.or(file.database).unwrap_or_else(|| PathBuf::from("requests.db")),
+            cors_origins,
+            preload_tables,
+            scheduler,
+            log,
+            admin_token,
+            job_expiry: Duration::from_secs(job_expiry),
+            trusted_proxies,
+        })
+    }
+}
+
+fn positive<T: Default + PartialEq>(name: &str, value: Option<T>) -> Result<Option<T>, String> {
+    match value {
+        Some(value) if value == T::default() => Err(format!("{name} must be greater than 0")),
+        value => Ok(value),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    use clap::Parser;
+
+    use crate::config::{Args, BackendConfig};
+
+    fn load(args: &[&str]) -> Result<BackendConfig, String> {
+        BackendConfig::load(Args::try_parse_from(["cubelib-backend"].iter().chain(args)).unwrap())
+    }
+
+    fn write_config(name: &str, content: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("cubelib-backend-{}-{name}.toml", std::process::id()));
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_invalid_settings() {
+        for (args, error) in [
+            (["--listen", "localhost"], "Invalid listen address 'localhost'"),
+            (["--cors-origins", "example.com"], "Invalid CORS origin 'example.com'"),
+            (["--preload-tables", "eo,xyz"], "Unknown table 'xyz'"),
+            (["--max-concurrent", "0"], "max_concurrent must be greater than 0"),
+            (["--max-queued-per-client", "0"], "max_queued_per_client must be greater than 0"),
+            (["--time-limit", "0"], "time_limit must be greater than 0"),
+        ] {
+            let result = load(&args);
+            assert!(result.as_ref().is_err_and(|err| err.contains(error)), "{args:?}: {result:?}");
+        }
+    }
+
+    #[test]
+    fn test_unknown_key() {
+        let path = write_config("unknown", "[limits]\nmax_queue = 3\n");
+        let result = load(&["--config", path.to_str().unwrap()]);
+        fs::remove_file(&path).unwrap();
+        assert!(result.as_ref().is_err_and(|err| err.contains("unknown field `max_queue`")), "{result:?}");
+    }
+
+    #[test]
+    fn test_precedence() {
+        let path = write_config("precedence", "listen = \"0.0.0.0:80\"\n[limits]\nmax_per_client = 3\nmax_queued = 30\ntime_limit = 10\n");
+        env::set_var("CUBELIB_BACKEND_MAX_PER_CLIENT", "4");
+        env::set_var("CUBELIB_BACKEND_MAX_QUEUED", "40");
+        let result = load(&["--config", path.to_str().unwrap(), "--max-per-client", "5"]);
+        env::remove_var("CUBELIB_BACKEND_MAX_PER_CLIENT");
+        env::remove_var("CUBELIB_BACKEND_MAX_QUEUED");
+        fs::remove_file(&path).unwrap();
+
+        let config = result.unwrap();
+        assert_eq!("0.0.0.0:80", config.listen.to_string());
+        assert_eq!(5, config.scheduler.max_per_client);
+        assert_eq!(40, config.scheduler.max_queued);
+        assert_eq!(Duration::from_secs(10), config.scheduler.time_limit);
+        assert_eq!(PathBuf::from("requests.db"), config.database);
+    }
+}
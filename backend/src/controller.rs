@@ -2,142 +2,258 @@ use std::collections::hash_map::DefaultHasher;
 use std::collections::{HashMap, HashSet};
 use std::convert::Infallible;
 use std::hash::{Hash, Hasher};
-use std::str::FromStr;
+use std::net::IpAddr;
 use std::sync::Arc;
-use std::thread;
 use std::thread::sleep;
 use std::time::Duration;
-use actix_web::{HttpResponse, post, Responder, web, HttpRequest};
+use actix_web::{HttpResponse, get, post, Responder, web, HttpRequest};
 use actix_web::web::Query;
 use actix_web_lab::body;
 use actix_web_lab::body::Sender;
 use cubelib::algs::Algorithm;
 use cubelib::cube::*;
 use cubelib::cube::turn::{ApplyAlgorithm, TransformableMut};
-use cubelib::defs::StepKind;
+use cubelib::defs::{NissSwitchType, StepKind};
+use cubelib::solver::backend::{IterStreamSolver, SolveRequest, Solver, SolverError};
 use cubelib::solver::df_search::CancelToken;
 use cubelib::solver::lookup_table::DepthEstimate;
+use cubelib::solver::scoring::Ranking;
 use cubelib::solver::solution::Solution;
-use cubelib::solver_new::{build_steps, TryRecvError};
+use cubelib::solver_new::backend::MultiPathChannelSolver;
+use cubelib::solver_new::util_cube::CubeState;
 use cubelib::steps::coord::Coord;
 use cubelib::steps::dr::coords::DRUDEOFBCoord;
+use cubelib::steps::eo::coords::BadEdgeCount;
 use cubelib::steps::htr::coords::HTRDRUDCoord;
 use cubelib::steps::htr::subsets::DR_SUBSETS;
-use cubelib::steps::solver;
 use cubelib::steps::step::StepConfig;
 use cubelib::steps::tables::PruningTables333;
-use cubelib_interface::{SolverRequest, SolverResponse};
+use cubelib_interface::{AnalyzeRequest, ApiError, CubeAnalysis, EOAnalysis, HealthResponse, ScrambleResponse, ServerMessage, SolveLimits, SolverBackend, SolverRequest, SolverResponse, TablesResponse, VerifyRequest, VerifyResponse};
 use log::{debug, error, info, trace};
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
 use serde::Deserialize;
 use crate::{AppData, db};
-
-#[derive(Clone, Default, Debug, Deserialize)]
-#[serde(rename_all = "snake_case")]
-pub enum SolverBackend {
-    #[default]
-    IterStream,
-    MultiPathChannel,
-}
+use crate::error::{error_response, internal, parse_scramble, parse_solution};
+use crate::websocket::start_solve;
 
 #[derive(Deserialize)]
 pub struct SolveStreamParameters {
     #[serde(default)]
     backend: SolverBackend,
+    /// Solutions are only sent if they rank better than the previous one. By default they have to be shorter
+    rank_by: Option<String>,
 }
 
 #[post("/solve_stream")]
-pub async fn solve_stream(req: HttpRequest, steps: web::Json<SolverRequest>, app_data: web::Data<AppData>, params: Query<SolveStreamParameters>) -> impl Responder {
-    // let mut hasher = DefaultHasher::new();
-    // let peer = req.peer_addr().unwrap();
-    // peer.ip().to_string().hash(&mut hasher);
-    // serde_json::to_string(&steps).unwrap().hash(&mut hasher);
-    // let hash = hasher.finish();
-    // let last = app_data.debounce_cache.get_with(hash, ||peer);
-    // if last.ip() == peer.ip() && last.port() != peer.port() {
-    //     debug!("Debounce kill");
-    //     return HttpResponse::ServiceUnavailable().finish()
-    // }
-
-    let SolverRequest{ steps, scramble } = steps.0;
-    let scramble = Algorithm::from_str(scramble.as_str()).unwrap();
-    let conn = app_data.pool.get();
-
-    match conn {
-        Ok(conn) => {
-            if let Err(err) = db::record_request(&conn, &scramble, &steps, app_data.pruning_tables.as_ref()) {
-                error!("{err}");
-                return HttpResponse::InternalServerError().finish()
+pub async fn solve_stream(req: HttpRequest, request: web::Json<SolverRequest>, app_data: web::Data<AppData>, params: Query<SolveStreamParameters>) -> impl Responder {
+    let (mut body_tx, body) = body::channel::<std::convert::Infallible>();
+    let mut keepalive_tx = body_tx.clone();
+    // Stops the keepalive once the response is complete
+    let finished = Arc::new(CancelToken::default());
+    let response_finished = finished.clone();
+    let params = params.into_inner();
+    let started = start_solve(0, request.into_inner(), params.backend, params.rank_by, SolveLimits::default(), "stream", &client_address(&req, &app_data.trusted_proxies), &app_data, move |message| {
+        let response = match message {
+            ServerMessage::Solution { solution, .. } => SolverResponse { solution: Some(solution), done: false, queue_position: None, error: None },
+            ServerMessage::Queued { position, .. } => SolverResponse { solution: None, done: false, queue_position: Some(position), error: None },
+            ServerMessage::Error { error, .. } => SolverResponse { solution: None, done: false, queue_position: None, error: Some(error) },
+            ServerMessage::Done { .. } => {
+                response_finished.cancel();
+                SolverResponse { solution: None, done: true, queue_position: None, error: None }
+            },
+            // Only tells us whether the stream is still open
+            ServerMessage::Started { .. } | ServerMessage::Progress { .. } => return body_tx.send(web::Bytes::from_static(b" ")).is_ok(),
+        };
+        send_response(&mut body_tx, &response)
+    });
+    let solve = match started {
+        Ok(solve) => solve,
+        Err(err) => return error_response(err),
+    };
+
+    // Rounds can take a while without any message, so closed streams are noticed by sending spaces in between
+    let _ = web::block(move || {
+        while !finished.is_cancelled() && !solve.cancel_token.is_cancelled() {
+            sleep(Duration::from_secs(1));
+            trace!("Sending keepalive");
+            if keepalive_tx.send(web::Bytes::from_static(b" ")).is_err() {
+                info!("Stream closed, cancelling");
+                solve.cancel_token.cancel();
             }
-        },
-        Err(err) => {
-            error!("{err}");
-            return HttpResponse::InternalServerError().finish()
+        }
+    });
+
+    HttpResponse::Ok().body(body)
+}
+
+// Returns false if the stream is closed
+fn send_response(tx: &mut Sender<Infallible>, response: &SolverResponse) -> bool {
+    let data = web::Bytes::from(serde_json::to_string(response).unwrap());
+    tx.send(data).is_ok() && tx.send(web::Bytes::from_static(b"\n")).is_ok()
+}
+
+/// The address of the client. The `Forwarded` headers are only used for requests from a trusted proxy, anyone else could make them up
+pub fn client_address(req: &HttpRequest, trusted_proxies: &[IpAddr]) -> String {
+    let Some(peer) = req.peer_addr() else {
+        return "unknown".to_string();
+    };
+    if trusted_proxies.contains(&peer.ip()) {
+        if let Some(forwarded) = req.connection_info().realip_remote_addr() {
+            return forwarded.to_string();
         }
     }
+    peer.ip().to_string()
+}
 
-    info!("Streaming solve request for {scramble} using backend {:?}", params.backend);
+#[post("/analyze")]
+pub async fn analyze(request: web::Json<AnalyzeRequest>) -> impl Responder {
+    let scramble = match parse_scramble(request.scramble.as_str()) {
+        Ok(scramble) => scramble,
+        Err(err) => return error_response(err),
+    };
+    let mut cube = Cube333::default();
+    cube.apply_alg(&scramble);
+    HttpResponse::Ok().json(analyze_cube(&cube))
+}
 
+#[post("/verify")]
+pub async fn verify(request: web::Json<VerifyRequest>) -> impl Responder {
+    let parsed = parse_scramble(request.scramble.as_str())
+        .and_then(|scramble| parse_solution(request.solution.as_str()).map(|solution| (scramble, solution)));
+    let (scramble, solution) = match parsed {
+        Ok(parsed) => parsed,
+        Err(err) => return error_response(err),
+    };
     let mut cube = Cube333::default();
     cube.apply_alg(&scramble);
+    cube.apply_alg(&solution);
+    HttpResponse::Ok().json(VerifyResponse {
+        solved: cube == Cube333::default(),
+        length: solution.len(),
+        analysis: analyze_cube(&cube),
+    })
+}
 
-    let cancel_token = Arc::new(CancelToken::default());
+fn analyze_cube(cube: &Cube333) -> CubeAnalysis {
+    let state = cube.get_cube_state();
+    let dr_axes = match &state {
+        CubeState::Scrambled | CubeState::EO(_) => vec![],
+        CubeState::DR(axis) => vec![axis.name().to_string()],
+        _ => vec![CubeAxis::UD, CubeAxis::FB, CubeAxis::LR].iter().map(|axis| axis.name().to_string()).collect(),
+    };
+    let name = match state {
+        CubeState::Scrambled => "scrambled",
+        CubeState::EO(_) => "eo",
+        CubeState::DR(_) => "dr",
+        CubeState::TripleDR => "triple-dr",
+        CubeState::HTR => "htr",
+        CubeState::FR(_) => "fr",
+        CubeState::Solved => "solved",
+    };
+    let eo = [(CubeAxis::UD, cube.count_bad_edges_ud()), (CubeAxis::FB, cube.count_bad_edges_fb()), (CubeAxis::LR, cube.count_bad_edges_lr())].into_iter()
+        .map(|(axis, bad_edges)| EOAnalysis { axis: axis.name().to_string(), bad_edges })
+        .collect();
+    CubeAnalysis {
+        state: name.to_string(),
+        eo,
+        dr_axes,
+        dr_subset: cube.get_dr_subset().map(|subset| subset.to_string()),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ScrambleParameters {
+    #[serde(default = "default_scramble_count")]
+    count: usize,
+}
 
-    let solutions: Box<dyn Iterator<Item = Solution> + Send> = match params.backend {
-        SolverBackend::IterStream => Box::new(solve_steps_quality_doubling(cube, steps.clone(), app_data.pruning_tables.clone(), cancel_token.clone())),
-        SolverBackend::MultiPathChannel => Box::new(solve_steps_quality_doubling_mpc(cube, steps.clone(), cancel_token.clone())),
+fn default_scramble_count() -> usize {
+    1
+}
+
+const MAX_SCRAMBLE_COUNT: usize = 10;
+
+#[get("/scramble")]
+pub async fn generate_scramble(req: HttpRequest, app_data: web::Data<AppData>, params: Query<ScrambleParameters>) -> impl Responder {
+    if params.count == 0 || params.count > MAX_SCRAMBLE_COUNT {
+        return error_response(ApiError::InvalidRequest { message: format!("Scramble count must be between 1 and {MAX_SCRAMBLE_COUNT}") });
+    }
+    let count = params.count;
+    // Every scramble is a solve, so they count against the same limits
+    let cancel_token = Arc::new(CancelToken::default());
+    let mut slot = match app_data.scheduler.enqueue(client_address(&req, &app_data.trusted_proxies), cancel_token.clone()) {
+        Ok(slot) => slot,
+        Err(message) => return error_response(ApiError::Overloaded { message }),
     };
+    let ct = cancel_token.clone();
+    let scrambles = web::block(move || {
+        let tables = app_data.tables.for_steps(&scramble_steps());
+        if !slot.wait_for_start(|_| {}) {
+            return None;
+        }
+        (0..count)
+            .map(|_| random_state_scramble(tables.clone(), ct.clone()))
+            .collect::<Option<Vec<String>>>()
+    })
+        .await;
+    match scrambles {
+        Ok(Some(scrambles)) => HttpResponse::Ok().json(ScrambleResponse { scrambles }),
+        Ok(None) if cancel_token.is_cancelled() => error_response(ApiError::Overloaded { message: "Generating the scrambles took too long, try again later".to_string() }),
+        Ok(None) => error_response(ApiError::Internal),
+        Err(err) => error_response(internal(err)),
+    }
+}
 
-    let (mut body_tx, body) = body::channel::<std::convert::Infallible>();
+fn scramble_steps() -> Vec<StepConfig> {
+    [StepKind::EO, StepKind::DR, StepKind::HTR, StepKind::FIN].into_iter()
+        .map(|kind| StepConfig {
+            max: (kind == StepKind::EO).then_some(7),
+            niss: Some(NissSwitchType::Never),
+            ..StepConfig::new(kind)
+        })
+        .collect()
+}
 
-    let ct1 = cancel_token.clone();
-    let _ = web::block(move || {
-        let mut keepalive_tx = body_tx.clone();
-        let cancel_token = ct1.clone();
-        let _ = web::block(move || {
-            while !ct1.is_cancelled() {
-                sleep(Duration::from_secs(1));
-                trace!("Sending keepalive");
-                if let Err(_) = keepalive_tx.send(web::Bytes::from_static(b" ")) {
-                    info!("Stream closed, cancelling");
-                    ct1.cancel();
-                }
-            }
-        });
-        let mut previous_length = usize::MAX;
-        for mut sol in solutions {
-            if cancel_token.is_cancelled() {
-                break;
-            }
-            if sol.len() < previous_length {
-                previous_length = sol.len();
-            } else {
-                continue;
-            }
-            add_comments(cube.clone(), &mut sol, app_data.pruning_tables.as_ref());
-            let resp = SolverResponse { solution: Some(sol), done: false };
-            let data = web::Bytes::from(serde_json::to_string(&resp).unwrap());
-            if let Err(_) = body_tx.send(data) {
-                break;
-            }
-            if let Err(_) = body_tx.send(web::Bytes::from_static(b"\n")) {
-                break;
-            }
+// The solution of a random state is a random state scramble as well
+fn random_state_scramble(tables: Arc<PruningTables333>, cancel_token: Arc<CancelToken>) -> Option<String> {
+    let cube = Cube333::random(&mut rand::rng());
+    let request = SolveRequest {
+        quality: 1000,
+        solution_count: Some(1),
+        cancel_token,
+        ..SolveRequest::new(cube, scramble_steps())
+    };
+    match IterStreamSolver::new(tables).solve(request) {
+        Ok(mut solutions) => solutions.next().map(|solution| Into::<Algorithm>::into(solution).to_string()),
+        Err(err) => {
+            error!("{err}");
+            None
         }
-        cancel_token.cancel();
-        let data = web::Bytes::from(serde_json::to_string(&SolverResponse { solution: None, done: true }).unwrap());
-        let _ = body_tx.send(data);
-        let _ = body_tx.send(web::Bytes::from_static(b"\n"));
-    });
+    }
+}
 
-    actix_rt::spawn(async move {
-        actix_rt::time::sleep(Duration::from_secs(60)).await;
-        cancel_token.as_ref().cancel();
-    });
+#[get("/tables")]
+pub async fn list_tables(app_data: web::Data<AppData>) -> impl Responder {
+    HttpResponse::Ok().json(TablesResponse { tables: app_data.tables.status() })
+}
 
-    HttpResponse::Ok().body(body)
+/// Returns 503 until the database is reachable and all preloaded pruning tables are loaded
+#[get("/health")]
+pub async fn health(app_data: web::Data<AppData>) -> impl Responder {
+    let database = app_data.pool.get()
+        .map(|conn| conn.execute_batch("SELECT 1").is_ok())
+        .unwrap_or(false);
+    let tables = app_data.tables.preloaded();
+    let response = HealthResponse { ready: database && tables, database, tables };
+    if response.ready {
+        HttpResponse::Ok().json(response)
+    } else {
+        HttpResponse::ServiceUnavailable().json(response)
+    }
 }
 
-fn add_comments(mut cube: Cube333, solution: &mut Solution, tables: &PruningTables333) {
+pub fn add_comments(mut cube: Cube333, solution: &mut Solution, tables: &PruningTables333) {
     for step in solution.steps.iter_mut() {
         cube.apply_alg(&step.alg);
         match Into::<StepKind>::into(step.variant) {
@@ -159,49 +275,203 @@ fn add_comments(mut cube: Cube333, solution: &mut Solution, tables: &PruningTabl
     }
 }
 
-pub fn solve_steps_quality_doubling<'a>(puzzle: Cube333, steps: Vec<StepConfig>, tables: Arc<PruningTables333>, cancel_token: Arc<CancelToken>) -> impl Iterator<Item = Solution> {
-    let t1 = tables.clone();
+pub fn create_solver(backend: SolverBackend, tables: Arc<PruningTables333>) -> Box<dyn Solver + Send> {
+    match backend {
+        SolverBackend::IterStream => Box::new(IterStreamSolver::new(tables)),
+        SolverBackend::MultiPathChannel => Box::new(MultiPathChannelSolver::new()),
+    }
+}
+
+/// Only lets solutions through that rank better than all previous ones. Without a ranking they have to be shorter
+pub struct BestSolution {
+    cube: Cube333,
+    ranking: Option<Arc<Ranking>>,
+    previous: Option<(Vec<f64>, usize)>,
+}
+
+impl BestSolution {
+    pub fn new(cube: Cube333, ranking: Option<Arc<Ranking>>) -> Self {
+        Self {
+            cube,
+            ranking,
+            previous: None,
+        }
+    }
+
+    pub fn improves(&mut self, solution: &Solution) -> bool {
+        let scores = self.ranking.as_ref().map(|r| r.scores(&self.cube, solution)).unwrap_or_default();
+        let improves = self.previous.as_ref().is_none_or(|(previous_scores, previous_length)| {
+            Ranking::compare(&scores, previous_scores).then(solution.len().cmp(previous_length)).is_lt()
+        });
+        if improves {
+            self.previous = Some((scores, solution.len()));
+        }
+        improves
+    }
+}
+
+/// Remembers the shortest solution found for a request. Its length is stored for the statistics once the request is done
+pub struct RequestRecord {
+    pool: Pool<SqliteConnectionManager>,
+    request_id: i64,
+    shortest: Option<usize>,
+}
+
+impl RequestRecord {
+    pub fn new(pool: Pool<SqliteConnectionManager>, request_id: i64) -> Self {
+        Self {
+            pool,
+            request_id,
+            shortest: None,
+        }
+    }
+
+    pub fn add_solution(&mut self, solution: &Solution) {
+        self.shortest = Some(self.shortest.map_or(solution.len(), |shortest| shortest.min(solution.len())));
+    }
+
+    pub fn finish(self) {
+        let Some(length) = self.shortest else {
+            return;
+        };
+        let recorded = self.pool.get()
+            .map_err(|err| err.to_string())
+            .and_then(|conn| db::record_solution_length(&conn, self.request_id, length).map_err(|err| err.to_string()));
+        if let Err(err) = recorded {
+            error!("Failed to record solution length. {err}");
+        }
+    }
+}
+
+/// Searches with increasing quality, starting at `first_quality`, and returns the best solution of every round together with its quality.
+/// `max_length` is read before every round, so it can change during the search. Without it, a ranking only picks the best of the
+/// shortest solutions, as a round otherwise wouldn't end. Stops after the first error
+pub fn solve_rounds_quality_doubling<F: Fn() -> Option<usize>>(solver: Box<dyn Solver + Send>, puzzle: Cube333, steps: Vec<StepConfig>, ranking: Option<Arc<Ranking>>, first_quality: usize, max_length: F, cancel_token: Arc<CancelToken>) -> impl Iterator<Item = Result<(usize, Option<Solution>), SolverError>> {
     (5..20usize).into_iter()
         .map(|q| 2u32.pow(q as u32) as usize)
-        .flat_map(move |quality| {
+        .filter(move |quality| *quality >= first_quality)
+        .scan(false, move |failed, quality| {
+            if *failed || cancel_token.is_cancelled() {
+                return None;
+            }
             let mut steps = steps.clone();
             for x in &mut steps {
-                x.quality = quality;
                 x.step_limit = None;
             }
-            let tables = t1.as_ref();
-            let steps = solver::build_steps(steps, &tables).unwrap();
-            let best = cubelib::solver::solve_steps(puzzle, &steps, cancel_token.as_ref()).next();
-            best
+            let request = SolveRequest {
+                quality,
+                max_length: max_length(),
+                solution_count: Some(1),
+                all_solutions: true,
+                cancel_token: cancel_token.clone(),
+                ranking: ranking.clone(),
+                ..SolveRequest::new(puzzle, steps)
+            };
+            match solver.solve(request) {
+                Ok(mut solutions) => Some(Ok((quality, solutions.next()))),
+                Err(err) => {
+                    *failed = true;
+                    Some(Err(err))
+                }
+            }
         })
 }
 
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
 
-pub fn solve_steps_quality_doubling_mpc<'a>(puzzle: Cube333, steps: Vec<StepConfig>, cancel_token: Arc<CancelToken>) -> impl Iterator<Item = Solution> {
-    (5..20usize).into_iter()
-        .map(|q| 2u32.pow(q as u32) as usize)
-        .flat_map(move |quality| {
-            if cancel_token.is_cancelled() {
-                return None;
-            }
-            let steps = steps.clone();
-            let mut steps = cubelib::solver_new::build_steps(steps).unwrap();
-            steps.apply_step_limit(quality);
-            let mut worker = steps.into_worker(puzzle);
-            while !cancel_token.is_cancelled() {
-                match worker.try_next() {
-                    Ok(sol) => {
-                        return Some(sol)
-                    },
-                    Err(TryRecvError::Disconnected) => {
-                        return None
-                    },
-                    Err(_) => {
-                        thread::sleep(Duration::from_millis(50));
-                        continue;
-                    },
-                };
-            }
-            None
-        })
-}
\ No newline at end of file
+    use actix_web::{web, App};
+    use actix_web::test::{call_and_read_body_json, call_service, init_service, TestRequest};
+    use actix_web::http::StatusCode;
+    use cubelib::algs::Algorithm;
+    use cubelib::cube::Cube333;
+    use cubelib::cube::turn::ApplyAlgorithm;
+    use cubelib_interface::{CubeAnalysis, EOAnalysis, HealthResponse, VerifyRequest, VerifyResponse};
+
+    use crate::controller::{analyze_cube, generate_scramble, health, verify};
+    use crate::scheduler::SchedulerConfig;
+
+    fn analyze(scramble: &str) -> CubeAnalysis {
+        let mut cube = Cube333::default();
+        cube.apply_alg(&Algorithm::from_str(scramble).unwrap());
+        analyze_cube(&cube)
+    }
+
+    fn bad_edges(analysis: &CubeAnalysis) -> Vec<(&str, u32)> {
+        analysis.eo.iter()
+            .map(|EOAnalysis { axis, bad_edges }| (axis.as_str(), *bad_edges))
+            .collect()
+    }
+
+    #[test]
+    fn test_analyze_cube() {
+        let solved = analyze("");
+        assert_eq!("solved", solved.state);
+        assert_eq!(vec!["ud", "fb", "lr"], solved.dr_axes);
+        assert_eq!(vec![("ud", 0), ("fb", 0), ("lr", 0)], bad_edges(&solved));
+
+        // R keeps EO on UD and FB, and DR on LR
+        let dr = analyze("R");
+        assert_eq!("dr", dr.state);
+        assert_eq!(vec!["lr"], dr.dr_axes);
+        assert_eq!(vec![("ud", 0), ("fb", 0), ("lr", 4)], bad_edges(&dr));
+        assert!(dr.dr_subset.is_some());
+
+        let eo = analyze("R U");
+        assert_eq!("eo", eo.state);
+        assert!(eo.dr_axes.is_empty());
+        assert_eq!(vec![("ud", 4), ("fb", 0), ("lr", 4)], bad_edges(&eo));
+        assert_eq!(None, eo.dr_subset);
+
+        let scrambled = analyze("R U F");
+        assert_eq!("scrambled", scrambled.state);
+        assert!(scrambled.dr_axes.is_empty());
+        assert!(bad_edges(&scrambled).iter().all(|(_, bad_edges)| *bad_edges > 0));
+    }
+
+    #[actix_web::test]
+    async fn test_verify() {
+        let app = init_service(App::new().service(verify)).await;
+        let request = |solution: &str| TestRequest::post()
+            .uri("/verify")
+            .set_json(VerifyRequest { scramble: "R U F".to_string(), solution: solution.to_string() })
+            .to_request();
+
+        let response: VerifyResponse = call_and_read_body_json(&app, request("F' U' R'")).await;
+        assert!(response.solved);
+        assert_eq!(3, response.length);
+        assert_eq!("solved", response.analysis.state);
+
+        // Moves on inverse act like premoves, so this leaves U
+        let response: VerifyResponse = call_and_read_body_json(&app, request("F' (R)")).await;
+        assert!(!response.solved);
+        assert_eq!(2, response.length);
+        assert_eq!(analyze("U"), response.analysis);
+
+        let response = call_service(&app, request("F' X")).await;
+        assert_eq!(StatusCode::BAD_REQUEST, response.status());
+    }
+
+    #[actix_web::test]
+    async fn test_health() {
+        let app_data = web::Data::new(crate::test_app_data(SchedulerConfig::default()));
+        let app = init_service(App::new().app_data(app_data).service(health)).await;
+        let request = TestRequest::get().uri("/health").to_request();
+        let response: HealthResponse = call_and_read_body_json(&app, request).await;
+        assert_eq!(HealthResponse { ready: true, database: true, tables: true }, response);
+    }
+
+    #[actix_web::test]
+    async fn test_scramble_limits() {
+        let app_data = web::Data::new(crate::test_app_data(SchedulerConfig {
+            max_queued: 0,
+            ..SchedulerConfig::default()
+        }));
+        let app = init_service(App::new().app_data(app_data).service(generate_scramble)).await;
+        for (uri, status) in [("/scramble?count=0", StatusCode::BAD_REQUEST), ("/scramble?count=11", StatusCode::BAD_REQUEST), ("/scramble", StatusCode::SERVICE_UNAVAILABLE)] {
+            let request = TestRequest::get().uri(uri).peer_addr("127.0.0.1:1234".parse().unwrap()).to_request();
+            assert_eq!(status, call_service(&app, request).await.status(), "{uri}");
+        }
+    }
+}
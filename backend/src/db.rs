@@ -3,14 +3,24 @@ use std::time::SystemTime;
 use base64::Engine;
 use cubelib::algs::Algorithm;
 use cubelib::cube::Cube333;
-use cubelib::cube::turn::{ApplyAlgorithm, InvertibleMut};
-use cubelib::steps::tables::PruningTables333;
+use cubelib::cube::turn::ApplyAlgorithm;
+use cubelib::solver::solution::Solution;
 use cubelib::steps::step::StepConfig;
+use cubelib_interface::{ApiError, JobRequest, JobState, JobStatus};
+use rusqlite::OptionalExtension;
+
+use crate::cache::CacheKey;
 
 pub type Connection = r2d2::PooledConnection<r2d2_sqlite::SqliteConnectionManager>;
 
-pub fn record_request(conn: &Connection, scramble: &Algorithm, step_configs: &Vec<StepConfig>, _: &PruningTables333) -> rusqlite::Result<()> {
+/// Returns the id of the request
+pub fn record_request(conn: &Connection, scramble: &Algorithm, step_configs: &Vec<StepConfig>) -> rusqlite::Result<i64> {
     let time = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).expect("System time before unix epoch").as_secs();
+    record_request_at(conn, scramble, step_configs, time)
+}
+
+/// Like [record_request], with the time in seconds since the unix epoch
+pub fn record_request_at(conn: &Connection, scramble: &Algorithm, step_configs: &Vec<StepConfig>, time: u64) -> rusqlite::Result<i64> {
     let canonical = get_canonical_scramble_id(scramble);
     let encoded_steps = base64::engine::general_purpose::STANDARD.encode(serde_json::to_string(step_configs).unwrap());
     conn.execute(
@@ -21,20 +31,24 @@ pub fn record_request(conn: &Connection, scramble: &Algorithm, step_configs: &Ve
             canonical,
             encoded_steps
         )
-    ).map(|_|())
+    ).map(|_|conn.last_insert_rowid())
+}
+
+pub fn record_solution_length(conn: &Connection, request_id: i64, length: usize) -> rusqlite::Result<()> {
+    conn.execute("UPDATE requests SET solution_length = ? WHERE id = ?", (length, request_id)).map(|_|())
 }
 
 fn get_canonical_scramble_id(scramble: &Algorithm) -> String {
     let mut cube = Cube333::default();
     cube.apply_alg(scramble);
-    let normal_encoded = serialize_cube_to_base64(&cube);
-    cube.invert();
-    let inverse_encoded = serialize_cube_to_base64(&cube);
-    std::cmp::min(normal_encoded, inverse_encoded)
+    let (canonical, _) = cube.canonical();
+    serialize_cube_to_base64(&canonical)
 }
 
 pub fn init_db(conn: &Connection) -> rusqlite::Result<usize> {
-    create_requests_table(&conn)
+    create_requests_table(&conn)?;
+    create_cache_tables(&conn)?;
+    create_job_tables(&conn)
 }
 
 fn create_requests_table(conn: &Connection) -> rusqlite::Result<usize> {
@@ -49,14 +63,369 @@ fn create_requests_table(conn: &Connection) -> rusqlite::Result<usize> {
     ", [])?;
     conn.execute("CREATE INDEX IF NOT EXISTS id ON requests (scramble)", [])?;
     conn.execute("CREATE INDEX IF NOT EXISTS id ON requests (canonical_scramble_id)", [])?;
-    conn.execute("CREATE INDEX IF NOT EXISTS id ON requests (canonical_scramble_inv_id)", [])
+    conn.execute("CREATE INDEX IF NOT EXISTS id ON requests (canonical_scramble_inv_id)", [])?;
+    // Databases created before solution lengths were recorded don't have the column yet
+    if conn.prepare("SELECT solution_length FROM requests LIMIT 0").is_err() {
+        conn.execute("ALTER TABLE requests ADD COLUMN solution_length INTEGER", [])?;
+    }
+    Ok(0)
+}
+
+fn create_cache_tables(conn: &Connection) -> rusqlite::Result<usize> {
+    // Older caches stored solutions in the orientation of each request. It's only a cache, so we start over
+    if conn.prepare("SELECT transformation FROM cached_solutions LIMIT 0").is_ok() {
+        conn.execute("DROP TABLE cached_solutions", [])?;
+        conn.execute("DROP TABLE cached_searches", [])?;
+    }
+    conn.execute("
+        CREATE TABLE IF NOT EXISTS cached_solutions (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            canonical_scramble_id TEXT NOT NULL,
+            settings TEXT NOT NULL,
+            solution TEXT NOT NULL,
+            length INTEGER NOT NULL,
+            UNIQUE (canonical_scramble_id, settings, solution)
+        )
+    ", [])?;
+    // The highest quality that was fully searched, so later requests can continue from there
+    conn.execute("
+        CREATE TABLE IF NOT EXISTS cached_searches (
+            canonical_scramble_id TEXT NOT NULL,
+            settings TEXT NOT NULL,
+            ranking TEXT NOT NULL,
+            quality INTEGER NOT NULL,
+            PRIMARY KEY (canonical_scramble_id, settings, ranking)
+        )
+    ", [])
+}
+
+fn create_job_tables(conn: &Connection) -> rusqlite::Result<usize> {
+    conn.execute("
+        CREATE TABLE IF NOT EXISTS jobs (
+            id INTEGER PRIMARY KEY,
+            client TEXT NOT NULL,
+            request TEXT NOT NULL,
+            state TEXT NOT NULL,
+            queue_position INTEGER,
+            quality INTEGER,
+            error TEXT,
+            created INTEGER NOT NULL,
+            expires INTEGER NOT NULL
+        )
+    ", [])?;
+    conn.execute("
+        CREATE TABLE IF NOT EXISTS job_solutions (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            job_id INTEGER NOT NULL,
+            solution TEXT NOT NULL
+        )
+    ", [])?;
+    conn.execute("CREATE INDEX IF NOT EXISTS job_solutions_job_id ON job_solutions (job_id)", [])
+}
+
+/// Shortest first
+pub fn get_cached_solutions(conn: &Connection, key: &CacheKey) -> rusqlite::Result<Vec<Solution>> {
+    let mut statement = conn.prepare("
+        SELECT solution FROM cached_solutions
+        WHERE canonical_scramble_id = ? AND settings = ?
+        ORDER BY length, id
+    ")?;
+    let solutions = statement.query_map((&key.canonical_scramble_id, &key.settings), |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<String>>>()?;
+    Ok(solutions.iter()
+        .filter_map(|solution| serde_json::from_str(solution).ok())
+        .collect())
+}
+
+pub fn cache_solution(conn: &Connection, key: &CacheKey, solution: &Solution) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT OR IGNORE INTO cached_solutions (id, canonical_scramble_id, settings, solution, length) VALUES (NULL, ?, ?, ?, ?)",
+        (
+            &key.canonical_scramble_id,
+            &key.settings,
+            serde_json::to_string(solution).unwrap(),
+            solution.len(),
+        )
+    ).map(|_|())
+}
+
+pub fn get_cached_quality(conn: &Connection, key: &CacheKey, ranking: &str) -> rusqlite::Result<Option<usize>> {
+    conn.query_row(
+        "SELECT quality FROM cached_searches WHERE canonical_scramble_id = ? AND settings = ? AND ranking = ?",
+        (&key.canonical_scramble_id, &key.settings, ranking),
+        |row| row.get(0)
+    ).optional()
+}
+
+pub fn cache_quality(conn: &Connection, key: &CacheKey, ranking: &str, quality: usize) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT INTO cached_searches (canonical_scramble_id, settings, ranking, quality) VALUES (?, ?, ?, ?)
+        ON CONFLICT DO UPDATE SET quality = max(quality, excluded.quality)",
+        (&key.canonical_scramble_id, &key.settings, ranking, quality)
+    ).map(|_|())
+}
+
+pub fn insert_job(conn: &Connection, id: u64, client: &str, request: &JobRequest, created: u64, expires: u64) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT INTO jobs (id, client, request, state, created, expires) VALUES (?, ?, ?, ?, ?, ?)",
+        (id, client, serde_json::to_string(request).unwrap(), job_state_name(JobState::Queued), created, expires)
+    ).map(|_|())
+}
+
+pub fn get_job(conn: &Connection, id: u64) -> rusqlite::Result<Option<JobStatus>> {
+    let job = conn.query_row(
+        "SELECT state, queue_position, quality, error, created, expires FROM jobs WHERE id = ?",
+        [id],
+        |row| Ok((row.get::<_, String>(0)?, row.get(1)?, row.get(2)?, row.get::<_, Option<String>>(3)?, row.get(4)?, row.get(5)?))
+    ).optional()?;
+    let Some((state, queue_position, quality, error, created, expires)) = job else {
+        return Ok(None);
+    };
+    let mut statement = conn.prepare("SELECT solution FROM job_solutions WHERE job_id = ? ORDER BY id")?;
+    let solutions = statement.query_map([id], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<String>>>()?;
+    Ok(Some(JobStatus {
+        id,
+        state: parse_job_state(&state),
+        queue_position,
+        quality,
+        solutions: solutions.iter()
+            .filter_map(|solution| serde_json::from_str(solution).ok())
+            .collect(),
+        error: error.and_then(|error| serde_json::from_str(&error).ok()),
+        created,
+        expires,
+    }))
+}
+
+/// Returns the id, client and request of jobs that were queued or running
+pub fn get_unfinished_jobs(conn: &Connection) -> rusqlite::Result<Vec<(u64, String, Option<JobRequest>)>> {
+    let mut statement = conn.prepare("SELECT id, client, request FROM jobs WHERE state IN (?, ?)")?;
+    let jobs = statement.query_map([job_state_name(JobState::Queued), job_state_name(JobState::Running)], |row| {
+        Ok((row.get(0)?, row.get(1)?, serde_json::from_str(&row.get::<_, String>(2)?).ok()))
+    })?.collect();
+    jobs
+}
+
+pub fn set_job_state(conn: &Connection, id: u64, state: JobState, error: Option<&ApiError>) -> rusqlite::Result<()> {
+    conn.execute(
+        "UPDATE jobs SET state = ?, queue_position = NULL, error = ? WHERE id = ?",
+        (job_state_name(state), error.map(|error| serde_json::to_string(error).unwrap()), id)
+    ).map(|_|())
 }
 
-fn serialize_cube_to_base64(cube: &Cube333) -> String {
+pub fn set_job_queue_position(conn: &Connection, id: u64, position: usize) -> rusqlite::Result<()> {
+    conn.execute("UPDATE jobs SET queue_position = ? WHERE id = ?", (position, id)).map(|_|())
+}
+
+pub fn set_job_quality(conn: &Connection, id: u64, quality: usize) -> rusqlite::Result<()> {
+    conn.execute("UPDATE jobs SET quality = ? WHERE id = ?", (quality, id)).map(|_|())
+}
+
+pub fn add_job_solution(conn: &Connection, id: u64, solution: &Solution) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT INTO job_solutions (id, job_id, solution) VALUES (NULL, ?, ?)",
+        (id, serde_json::to_string(solution).unwrap())
+    ).map(|_|())
+}
+
+pub fn clear_job_solutions(conn: &Connection, id: u64) -> rusqlite::Result<()> {
+    conn.execute("DELETE FROM job_solutions WHERE job_id = ?", [id]).map(|_|())
+}
+
+pub fn delete_job(conn: &Connection, id: u64) -> rusqlite::Result<()> {
+    conn.execute("DELETE FROM job_solutions WHERE job_id = ?", [id])?;
+    conn.execute("DELETE FROM jobs WHERE id = ?", [id]).map(|_|())
+}
+
+/// Returns the ids of the deleted jobs
+pub fn delete_expired_jobs(conn: &Connection, now: u64) -> rusqlite::Result<Vec<u64>> {
+    let mut statement = conn.prepare("DELETE FROM jobs WHERE expires <= ? RETURNING id")?;
+    let ids = statement.query_map([now], |row| row.get(0))?
+        .collect::<rusqlite::Result<Vec<u64>>>()?;
+    conn.execute("DELETE FROM job_solutions WHERE job_id NOT IN (SELECT id FROM jobs)", [])?;
+    Ok(ids)
+}
+
+fn job_state_name(state: JobState) -> &'static str {
+    match state {
+        JobState::Queued => "queued",
+        JobState::Running => "running",
+        JobState::Done => "done",
+        JobState::Cancelled => "cancelled",
+        JobState::Failed => "failed",
+    }
+}
+
+fn parse_job_state(state: &str) -> JobState {
+    match state {
+        "queued" => JobState::Queued,
+        "running" => JobState::Running,
+        "done" => JobState::Done,
+        "cancelled" => JobState::Cancelled,
+        _ => JobState::Failed,
+    }
+}
+
+/// Dates are YYYY-MM-DD in UTC, both ends are inclusive
+pub struct DateRange {
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+const DATE_FILTER: &str = "(?1 IS NULL OR date(timestamp, 'unixepoch') >= ?1) AND (?2 IS NULL OR date(timestamp, 'unixepoch') <= ?2)";
+
+pub fn count_requests(conn: &Connection, range: &DateRange) -> rusqlite::Result<usize> {
+    conn.query_row(
+        &format!("SELECT COUNT(*) FROM requests WHERE {DATE_FILTER}"),
+        (&range.from, &range.to),
+        |row| row.get(0)
+    )
+}
+
+pub fn count_requests_per_day(conn: &Connection, range: &DateRange) -> rusqlite::Result<Vec<(String, usize)>> {
+    let mut statement = conn.prepare(&format!("
+        SELECT date(timestamp, 'unixepoch') AS day, COUNT(*) FROM requests
+        WHERE {DATE_FILTER}
+        GROUP BY day ORDER BY day
+    "))?;
+    let days = statement.query_map((&range.from, &range.to), |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect();
+    days
+}
+
+/// Returns the canonical id, one of the scrambles and the number of requests
+pub fn count_requests_per_canonical_scramble(conn: &Connection, range: &DateRange, limit: usize) -> rusqlite::Result<Vec<(String, String, usize)>> {
+    let mut statement = conn.prepare(&format!("
+        SELECT canonical_scramble_id, MIN(scramble), COUNT(*) AS count FROM requests
+        WHERE {DATE_FILTER}
+        GROUP BY canonical_scramble_id ORDER BY count DESC, canonical_scramble_id LIMIT ?3
+    "))?;
+    let scrambles = statement.query_map((&range.from, &range.to, limit), |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+        .collect();
+    scrambles
+}
+
+/// Settings that can't be decoded are skipped
+pub fn count_requests_per_settings(conn: &Connection, range: &DateRange) -> rusqlite::Result<Vec<(Vec<StepConfig>, usize)>> {
+    let mut statement = conn.prepare(&format!("
+        SELECT settings, COUNT(*) FROM requests
+        WHERE {DATE_FILTER}
+        GROUP BY settings
+    "))?;
+    let settings = statement.query_map((&range.from, &range.to), |row| Ok((row.get::<_, String>(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<Vec<(String, usize)>>>()?;
+    Ok(settings.into_iter()
+        .filter_map(|(settings, count)| {
+            let json = base64::engine::general_purpose::STANDARD.decode(settings).ok()?;
+            serde_json::from_slice(&json).ok().map(|steps| (steps, count))
+        })
+        .collect())
+}
+
+pub fn count_requests_per_solution_length(conn: &Connection, range: &DateRange) -> rusqlite::Result<Vec<(usize, usize)>> {
+    let mut statement = conn.prepare(&format!("
+        SELECT solution_length, COUNT(*) FROM requests
+        WHERE solution_length IS NOT NULL AND {DATE_FILTER}
+        GROUP BY solution_length ORDER BY solution_length
+    "))?;
+    let lengths = statement.query_map((&range.from, &range.to), |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect();
+    lengths
+}
+
+pub fn serialize_cube_to_base64(cube: &Cube333) -> String {
     let mut cube_longs = cube.edges.get_edges_raw().to_vec();
     cube_longs.push(cube.corners.get_corners_raw());
     let bytes: Vec<u8> = cube_longs.into_iter()
         .flat_map(|x| x.to_le_bytes().into_iter())
         .collect();
     base64::engine::general_purpose::STANDARD.encode(&bytes)
-}
\ No newline at end of file
+}
+
+/// A database that only lives as long as the pool. It has a single connection, as every connection would get its own database
+#[cfg(test)]
+pub fn test_pool() -> r2d2::Pool<r2d2_sqlite::SqliteConnectionManager> {
+    let pool = r2d2::Pool::builder().max_size(1).build(r2d2_sqlite::SqliteConnectionManager::memory()).unwrap();
+    init_db(&pool.get().unwrap()).unwrap();
+    pool
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use base64::Engine;
+    use cubelib::algs::Algorithm;
+    use cubelib::defs::StepKind;
+    use cubelib::steps::step::StepConfig;
+
+    use crate::db::{self, DateRange};
+
+    // 2024-02-28 23:59:59, 2024-02-29 00:00:00, 2024-02-29 12:00:00 and 2024-03-01 00:00:00 UTC
+    const TIMESTAMPS: [u64; 4] = [1709164799, 1709164800, 1709208000, 1709251200];
+
+    fn range(from: Option<&str>, to: Option<&str>) -> DateRange {
+        DateRange { from: from.map(str::to_string), to: to.map(str::to_string) }
+    }
+
+    #[test]
+    fn test_date_filter() {
+        let pool = db::test_pool();
+        let conn = pool.get().unwrap();
+        let scramble = Algorithm::from_str("R U F").unwrap();
+        for time in TIMESTAMPS {
+            db::record_request_at(&conn, &scramble, &vec![StepConfig::new(StepKind::EO)], time).unwrap();
+        }
+
+        assert_eq!(4, db::count_requests(&conn, &range(None, None)).unwrap());
+        // Both ends are included
+        assert_eq!(2, db::count_requests(&conn, &range(Some("2024-02-29"), Some("2024-02-29"))).unwrap());
+        assert_eq!(3, db::count_requests(&conn, &range(Some("2024-02-29"), None)).unwrap());
+        assert_eq!(3, db::count_requests(&conn, &range(None, Some("2024-02-29"))).unwrap());
+        assert_eq!(0, db::count_requests(&conn, &range(Some("2024-03-02"), None)).unwrap());
+
+        let days = db::count_requests_per_day(&conn, &range(None, None)).unwrap();
+        assert_eq!(vec![("2024-02-28".to_string(), 1), ("2024-02-29".to_string(), 2), ("2024-03-01".to_string(), 1)], days);
+        let days = db::count_requests_per_day(&conn, &range(Some("2024-03-01"), None)).unwrap();
+        assert_eq!(vec![("2024-03-01".to_string(), 1)], days);
+    }
+
+    #[test]
+    fn test_settings_are_decoded() {
+        let pool = db::test_pool();
+        let conn = pool.get().unwrap();
+        let scramble = Algorithm::from_str("R U F").unwrap();
+        let eo = vec![StepConfig::new(StepKind::EO)];
+        let eo_dr = vec![StepConfig::new(StepKind::EO), StepConfig::new(StepKind::DR)];
+        for steps in [&eo, &eo_dr, &eo] {
+            db::record_request_at(&conn, &scramble, steps, TIMESTAMPS[0]).unwrap();
+        }
+        let invalid = base64::engine::general_purpose::STANDARD.encode("not json");
+        conn.execute("INSERT INTO requests (timestamp, scramble, canonical_scramble_id, settings) VALUES (?, 'R', '', ?)", (TIMESTAMPS[0], invalid)).unwrap();
+
+        let mut settings = db::count_requests_per_settings(&conn, &range(None, None)).unwrap();
+        settings.sort_by_key(|(steps, _)| steps.len());
+        let settings: Vec<(Vec<StepKind>, usize)> = settings.into_iter()
+            .map(|(steps, count)| (steps.into_iter().map(|step| step.kind).collect(), count))
+            .collect();
+        assert_eq!(vec![(vec![StepKind::EO], 2), (vec![StepKind::EO, StepKind::DR], 1)], settings);
+    }
+
+    #[test]
+    fn test_solution_lengths() {
+        let pool = db::test_pool();
+        let conn = pool.get().unwrap();
+        let scramble = Algorithm::from_str("R U F").unwrap();
+        let steps = vec![StepConfig::new(StepKind::EO)];
+        for (time, length) in TIMESTAMPS.into_iter().zip([Some(3), Some(3), Some(5), None]) {
+            let id = db::record_request_at(&conn, &scramble, &steps, time).unwrap();
+            if let Some(length) = length {
+                db::record_solution_length(&conn, id, length).unwrap();
+            }
+        }
+
+        assert_eq!(vec![(3, 2), (5, 1)], db::count_requests_per_solution_length(&conn, &range(None, None)).unwrap());
+        assert_eq!(vec![(3, 1), (5, 1)], db::count_requests_per_solution_length(&conn, &range(Some("2024-02-29"), None)).unwrap());
+    }
+}
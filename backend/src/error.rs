@@ -0,0 +1,116 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use actix_web::{error, HttpRequest, HttpResponse};
+use actix_web::http::StatusCode;
+use cubelib::algs::Algorithm;
+use cubelib::solver::backend::{SolverError, Unsupported};
+use cubelib_interface::{ApiError, ErrorResponse};
+use log::error;
+
+pub fn error_response(error: ApiError) -> HttpResponse {
+    let status = match &error {
+        ApiError::InvalidScramble { .. } | ApiError::InvalidSolution { .. } | ApiError::InvalidStepConfig { .. } | ApiError::InvalidRequest { .. } => StatusCode::BAD_REQUEST,
+        ApiError::UnsupportedStepOrder { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+        ApiError::NotFound { .. } => StatusCode::NOT_FOUND,
+        ApiError::Unauthorized { .. } => StatusCode::UNAUTHORIZED,
+        ApiError::Overloaded { .. } => StatusCode::SERVICE_UNAVAILABLE,
+        ApiError::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    HttpResponse::build(status).json(ErrorResponse::from(error))
+}
+
+/// Logs the cause, clients only learn that something went wrong
+pub fn internal<E: Display>(err: E) -> ApiError {
+    error!("{err}");
+    ApiError::Internal
+}
+
+pub fn solver_error(err: SolverError) -> ApiError {
+    match err {
+        SolverError::Unsupported(_, Unsupported::StepOrder(_, _)) => ApiError::UnsupportedStepOrder { message: err.to_string() },
+        err => ApiError::InvalidStepConfig { message: err.to_string() },
+    }
+}
+
+pub fn parse_scramble(scramble: &str) -> Result<Algorithm, ApiError> {
+    parse_algorithm(scramble).map_err(|(position, token)| ApiError::InvalidScramble { position, token })
+}
+
+pub fn parse_solution(solution: &str) -> Result<Algorithm, ApiError> {
+    parse_algorithm(solution).map_err(|(position, token)| ApiError::InvalidSolution { position, token })
+}
+
+// The parser doesn't say where it failed, so we look for the first whitespace separated token that makes it fail.
+// A prefix that ends inside parentheses is fine as long as it parses once they are closed
+fn parse_algorithm(alg: &str) -> Result<Algorithm, (usize, String)> {
+    Algorithm::from_str(alg).map_err(|_| {
+        let mut position = 0;
+        for token in alg.split_whitespace() {
+            let start = alg[position..].find(token).unwrap() + position;
+            position = start + token.len();
+            let prefix = &alg[..position];
+            if Algorithm::from_str(prefix).is_err() && Algorithm::from_str(&format!("{prefix})")).is_err() {
+                return (alg[..start].chars().count(), token.to_string());
+            }
+        }
+        match alg.rfind('(') {
+            Some(open) => (alg[..open].chars().count(), "(".to_string()),
+            None => (alg.chars().count(), String::new()),
+        }
+    })
+}
+
+/// Replaces the plain text errors of actix for malformed JSON bodies and query strings
+pub fn json_error_handler(err: error::JsonPayloadError, _: &HttpRequest) -> error::Error {
+    let message = err.to_string();
+    error::InternalError::from_response(err, error_response(ApiError::InvalidRequest { message })).into()
+}
+
+pub fn query_error_handler(err: error::QueryPayloadError, _: &HttpRequest) -> error::Error {
+    let message = err.to_string();
+    error::InternalError::from_response(err, error_response(ApiError::InvalidRequest { message })).into()
+}
+
+#[cfg(test)]
+mod test {
+    use actix_web::http::StatusCode;
+    use cubelib_interface::ApiError;
+
+    use crate::error::{error_response, parse_algorithm};
+
+    #[test]
+    fn test_invalid_token_positions() {
+        for (alg, position, token) in [
+            ("R U X F", 4, "X"),
+            ("R (U F", 2, "("),
+            ("R (U) (F", 6, "("),
+            ("R U) F", 2, "U)"),
+            ("R ((U) F", 2, "((U)"),
+            // Positions count characters, not bytes
+            ("Ü R X", 0, "Ü"),
+            ("R2  Ü", 4, "Ü"),
+        ] {
+            assert_eq!(Err((position, token.to_string())), parse_algorithm(alg).map(|_| ()), "{alg}");
+        }
+        assert!(parse_algorithm("R (U) F").is_ok());
+    }
+
+    #[test]
+    fn test_status_codes() {
+        let message = String::new();
+        for (error, status) in [
+            (ApiError::InvalidScramble { position: 0, token: message.clone() }, StatusCode::BAD_REQUEST),
+            (ApiError::InvalidSolution { position: 0, token: message.clone() }, StatusCode::BAD_REQUEST),
+            (ApiError::InvalidStepConfig { message: message.clone() }, StatusCode::BAD_REQUEST),
+            (ApiError::InvalidRequest { message: message.clone() }, StatusCode::BAD_REQUEST),
+            (ApiError::UnsupportedStepOrder { message: message.clone() }, StatusCode::UNPROCESSABLE_ENTITY),
+            (ApiError::NotFound { message: message.clone() }, StatusCode::NOT_FOUND),
+            (ApiError::Unauthorized { message: message.clone() }, StatusCode::UNAUTHORIZED),
+            (ApiError::Overloaded { message: message.clone() }, StatusCode::SERVICE_UNAVAILABLE),
+            (ApiError::Internal, StatusCode::INTERNAL_SERVER_ERROR),
+        ] {
+            assert_eq!(status, error_response(error).status());
+        }
+    }
+}
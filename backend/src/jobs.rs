@@ -0,0 +1,327 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use actix_web::{delete, get, post, web, HttpRequest, HttpResponse, Responder};
+use actix_web::http::header;
+use cubelib_interface::{ApiError, JobRequest, JobState, JobStatus, ServerMessage};
+use log::{error, info};
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+
+use crate::{AppData, db};
+use crate::controller::client_address;
+use crate::error::{error_response, internal};
+use crate::websocket::{start_solve, RunningSolve};
+
+const EXPIRY_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Solves that run in the background. Their state and results are stored in the database after every change,
+/// so clients can poll them, and unfinished jobs are started again after a restart
+pub struct Jobs {
+    pool: Pool<SqliteConnectionManager>,
+    expiry: Duration,
+    running: Mutex<HashMap<u64, RunningSolve>>,
+}
+
+impl Jobs {
+    pub fn new(pool: Pool<SqliteConnectionManager>, expiry: Duration) -> Arc<Self> {
+        let jobs = Arc::new(Self {
+            pool,
+            expiry,
+            running: Mutex::new(HashMap::new()),
+        });
+        let expired = Arc::downgrade(&jobs);
+        thread::spawn(move || loop {
+            thread::sleep(EXPIRY_INTERVAL);
+            let Some(jobs) = expired.upgrade() else {
+                return;
+            };
+            jobs.delete_expired();
+        });
+        jobs
+    }
+
+    pub fn submit(self: &Arc<Self>, request: JobRequest, client: &str, app_data: &AppData) -> Result<JobStatus, ApiError> {
+        // Ids are random, so only the client that submitted a job knows it
+        let id = rand::random_range(1..1u64 << 53);
+        let created = now();
+        let expires = created + self.expiry.as_secs();
+        db::insert_job(&self.pool.get().map_err(internal)?, id, client, &request, created, expires).map_err(internal)?;
+        // Invalid requests are rejected right away, so they don't need to be kept
+        if let Err(err) = self.start(id, request, client, app_data) {
+            db::delete_job(&self.pool.get().map_err(internal)?, id).map_err(internal)?;
+            return Err(err);
+        }
+        self.status(id)
+    }
+
+    /// Starts the jobs that were queued or running when the backend stopped. Earlier solutions are replayed from the cache
+    pub fn resume(self: &Arc<Self>, app_data: &AppData) -> Result<(), String> {
+        for (id, client, request) in self.with_connection(db::get_unfinished_jobs)? {
+            info!("Resuming job {id}");
+            self.with_connection(|conn| db::clear_job_solutions(conn, id))?;
+            let started = match request {
+                Some(request) => self.start(id, request, &client, app_data),
+                None => Err(ApiError::Internal),
+            };
+            if let Err(err) = started {
+                self.with_connection(|conn| db::set_job_state(conn, id, JobState::Failed, Some(&err)))?;
+            }
+        }
+        Ok(())
+    }
+
+    // Connections aren't kept while a solve starts, as the solve needs one of its own
+    fn with_connection<T, F: FnOnce(&db::Connection) -> rusqlite::Result<T>>(&self, f: F) -> Result<T, String> {
+        let conn = self.pool.get().map_err(|err| err.to_string())?;
+        f(&conn).map_err(|err| err.to_string())
+    }
+
+    fn start(self: &Arc<Self>, id: u64, request: JobRequest, client: &str, app_data: &AppData) -> Result<(), ApiError> {
+        let JobRequest { request, backend, rank_by, limits } = request;
+        // Holding the lock until the solve is registered makes sure it can't finish before that
+        let mut running = self.running.lock().unwrap();
+        let jobs = self.clone();
+        let solve = start_solve(id, request, backend, rank_by, limits, "job", client, app_data, move |message| {
+            jobs.update(id, message);
+            true
+        })?;
+        running.insert(id, solve);
+        Ok(())
+    }
+
+    fn update(&self, id: u64, message: ServerMessage) {
+        // Starting a job takes a connection while holding the lock, so the lock is released before taking one here
+        if matches!(message, ServerMessage::Done { .. }) {
+            self.running.lock().unwrap().remove(&id);
+        }
+        let Ok(conn) = self.pool.get().inspect_err(|err| error!("Failed to update job {id}. {err}")) else {
+            return;
+        };
+        let updated = match &message {
+            ServerMessage::Solution { solution, .. } => db::add_job_solution(&conn, id, solution),
+            ServerMessage::Queued { position, .. } => db::set_job_queue_position(&conn, id, *position),
+            ServerMessage::Started { .. } => db::set_job_state(&conn, id, JobState::Running, None),
+            ServerMessage::Progress { quality, .. } => db::set_job_quality(&conn, id, *quality),
+            ServerMessage::Error { error, .. } => db::set_job_state(&conn, id, JobState::Failed, Some(error)),
+            ServerMessage::Done { cancelled, .. } => {
+                let failed = db::get_job(&conn, id).ok().flatten().is_some_and(|job| job.state == JobState::Failed);
+                match (failed, cancelled) {
+                    (true, _) => Ok(()),
+                    (false, true) => db::set_job_state(&conn, id, JobState::Cancelled, None),
+                    (false, false) => db::set_job_state(&conn, id, JobState::Done, None),
+                }
+            },
+        };
+        if let Err(err) = updated {
+            error!("Failed to update job {id}. {err}");
+        }
+    }
+
+    pub fn status(&self, id: u64) -> Result<JobStatus, ApiError> {
+        let conn = self.pool.get().map_err(internal)?;
+        db::get_job(&conn, id).map_err(internal)?
+            .ok_or_else(|| ApiError::NotFound { message: format!("Job {id} doesn't exist or expired") })
+    }
+
+    /// The job stays cancelled until it expires, so clients can still fetch the solutions found so far
+    pub fn cancel(&self, id: u64) -> Result<JobStatus, ApiError> {
+        let job = self.status(id)?;
+        if let Some(solve) = self.running.lock().unwrap().get(&id) {
+            solve.cancel_token.cancel();
+        }
+        Ok(job)
+    }
+
+    fn delete_expired(&self) {
+        match self.with_connection(|conn| db::delete_expired_jobs(conn, now())) {
+            Ok(ids) => {
+                let running = self.running.lock().unwrap();
+                for id in ids {
+                    if let Some(solve) = running.get(&id) {
+                        solve.cancel_token.cancel();
+                    }
+                }
+            },
+            Err(err) => error!("Failed to delete expired jobs. {err}"),
+        }
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).expect("System time before unix epoch").as_secs()
+}
+
+/// Starts a job and returns its status. The job keeps running after the response is sent
+#[post("/jobs")]
+pub async fn submit_job(req: HttpRequest, request: web::Json<JobRequest>, app_data: web::Data<AppData>) -> impl Responder {
+    match app_data.jobs.submit(request.into_inner(), &client_address(&req, &app_data.trusted_proxies), &app_data) {
+        Ok(job) => HttpResponse::Accepted()
+            .insert_header((header::LOCATION, format!("/jobs/{}", job.id)))
+            .json(job),
+        Err(err) => error_response(err),
+    }
+}
+
+#[get("/jobs/{id}")]
+pub async fn get_job(id: web::Path<u64>, app_data: web::Data<AppData>) -> impl Responder {
+    match app_data.jobs.status(id.into_inner()) {
+        Ok(job) => HttpResponse::Ok().json(job),
+        Err(err) => error_response(err),
+    }
+}
+
+#[delete("/jobs/{id}")]
+pub async fn cancel_job(id: web::Path<u64>, app_data: web::Data<AppData>) -> impl Responder {
+    match app_data.jobs.cancel(id.into_inner()) {
+        Ok(job) => HttpResponse::Accepted().json(job),
+        Err(err) => error_response(err),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    use cubelib::defs::StepKind;
+    use cubelib::solver::solution::Solution;
+    use cubelib::steps::step::StepConfig;
+    use cubelib_interface::{ApiError, JobRequest, JobState, JobStatus, SolveLimits, SolverRequest};
+
+    use crate::AppData;
+    use crate::db;
+    use crate::jobs::{now, Jobs};
+    use crate::scheduler::SchedulerConfig;
+
+    // Jobs wait in the queue forever if max_concurrent is 0
+    fn app_data(max_concurrent: usize) -> AppData {
+        crate::test_app_data(SchedulerConfig {
+            max_concurrent,
+            ..SchedulerConfig::default()
+        })
+    }
+
+    fn eo_request(scramble: &str) -> JobRequest {
+        JobRequest {
+            request: SolverRequest {
+                scramble: scramble.to_string(),
+                steps: vec![StepConfig::new(StepKind::EO)],
+            },
+            backend: Default::default(),
+            rank_by: None,
+            limits: SolveLimits {
+                max_length: None,
+                solution_count: Some(1),
+            },
+        }
+    }
+
+    fn wait_until_finished(jobs: &Jobs, id: u64) -> JobStatus {
+        let deadline = Instant::now() + Duration::from_secs(30);
+        loop {
+            let job = jobs.status(id).unwrap();
+            if !matches!(job.state, JobState::Queued | JobState::Running) {
+                return job;
+            }
+            assert!(Instant::now() < deadline, "Job {id} didn't finish");
+            thread::sleep(Duration::from_millis(20));
+        }
+    }
+
+    #[test]
+    fn test_submit_and_poll() {
+        let app_data = app_data(1);
+        let job = app_data.jobs.submit(eo_request("R U F"), "client", &app_data).unwrap();
+        assert_eq!(app_data.jobs.expiry.as_secs(), job.expires - job.created);
+
+        let job = wait_until_finished(&app_data.jobs, job.id);
+        assert_eq!(JobState::Done, job.state);
+        assert_eq!(1, job.solutions.len());
+        assert_eq!(None, job.error);
+    }
+
+    #[test]
+    fn test_invalid_requests_are_not_kept() {
+        let app_data = app_data(1);
+        let err = app_data.jobs.submit(eo_request("R U X"), "client", &app_data).unwrap_err();
+        assert!(matches!(err, ApiError::InvalidScramble { .. }), "{err:?}");
+        let jobs: usize = app_data.pool.get().unwrap().query_row("SELECT COUNT(*) FROM jobs", [], |row| row.get(0)).unwrap();
+        assert_eq!(0, jobs);
+        assert!(matches!(app_data.jobs.status(1), Err(ApiError::NotFound { .. })));
+    }
+
+    #[test]
+    fn test_cancel() {
+        let app_data = app_data(0);
+        let job = app_data.jobs.submit(eo_request("R U F"), "client", &app_data).unwrap();
+        assert_eq!(JobState::Queued, job.state);
+
+        assert_eq!(job.id, app_data.jobs.cancel(job.id).unwrap().id);
+        let job = wait_until_finished(&app_data.jobs, job.id);
+        assert_eq!(JobState::Cancelled, job.state);
+        assert!(app_data.jobs.running.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_resume() {
+        let app_data = app_data(1);
+        let created = now();
+        {
+            let conn = app_data.pool.get().unwrap();
+            // A job that was running when the backend stopped, with a solution from before the restart
+            db::insert_job(&conn, 1, "client", &eo_request("R U F"), created, created + 60).unwrap();
+            db::set_job_state(&conn, 1, JobState::Running, None).unwrap();
+            db::add_job_solution(&conn, 1, &Solution::new()).unwrap();
+            // A job from a version with a different request format
+            db::insert_job(&conn, 2, "client", &eo_request("R U F"), created, created + 60).unwrap();
+            conn.execute("UPDATE jobs SET request = '{}' WHERE id = 2", []).unwrap();
+            // Finished jobs stay as they are
+            db::insert_job(&conn, 3, "client", &eo_request("R U F"), created, created + 60).unwrap();
+            db::set_job_state(&conn, 3, JobState::Done, None).unwrap();
+        }
+
+        app_data.jobs.resume(&app_data).unwrap();
+
+        let job = wait_until_finished(&app_data.jobs, 1);
+        assert_eq!(JobState::Done, job.state);
+        assert_eq!(1, job.solutions.len());
+        assert!(!job.solutions[0].steps.is_empty());
+        let job = app_data.jobs.status(2).unwrap();
+        assert_eq!(JobState::Failed, job.state);
+        assert_eq!(Some(ApiError::Internal), job.error);
+        let job = app_data.jobs.status(3).unwrap();
+        assert_eq!(JobState::Done, job.state);
+        assert!(job.solutions.is_empty());
+    }
+
+    #[test]
+    fn test_delete_expired() {
+        let app_data = app_data(0);
+        let running = app_data.jobs.submit(eo_request("R U F"), "client", &app_data).unwrap();
+        let kept = app_data.jobs.submit(eo_request("R U F"), "client", &app_data).unwrap();
+        {
+            let conn = app_data.pool.get().unwrap();
+            db::insert_job(&conn, 1, "client", &eo_request("R U F"), 0, 60).unwrap();
+            db::add_job_solution(&conn, 1, &Solution::new()).unwrap();
+            conn.execute("UPDATE jobs SET expires = ? WHERE id = ?", (now(), running.id)).unwrap();
+        }
+
+        app_data.jobs.delete_expired();
+
+        assert!(matches!(app_data.jobs.status(1), Err(ApiError::NotFound { .. })));
+        assert!(matches!(app_data.jobs.status(running.id), Err(ApiError::NotFound { .. })));
+        assert_eq!(JobState::Queued, app_data.jobs.status(kept.id).unwrap().state);
+        let solutions: usize = app_data.pool.get().unwrap().query_row("SELECT COUNT(*) FROM job_solutions", [], |row| row.get(0)).unwrap();
+        assert_eq!(0, solutions);
+        // The solve of the deleted job is cancelled
+        let deadline = Instant::now() + Duration::from_secs(30);
+        while app_data.jobs.running.lock().unwrap().contains_key(&running.id) {
+            assert!(Instant::now() < deadline, "Job {} wasn't cancelled", running.id);
+            thread::sleep(Duration::from_millis(20));
+        }
+        assert!(app_data.jobs.running.lock().unwrap().contains_key(&kept.id));
+    }
+}
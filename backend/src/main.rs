@@ -1,68 +1,151 @@
-use std::net::SocketAddr;
-use std::str::FromStr;
+use std::net::IpAddr;
+use std::process::exit;
 use std::sync::Arc;
-use std::time::Duration;
 
 use actix_cors::Cors;
 use actix_web::{App, HttpServer, web};
-use cubelib::algs::Algorithm;
-use cubelib::steps::tables::PruningTables333;
-use log::LevelFilter;
-use moka::sync::Cache;
+use clap::Parser;
+use log::{error, info};
 use r2d2::Pool;
 use r2d2_sqlite::SqliteConnectionManager;
 use simple_logger::SimpleLogger;
 
+use crate::config::{Args, BackendConfig, Command};
+use crate::jobs::Jobs;
+use crate::metrics::Metrics;
+use crate::scheduler::Scheduler;
+use crate::tables::TableStore;
+
+mod cache;
+mod config;
 mod controller;
 mod db;
+mod error;
+mod jobs;
+mod metrics;
+mod scheduler;
+mod stats;
+mod tables;
+mod websocket;
 
 struct AppData {
-    pruning_tables: Arc<PruningTables333>,
+    tables: Arc<TableStore>,
     pool: Pool<SqliteConnectionManager>,
-    debounce_cache: Arc<Cache<u64, SocketAddr>>,
+    scheduler: Arc<Scheduler>,
+    admin_token: Option<String>,
+    trusted_proxies: Vec<IpAddr>,
+    metrics: Arc<Metrics>,
+    jobs: Arc<Jobs>,
+}
+
+/// Uses an in-memory database and only preloads the EO table
+#[cfg(test)]
+fn test_app_data(scheduler: scheduler::SchedulerConfig) -> AppData {
+    let pool = db::test_pool();
+    AppData {
+        tables: Arc::new(TableStore::new(vec![tables::Table::EO])),
+        pool: pool.clone(),
+        scheduler: Scheduler::new(scheduler),
+        admin_token: None,
+        trusted_proxies: vec![],
+        metrics: Arc::new(Metrics::default()),
+        jobs: Jobs::new(pool, std::time::Duration::from_secs(3600)),
+    }
 }
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
+    let mut args = Args::parse();
+    let command = args.command.take();
+    let config = match BackendConfig::load(args) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("{err}");
+            exit(1);
+        }
+    };
+
     SimpleLogger::new()
-        .with_level(LevelFilter::Info)
+        .with_level(config.log)
         .init()
         .unwrap();
 
-    let manager = SqliteConnectionManager::file("requests.db");
+    let manager = SqliteConnectionManager::file(&config.database);
     let pool = Pool::new(manager).unwrap();
-    let conn = pool.get().expect("Connection required");
-    db::init_db(&conn).unwrap();
+    let initialized = pool.get()
+        .map_err(|err| err.to_string())
+        .and_then(|conn| db::init_db(&conn).map_err(|err| err.to_string()));
+    if let Err(err) = initialized {
+        error!("Failed to open database {}. {err}", config.database.display());
+        exit(1);
+    }
 
-    let mut pruning_tables = PruningTables333::new();
-    pruning_tables.gen_eo();
-    pruning_tables.gen_dr();
-    pruning_tables.gen_htr();
-    pruning_tables.gen_fr();
-    pruning_tables.gen_fr_leave_slice();
-    pruning_tables.gen_fr_finish();
-    pruning_tables.gen_htr_finish();
-    pruning_tables.gen_htr_leave_slice_finish();
+    if let Some(Command::Stats { from, to, limit, json }) = command {
+        print_stats(&pool, from, to, limit, json);
+        return Ok(());
+    }
 
-    let pruning_tables = Arc::new(pruning_tables);
-    let debounce_cache = Arc::new(Cache::builder()
-        .time_to_live(Duration::from_millis(500))
-        .max_capacity(1000)
-        .build()
-    );
+    let tables = Arc::new(TableStore::new(config.preload_tables.clone()));
+    let scheduler = Scheduler::new(config.scheduler.clone());
+    let app_data = web::Data::new(AppData {
+        tables,
+        pool: pool.clone(),
+        scheduler,
+        admin_token: config.admin_token,
+        trusted_proxies: config.trusted_proxies,
+        metrics: Arc::new(Metrics::default()),
+        jobs: Jobs::new(pool, config.job_expiry),
+    });
+    if let Err(err) = app_data.jobs.resume(&app_data) {
+        error!("Failed to resume jobs. {err}");
+    }
 
+    let cors_origins = config.cors_origins;
+    info!("Listening on {}", config.listen);
     HttpServer::new(move || {
-        let cors = Cors::permissive();
+        let cors = if cors_origins.is_empty() {
+            Cors::permissive()
+        } else {
+            cors_origins.iter()
+                .fold(Cors::default(), |cors, origin| cors.allowed_origin(origin))
+                .allow_any_method()
+                .allow_any_header()
+        };
         App::new()
-            .app_data(web::Data::new(AppData {
-                pruning_tables: pruning_tables.clone(),
-                pool: pool.clone(),
-                debounce_cache: debounce_cache.clone(),
-            }))
+            .app_data(app_data.clone())
+            .app_data(web::JsonConfig::default().error_handler(error::json_error_handler))
+            .app_data(web::QueryConfig::default().error_handler(error::query_error_handler))
             .wrap(cors)
             .service(controller::solve_stream)
+            .service(controller::analyze)
+            .service(controller::verify)
+            .service(controller::generate_scramble)
+            .service(controller::list_tables)
+            .service(controller::health)
+            .service(websocket::solve_ws)
+            .service(jobs::submit_job)
+            .service(jobs::get_job)
+            .service(jobs::cancel_job)
+            .service(stats::admin_stats)
+            .service(metrics::metrics)
     })
-        .bind(("127.0.0.1", 8049))?
+        .bind(config.listen)?
         .run()
         .await
 }
+
+fn print_stats(pool: &Pool<SqliteConnectionManager>, from: Option<String>, to: Option<String>, limit: usize, json: bool) {
+    let stats = stats::date_range(from, to)
+        .and_then(|range| {
+            let conn = pool.get().map_err(|err| err.to_string())?;
+            stats::collect_stats(&conn, &range, limit).map_err(|err| err.to_string())
+        });
+    match stats {
+        Ok(stats) if json => println!("{}", serde_json::to_string_pretty(&stats).unwrap()),
+        Ok(stats) => print!("{}", stats::format_report(&stats)),
+        Err(err) => {
+            eprintln!("{err}");
+            exit(1);
+        }
+    }
+}
@@ -0,0 +1,205 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use actix_web::{get, web, HttpResponse, Responder};
+use cubelib_interface::SolverBackend;
+
+use crate::AppData;
+
+// In seconds
+const TIME_TO_FIRST_SOLUTION_BUCKETS: [f64; 10] = [0.01, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0];
+
+/// Counters of the solve path, exposed by `/metrics` in the Prometheus text format.
+/// Gauges like the number of running solves are read from the scheduler and table store instead
+#[derive(Default)]
+pub struct Metrics {
+    state: Mutex<MetricsState>,
+}
+
+#[derive(Default)]
+struct MetricsState {
+    solve_requests: BTreeMap<(&'static str, &'static str), u64>,
+    solutions_streamed: u64,
+    cancelled_solves: u64,
+    time_to_first_solution: Histogram,
+}
+
+struct Histogram {
+    buckets: &'static [f64],
+    counts: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self {
+            buckets: &TIME_TO_FIRST_SOLUTION_BUCKETS,
+            counts: vec![0; TIME_TO_FIRST_SOLUTION_BUCKETS.len()],
+            sum: 0.0,
+            count: 0,
+        }
+    }
+}
+
+impl Histogram {
+    fn observe(&mut self, value: f64) {
+        if let Some(bucket) = self.buckets.iter().position(|le| value <= *le) {
+            self.counts[bucket] += 1;
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+}
+
+impl Metrics {
+    /// `transport` is the endpoint the solve was requested through, `stream`, `websocket` or `job`
+    pub fn start_solve(self: &Arc<Self>, backend: SolverBackend, transport: &'static str) -> SolveMetrics {
+        let backend = match backend {
+            SolverBackend::IterStream => "iter_stream",
+            SolverBackend::MultiPathChannel => "multi_path_channel",
+        };
+        *self.state.lock().unwrap().solve_requests.entry((backend, transport)).or_default() += 1;
+        SolveMetrics {
+            metrics: self.clone(),
+            started: Instant::now(),
+            solutions: 0,
+        }
+    }
+}
+
+/// Records the metrics of a single solve
+pub struct SolveMetrics {
+    metrics: Arc<Metrics>,
+    started: Instant,
+    solutions: u64,
+}
+
+impl SolveMetrics {
+    /// A solution found by the search
+    pub fn solution_sent(&mut self) {
+        let mut state = self.metrics.state.lock().unwrap();
+        if self.solutions == 0 {
+            state.time_to_first_solution.observe(self.started.elapsed().as_secs_f64());
+        }
+        self.solutions += 1;
+        state.solutions_streamed += 1;
+    }
+
+    /// A solution replayed from the cache. It says nothing about how fast the search is,
+    /// so it doesn't count as the first solution
+    pub fn cached_solution_sent(&mut self) {
+        self.metrics.state.lock().unwrap().solutions_streamed += 1;
+    }
+
+    pub fn finish(self, cancelled: bool) {
+        if cancelled {
+            self.metrics.state.lock().unwrap().cancelled_solves += 1;
+        }
+    }
+}
+
+#[get("/metrics")]
+pub async fn metrics(app_data: web::Data<AppData>) -> impl Responder {
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(render(&app_data))
+}
+
+fn render(app_data: &AppData) -> String {
+    let mut out = String::new();
+    {
+        let state = app_data.metrics.state.lock().unwrap();
+        header(&mut out, "cubelib_solve_requests_total", "counter", "Solve requests by solver backend and transport");
+        for ((backend, transport), count) in &state.solve_requests {
+            let _ = writeln!(out, "cubelib_solve_requests_total{{backend=\"{}\",transport=\"{}\"}} {count}", label(backend), label(transport));
+        }
+        header(&mut out, "cubelib_solutions_streamed_total", "counter", "Solutions sent to clients, including cached ones");
+        let _ = writeln!(out, "cubelib_solutions_streamed_total {}", state.solutions_streamed);
+        header(&mut out, "cubelib_cancelled_solves_total", "counter", "Solves cancelled by the client or the time limit");
+        let _ = writeln!(out, "cubelib_cancelled_solves_total {}", state.cancelled_solves);
+        let histogram = &state.time_to_first_solution;
+        header(&mut out, "cubelib_time_to_first_solution_seconds", "histogram", "Time from the request to the first solution found by the search, including time spent in the queue. Cached solutions don't count");
+        let mut cumulative = 0;
+        for (le, count) in histogram.buckets.iter().zip(&histogram.counts) {
+            cumulative += count;
+            let _ = writeln!(out, "cubelib_time_to_first_solution_seconds_bucket{{le=\"{le}\"}} {cumulative}");
+        }
+        let _ = writeln!(out, "cubelib_time_to_first_solution_seconds_bucket{{le=\"+Inf\"}} {}", histogram.count);
+        let _ = writeln!(out, "cubelib_time_to_first_solution_seconds_sum {}", histogram.sum);
+        let _ = writeln!(out, "cubelib_time_to_first_solution_seconds_count {}", histogram.count);
+    }
+
+    let scheduler = app_data.scheduler.stats();
+    header(&mut out, "cubelib_active_solves", "gauge", "Solves that are currently running");
+    let _ = writeln!(out, "cubelib_active_solves {}", scheduler.running);
+    header(&mut out, "cubelib_queued_solves", "gauge", "Solves waiting for others to finish");
+    let _ = writeln!(out, "cubelib_queued_solves {}", scheduler.queued);
+    header(&mut out, "cubelib_timed_out_solves_total", "counter", "Solves cancelled because they exceeded the time limit");
+    let _ = writeln!(out, "cubelib_timed_out_solves_total {}", scheduler.timed_out);
+
+    header(&mut out, "cubelib_table_load_seconds", "gauge", "How long the last load of a pruning table took");
+    for (table, duration) in app_data.tables.load_times() {
+        let _ = writeln!(out, "cubelib_table_load_seconds{{table=\"{}\"}} {}", label(table.name()), duration.as_secs_f64());
+    }
+    out
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+}
+
+// Escapes a label value as required by the text format
+fn label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod test {
+    use cubelib_interface::SolverBackend;
+
+    use crate::metrics::{label, render};
+    use crate::scheduler::SchedulerConfig;
+
+    #[test]
+    fn test_render() {
+        let app_data = crate::test_app_data(SchedulerConfig::default());
+        let mut solve = app_data.metrics.start_solve(SolverBackend::MultiPathChannel, "stream");
+        solve.cached_solution_sent();
+        solve.finish(true);
+        // The cached solution isn't observed, so these are the only ones
+        {
+            let mut state = app_data.metrics.state.lock().unwrap();
+            for value in [0.03, 0.3, 0.5, 100.0] {
+                state.time_to_first_solution.observe(value);
+            }
+        }
+
+        let out = render(&app_data);
+        let lines: Vec<&str> = out.lines().collect();
+        for line in [
+            "cubelib_solve_requests_total{backend=\"multi_path_channel\",transport=\"stream\"} 1",
+            "cubelib_solutions_streamed_total 1",
+            "cubelib_cancelled_solves_total 1",
+            "cubelib_time_to_first_solution_seconds_bucket{le=\"0.01\"} 0",
+            "cubelib_time_to_first_solution_seconds_bucket{le=\"0.05\"} 1",
+            "cubelib_time_to_first_solution_seconds_bucket{le=\"0.25\"} 1",
+            "cubelib_time_to_first_solution_seconds_bucket{le=\"0.5\"} 3",
+            "cubelib_time_to_first_solution_seconds_bucket{le=\"30\"} 3",
+            "cubelib_time_to_first_solution_seconds_bucket{le=\"+Inf\"} 4",
+            "cubelib_time_to_first_solution_seconds_count 4",
+        ] {
+            assert!(lines.contains(&line), "{line} missing in\n{out}");
+        }
+    }
+
+    #[test]
+    fn test_label_escaping() {
+        assert_eq!("eo", label("eo"));
+        assert_eq!(r#"a\\b\"c\nd"#, label("a\\b\"c\nd"));
+    }
+}
@@ -0,0 +1,312 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Condvar, Mutex, Weak};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use cubelib::solver::df_search::CancelToken;
+use log::info;
+
+const WATCHDOG_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Clone, Debug)]
+pub struct SchedulerConfig {
+    /// Solves running at the same time, across all clients
+    pub max_concurrent: usize,
+    pub max_per_client: usize,
+    /// Requests are rejected once this many are waiting
+    pub max_queued: usize,
+    /// Requests of one client are rejected once this many of them are waiting
+    pub max_queued_per_client: usize,
+    /// Solves are cancelled once they ran this long. Time spent in the queue doesn't count
+    pub time_limit: Duration,
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrent: thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+            max_per_client: 2,
+            max_queued: 32,
+            max_queued_per_client: 8,
+            time_limit: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Limits how many solves run at the same time. Requests over the limit wait in a queue,
+/// and are started in order unless their client already has too many running solves.
+pub struct Scheduler {
+    config: SchedulerConfig,
+    state: Mutex<SchedulerState>,
+    changed: Condvar,
+}
+
+#[derive(Default)]
+struct SchedulerState {
+    next_id: u64,
+    queue: VecDeque<(u64, String)>,
+    running: HashMap<u64, RunningSolve>,
+    timed_out: u64,
+}
+
+pub struct SchedulerStats {
+    pub running: usize,
+    pub queued: usize,
+    /// Solves that were cancelled because they exceeded the time limit, since the start of the server
+    pub timed_out: u64,
+}
+
+struct RunningSolve {
+    client: String,
+    cancel_token: Arc<CancelToken>,
+    deadline: Instant,
+}
+
+impl SchedulerState {
+    fn running_for(&self, client: &str) -> usize {
+        self.running.values().filter(|r| r.client == client).count()
+    }
+
+    fn queued_for(&self, client: &str) -> usize {
+        self.queue.iter().filter(|(_, queued)| queued == client).count()
+    }
+}
+
+impl Scheduler {
+    pub fn new(config: SchedulerConfig) -> Arc<Self> {
+        let scheduler = Arc::new(Self {
+            config,
+            state: Mutex::new(SchedulerState::default()),
+            changed: Condvar::new(),
+        });
+        let watchdog = Arc::downgrade(&scheduler);
+        thread::spawn(move || Self::watchdog(watchdog));
+        scheduler
+    }
+
+    /// Queues a solve of `client`. `cancel_token` is cancelled once the solve exceeds the time limit
+    pub fn enqueue(self: &Arc<Self>, client: String, cancel_token: Arc<CancelToken>) -> Result<SolveSlot, String> {
+        let mut state = self.state.lock().unwrap();
+        if state.queue.len() >= self.config.max_queued {
+            return Err("Too many requests, try again later".to_string());
+        }
+        if state.queued_for(&client) >= self.config.max_queued_per_client {
+            return Err("Too many requests from this client, try again later".to_string());
+        }
+        let id = state.next_id;
+        state.next_id += 1;
+        state.queue.push_back((id, client));
+        Ok(SolveSlot {
+            scheduler: self.clone(),
+            id,
+            cancel_token,
+            running: false,
+        })
+    }
+
+    // Starts the solve if it is the first one in the queue that may run. Otherwise returns its position
+    fn try_start(&self, state: &mut SchedulerState, slot: &SolveSlot) -> Result<(), usize> {
+        let position = state.queue.iter().position(|(id, _)| *id == slot.id).expect("Queued solve");
+        if state.running.len() >= self.config.max_concurrent {
+            return Err(position);
+        }
+        let next = state.queue.iter()
+            .position(|(_, client)| state.running_for(client) < self.config.max_per_client);
+        if next != Some(position) {
+            return Err(position);
+        }
+        let (_, client) = state.queue.remove(position).unwrap();
+        state.running.insert(slot.id, RunningSolve {
+            client,
+            cancel_token: slot.cancel_token.clone(),
+            deadline: Instant::now() + self.config.time_limit,
+        });
+        // There might be room for the next one as well
+        self.changed.notify_all();
+        Ok(())
+    }
+
+    pub fn stats(&self) -> SchedulerStats {
+        let state = self.state.lock().unwrap();
+        SchedulerStats {
+            running: state.running.len(),
+            queued: state.queue.len(),
+            timed_out: state.timed_out,
+        }
+    }
+
+    fn release(&self, id: u64) {
+        let mut state = self.state.lock().unwrap();
+        state.queue.retain(|(queued, _)| *queued != id);
+        state.running.remove(&id);
+        self.changed.notify_all();
+    }
+
+    fn watchdog(scheduler: Weak<Scheduler>) {
+        loop {
+            thread::sleep(WATCHDOG_INTERVAL);
+            let Some(scheduler) = scheduler.upgrade() else {
+                return;
+            };
+            let now = Instant::now();
+            let mut state = scheduler.state.lock().unwrap();
+            let mut timed_out = 0;
+            for (id, solve) in state.running.iter() {
+                if solve.deadline <= now && !solve.cancel_token.is_cancelled() {
+                    info!("Solve {id} of {} exceeded the time limit, cancelling", solve.client);
+                    solve.cancel_token.cancel();
+                    timed_out += 1;
+                }
+            }
+            state.timed_out += timed_out;
+        }
+    }
+}
+
+/// A place in the queue of the [`Scheduler`], which becomes a running solve once [`SolveSlot::wait`] returns `None`.
+/// Dropping the slot frees it for the next request
+pub struct SolveSlot {
+    scheduler: Arc<Scheduler>,
+    id: u64,
+    cancel_token: Arc<CancelToken>,
+    running: bool,
+}
+
+impl SolveSlot {
+    /// Blocks until the solve starts, and calls `on_position` whenever its position in the queue changes.
+    /// Returns false if the cancel token was cancelled before the solve could start
+    pub fn wait_for_start<F: FnMut(usize)>(&mut self, mut on_position: F) -> bool {
+        let mut last_position = None;
+        loop {
+            if self.cancel_token.is_cancelled() {
+                return false;
+            }
+            match self.try_start() {
+                None => return true,
+                Some(position) if last_position != Some(position) => {
+                    on_position(position);
+                    last_position = Some(position);
+                },
+                Some(_) => {},
+            }
+            // The timeout makes sure we notice cancellations while nothing else changes
+            let state = self.scheduler.state.lock().unwrap();
+            let _ = self.scheduler.changed.wait_timeout(state, Duration::from_secs(1)).unwrap();
+        }
+    }
+
+    // Returns the position in the queue if the solve can't start yet
+    fn try_start(&mut self) -> Option<usize> {
+        if self.running {
+            return None;
+        }
+        let mut state = self.scheduler.state.lock().unwrap();
+        match self.scheduler.try_start(&mut state, self) {
+            Ok(()) => {
+                self.running = true;
+                None
+            },
+            Err(position) => Some(position),
+        }
+    }
+}
+
+impl Drop for SolveSlot {
+    fn drop(&mut self) {
+        self.scheduler.release(self.id);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    use cubelib::solver::df_search::CancelToken;
+
+    use crate::scheduler::{Scheduler, SchedulerConfig, SolveSlot};
+
+    fn scheduler(max_concurrent: usize, max_per_client: usize) -> Arc<Scheduler> {
+        Scheduler::new(SchedulerConfig {
+            max_concurrent,
+            max_per_client,
+            ..SchedulerConfig::default()
+        })
+    }
+
+    fn enqueue(scheduler: &Arc<Scheduler>, client: &str) -> SolveSlot {
+        scheduler.enqueue(client.to_string(), Arc::new(CancelToken::default())).unwrap()
+    }
+
+    #[test]
+    fn test_skips_client_at_limit() {
+        let scheduler = scheduler(2, 1);
+        let mut first = enqueue(&scheduler, "a");
+        let mut second = enqueue(&scheduler, "a");
+        let mut third = enqueue(&scheduler, "b");
+        assert_eq!(None, first.try_start());
+        assert_eq!(Some(0), second.try_start());
+        assert_eq!(None, third.try_start());
+        drop(first);
+        assert_eq!(None, second.try_start());
+    }
+
+    #[test]
+    fn test_queue_positions() {
+        let scheduler = scheduler(1, 1);
+        let mut running = enqueue(&scheduler, "a");
+        assert_eq!(None, running.try_start());
+        let mut slots: Vec<SolveSlot> = ["b", "c", "d"].iter().map(|client| enqueue(&scheduler, client)).collect();
+        assert_eq!(vec![Some(0), Some(1), Some(2)], slots.iter_mut().map(|slot| slot.try_start()).collect::<Vec<_>>());
+
+        slots.remove(0);
+        assert_eq!(vec![Some(0), Some(1)], slots.iter_mut().map(|slot| slot.try_start()).collect::<Vec<_>>());
+        drop(running);
+        assert_eq!(vec![None, Some(0)], slots.iter_mut().map(|slot| slot.try_start()).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_release_on_drop() {
+        let scheduler = scheduler(1, 1);
+        let mut running = enqueue(&scheduler, "a");
+        assert_eq!(None, running.try_start());
+        let queued = enqueue(&scheduler, "b");
+        assert_eq!((1, 1), (scheduler.stats().running, scheduler.stats().queued));
+
+        drop(queued);
+        assert_eq!((1, 0), (scheduler.stats().running, scheduler.stats().queued));
+        drop(running);
+        assert_eq!((0, 0), (scheduler.stats().running, scheduler.stats().queued));
+    }
+
+    #[test]
+    fn test_queue_limits() {
+        let scheduler = Scheduler::new(SchedulerConfig {
+            max_concurrent: 1,
+            max_queued: 3,
+            max_queued_per_client: 2,
+            ..SchedulerConfig::default()
+        });
+        let _first = enqueue(&scheduler, "a");
+        let _second = enqueue(&scheduler, "a");
+        assert!(scheduler.enqueue("a".to_string(), Arc::new(CancelToken::default())).is_err());
+        let _third = enqueue(&scheduler, "b");
+        assert!(scheduler.enqueue("c".to_string(), Arc::new(CancelToken::default())).is_err());
+    }
+
+    #[test]
+    fn test_watchdog_cancels_after_time_limit() {
+        let scheduler = Scheduler::new(SchedulerConfig {
+            time_limit: Duration::from_millis(200),
+            ..SchedulerConfig::default()
+        });
+        let cancel_token = Arc::new(CancelToken::default());
+        let mut slot = scheduler.enqueue("a".to_string(), cancel_token.clone()).unwrap();
+        assert!(slot.wait_for_start(|_| {}));
+        assert!(!cancel_token.is_cancelled());
+        thread::sleep(Duration::from_millis(600));
+        assert!(cancel_token.is_cancelled());
+        assert_eq!(1, scheduler.stats().timed_out);
+    }
+}
@@ -0,0 +1,212 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use actix_web::{get, web, HttpRequest, HttpResponse, Responder};
+use actix_web::http::header;
+use actix_web::web::Query;
+use cubelib::steps::step::StepConfig;
+use cubelib_interface::{ApiError, DayCount, LengthCount, ScrambleCount, StatsResponse, StepsCount};
+use serde::Deserialize;
+
+use crate::AppData;
+use crate::db::{self, Connection, DateRange};
+use crate::error::{error_response, internal};
+
+#[derive(Deserialize)]
+pub struct StatsParameters {
+    from: Option<String>,
+    to: Option<String>,
+    #[serde(default = "default_limit")]
+    limit: usize,
+}
+
+fn default_limit() -> usize {
+    10
+}
+
+/// Requires the admin token in an `Authorization: Bearer <token>` header. Disabled if no admin token is configured
+#[get("/admin/stats")]
+pub async fn admin_stats(req: HttpRequest, app_data: web::Data<AppData>, params: Query<StatsParameters>) -> impl Responder {
+    if let Err(err) = authorize(&req, app_data.admin_token.as_deref()) {
+        return error_response(err);
+    }
+    let StatsParameters { from, to, limit } = params.into_inner();
+    let range = match date_range(from, to) {
+        Ok(range) => range,
+        Err(message) => return error_response(ApiError::InvalidRequest { message }),
+    };
+    let stats = web::block(move || {
+        let conn = app_data.pool.get().map_err(internal)?;
+        collect_stats(&conn, &range, limit).map_err(internal)
+    }).await;
+    match stats {
+        Ok(Ok(stats)) => HttpResponse::Ok().json(stats),
+        Ok(Err(err)) => error_response(err),
+        Err(err) => error_response(internal(err)),
+    }
+}
+
+fn authorize(req: &HttpRequest, admin_token: Option<&str>) -> Result<(), ApiError> {
+    let Some(admin_token) = admin_token else {
+        return Err(ApiError::Unauthorized { message: "Admin endpoints are disabled, no admin token is configured".to_string() });
+    };
+    let token = req.headers().get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    if token == Some(admin_token) {
+        Ok(())
+    } else {
+        Err(ApiError::Unauthorized { message: "Invalid admin token".to_string() })
+    }
+}
+
+pub fn date_range(from: Option<String>, to: Option<String>) -> Result<DateRange, String> {
+    for date in from.iter().chain(to.iter()) {
+        if !is_date(date) {
+            return Err(format!("Invalid date '{date}'. Expected YYYY-MM-DD"));
+        }
+    }
+    Ok(DateRange { from, to })
+}
+
+fn is_date(date: &str) -> bool {
+    let parts: Vec<&str> = date.split('-').collect();
+    if parts.len() != 3 || !parts.iter().zip([4, 2, 2]).all(|(part, len)| part.len() == len && part.chars().all(|c| c.is_ascii_digit())) {
+        return false;
+    }
+    let [year, month, day] = [parts[0], parts[1], parts[2]].map(|part| part.parse::<u32>().unwrap());
+    let leap_year = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let days = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if leap_year => 29,
+        2 => 28,
+        _ => return false,
+    };
+    (1..=days).contains(&day)
+}
+
+pub fn collect_stats(conn: &Connection, range: &DateRange, limit: usize) -> rusqlite::Result<StatsResponse> {
+    let requests_per_day = db::count_requests_per_day(conn, range)?.into_iter()
+        .map(|(date, requests)| DayCount { date, requests })
+        .collect();
+    let popular_scrambles = db::count_requests_per_canonical_scramble(conn, range, limit)?.into_iter()
+        .map(|(canonical_scramble_id, scramble, requests)| ScrambleCount { canonical_scramble_id, scramble, requests })
+        .collect();
+    // Settings differ in details like the quality, so they are grouped by their steps
+    let mut steps: HashMap<String, usize> = HashMap::new();
+    for (settings, requests) in db::count_requests_per_settings(conn, range)? {
+        *steps.entry(describe_steps(&settings)).or_default() += requests;
+    }
+    let mut popular_steps: Vec<StepsCount> = steps.into_iter()
+        .map(|(steps, requests)| StepsCount { steps, requests })
+        .collect();
+    popular_steps.sort_by(|a, b| b.requests.cmp(&a.requests).then_with(|| a.steps.cmp(&b.steps)));
+    popular_steps.truncate(limit);
+    let solution_lengths = db::count_requests_per_solution_length(conn, range)?.into_iter()
+        .map(|(length, requests)| LengthCount { length, requests })
+        .collect();
+    Ok(StatsResponse {
+        total_requests: db::count_requests(conn, range)?,
+        requests_per_day,
+        popular_scrambles,
+        popular_steps,
+        solution_lengths,
+    })
+}
+
+fn describe_steps(steps: &[StepConfig]) -> String {
+    steps.iter()
+        .map(|step| {
+            let kind = step.kind.to_string().to_uppercase();
+            match &step.substeps {
+                Some(substeps) if !substeps.is_empty() => format!("{kind}[{}]", substeps.join(",")),
+                _ => kind,
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" > ")
+}
+
+/// A plain text version of the statistics for the `stats` command
+pub fn format_report(stats: &StatsResponse) -> String {
+    let mut report = String::new();
+    let _ = writeln!(report, "Requests: {}", stats.total_requests);
+    let _ = writeln!(report, "\nRequests per day");
+    for day in &stats.requests_per_day {
+        let _ = writeln!(report, "  {}  {:>8}", day.date, day.requests);
+    }
+    let _ = writeln!(report, "\nMost requested scrambles");
+    for scramble in &stats.popular_scrambles {
+        let _ = writeln!(report, "  {:>8}  {}", scramble.requests, scramble.scramble);
+    }
+    let _ = writeln!(report, "\nMost requested steps");
+    for steps in &stats.popular_steps {
+        let _ = writeln!(report, "  {:>8}  {}", steps.requests, steps.steps);
+    }
+    let _ = writeln!(report, "\nShortest solution length");
+    for length in &stats.solution_lengths {
+        let _ = writeln!(report, "  {:>3}  {:>8}", length.length, length.requests);
+    }
+    report
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use cubelib::algs::Algorithm;
+    use cubelib::defs::StepKind;
+    use cubelib::steps::step::StepConfig;
+    use cubelib_interface::StepsCount;
+
+    use crate::db;
+    use crate::stats::{collect_stats, date_range};
+
+    #[test]
+    fn test_date_range() {
+        assert!(date_range(Some("2024-02-29".to_string()), Some("2026-12-31".to_string())).is_ok());
+        for date in ["2026-99-99", "2023-02-29", "2026-04-31", "2026-01-00", "2026-1-01", "20260101"] {
+            assert!(date_range(Some(date.to_string()), None).is_err(), "{date}");
+        }
+    }
+
+    #[test]
+    fn test_collect_stats() {
+        let pool = db::test_pool();
+        let conn = pool.get().unwrap();
+        let eo = StepConfig {
+            substeps: Some(vec!["ud".to_string()]),
+            ..StepConfig::new(StepKind::EO)
+        };
+        // The same steps with another quality
+        let eo_quality = StepConfig {
+            quality: 1000,
+            ..eo.clone()
+        };
+        let requests = [
+            ("R U F", vec![eo.clone()], 1709164799),
+            // The same state rotated by y
+            ("F U L", vec![eo_quality], 1709164800),
+            ("R U F", vec![eo, StepConfig::new(StepKind::DR)], 1709208000),
+            ("D2 B", vec![StepConfig::new(StepKind::EO)], 1709251200),
+        ];
+        for (scramble, steps, time) in requests {
+            db::record_request_at(&conn, &Algorithm::from_str(scramble).unwrap(), &steps, time).unwrap();
+        }
+
+        let stats = collect_stats(&conn, &date_range(None, None).unwrap(), 10).unwrap();
+        assert_eq!(4, stats.total_requests);
+        assert_eq!(vec![3, 1], stats.popular_scrambles.iter().map(|count| count.requests).collect::<Vec<_>>());
+        assert_eq!(vec![
+            StepsCount { steps: "EO[ud]".to_string(), requests: 2 },
+            StepsCount { steps: "EO".to_string(), requests: 1 },
+            StepsCount { steps: "EO[ud] > DR".to_string(), requests: 1 },
+        ], stats.popular_steps);
+
+        let stats = collect_stats(&conn, &date_range(Some("2024-02-29".to_string()), Some("2024-02-29".to_string())).unwrap(), 1).unwrap();
+        assert_eq!(2, stats.total_requests);
+        assert_eq!(1, stats.popular_steps.len());
+        assert_eq!(vec!["2024-02-29"], stats.requests_per_day.iter().map(|day| day.date.as_str()).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,207 @@
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use cubelib::defs::StepKind;
+use cubelib::steps::step::StepConfig;
+use cubelib::steps::tables::PruningTables333;
+use cubelib_interface::TableStatus;
+use log::info;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Table {
+    EO,
+    DR,
+    HTR,
+    FR,
+    FRLS,
+    FRFinish,
+    HTRFinish,
+    HTRLSFinish,
+}
+
+impl Table {
+    pub const ALL: [Table; 8] = [Table::EO, Table::DR, Table::HTR, Table::FR, Table::FRLS, Table::FRFinish, Table::HTRFinish, Table::HTRLSFinish];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Table::EO => "eo",
+            Table::DR => "dr",
+            Table::HTR => "htr",
+            Table::FR => "fr",
+            Table::FRLS => "frls",
+            Table::FRFinish => "fr-finish",
+            Table::HTRFinish => "htr-finish",
+            Table::HTRLSFinish => "htr-ls-finish",
+        }
+    }
+
+    fn is_loaded(&self, tables: &PruningTables333) -> bool {
+        match self {
+            Table::EO => tables.eo().is_some(),
+            Table::DR => tables.dr().is_some(),
+            Table::HTR => tables.htr().is_some() && tables.htr_subset().is_some(),
+            Table::FR => tables.fr().is_some(),
+            Table::FRLS => tables.fr_leave_slice().is_some(),
+            Table::FRFinish => tables.fr_finish().is_some(),
+            Table::HTRFinish => tables.htr_finish().is_some(),
+            Table::HTRLSFinish => tables.htr_leave_slice_finish().is_some(),
+        }
+    }
+
+    fn load(&self, tables: &mut PruningTables333) {
+        match self {
+            Table::EO => tables.gen_eo(),
+            Table::DR => tables.gen_dr(),
+            Table::HTR => tables.gen_htr(),
+            Table::FR => tables.gen_fr(),
+            Table::FRLS => tables.gen_fr_leave_slice(),
+            Table::FRFinish => tables.gen_fr_finish(),
+            Table::HTRFinish => tables.gen_htr_finish(),
+            Table::HTRLSFinish => tables.gen_htr_leave_slice_finish(),
+        }
+    }
+
+    /// The tables needed to solve `steps`. Mirrors [`cubelib::steps::solver::gen_tables`], DR needs the HTR tables to name subsets
+    pub fn required(steps: &[StepConfig]) -> Vec<Table> {
+        let mut required = vec![];
+        let mut previous = None;
+        for step in steps {
+            let tables: &[Table] = match (previous, step.kind.clone()) {
+                (_, StepKind::EO) => &[Table::EO],
+                (_, StepKind::DR) => &[Table::DR, Table::HTR],
+                (_, StepKind::HTR) => &[Table::HTR],
+                (_, StepKind::FR) => &[Table::FR],
+                (_, StepKind::FRLS) => &[Table::FRLS],
+                (Some(StepKind::FR), StepKind::FIN) | (Some(StepKind::FRLS), StepKind::FINLS) => &[Table::FRFinish],
+                (Some(StepKind::HTR), StepKind::FIN) => &[Table::HTRFinish],
+                (Some(StepKind::HTR), StepKind::FINLS) => &[Table::HTRLSFinish],
+                _ => &[],
+            };
+            for table in tables {
+                if !required.contains(table) {
+                    required.push(*table);
+                }
+            }
+            previous = Some(step.kind.clone());
+        }
+        required
+    }
+}
+
+impl FromStr for Table {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Table::ALL.into_iter()
+            .find(|table| table.name() == s.to_lowercase())
+            .ok_or_else(|| format!("Unknown table '{s}'. Expected one of {}", Table::ALL.map(|t| t.name()).join(", ")))
+    }
+}
+
+/// The pruning tables shared by all solves. Tables that weren't preloaded are loaded the first time a request needs them.
+///
+/// Running solves keep using the tables they started with, so missing tables are added to a copy of the current set
+/// instead of changing it. Tables are saved to disk once generated, which makes loading them again cheap.
+pub struct TableStore {
+    current: RwLock<Arc<PruningTables333>>,
+    preloaded: Vec<Table>,
+    // Only one request generates missing tables, the others wait for it
+    loading: Mutex<()>,
+    // How long the last load of every table took
+    load_times: Mutex<Vec<(Table, Duration)>>,
+}
+
+impl TableStore {
+    pub fn new(preload: Vec<Table>) -> Self {
+        let store = Self {
+            current: RwLock::new(Arc::new(PruningTables333::new())),
+            preloaded: preload,
+            loading: Mutex::new(()),
+            load_times: Mutex::new(vec![]),
+        };
+        let mut tables = PruningTables333::new();
+        store.load(&mut tables, &store.preloaded);
+        *store.current.write().unwrap() = Arc::new(tables);
+        store
+    }
+
+    pub fn current(&self) -> Arc<PruningTables333> {
+        self.current.read().unwrap().clone()
+    }
+
+    /// Returns tables containing everything required by `steps`, loading missing tables first
+    pub fn for_steps(&self, steps: &[StepConfig]) -> Arc<PruningTables333> {
+        let required = Table::required(steps);
+        let tables = self.current();
+        if required.iter().all(|table| table.is_loaded(&tables)) {
+            return tables;
+        }
+
+        let _loading = self.loading.lock().unwrap();
+        let tables = self.current();
+        let missing: Vec<Table> = required.into_iter()
+            .filter(|table| !table.is_loaded(&tables))
+            .collect();
+        if missing.is_empty() {
+            return tables;
+        }
+        info!("Loading tables {}", missing.iter().map(|t| t.name()).collect::<Vec<_>>().join(", "));
+        let mut loaded = tables.as_ref().clone();
+        self.load(&mut loaded, &missing);
+        let loaded = Arc::new(loaded);
+        *self.current.write().unwrap() = loaded.clone();
+        loaded
+    }
+
+    pub fn load_times(&self) -> Vec<(Table, Duration)> {
+        self.load_times.lock().unwrap().clone()
+    }
+
+    fn load(&self, tables: &mut PruningTables333, load: &[Table]) {
+        for table in load {
+            let start = Instant::now();
+            table.load(tables);
+            let duration = start.elapsed();
+            let mut load_times = self.load_times.lock().unwrap();
+            load_times.retain(|(loaded, _)| loaded != table);
+            load_times.push((*table, duration));
+        }
+    }
+
+    pub fn status(&self) -> Vec<TableStatus> {
+        let tables = self.current();
+        Table::ALL.into_iter()
+            .map(|table| TableStatus { name: table.name().to_string(), loaded: table.is_loaded(&tables) })
+            .collect()
+    }
+
+    /// Whether all tables that should be loaded at startup are
+    pub fn preloaded(&self) -> bool {
+        let tables = self.current();
+        self.preloaded.iter().all(|table| table.is_loaded(&tables))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use cubelib::defs::StepKind;
+    use cubelib::steps::step::StepConfig;
+
+    use crate::tables::{Table, TableStore};
+
+    #[test]
+    fn test_only_missing_tables_are_loaded() {
+        let store = TableStore::new(vec![Table::EO]);
+        let eo_load_time = store.load_times();
+        assert_eq!(vec![Table::EO], eo_load_time.iter().map(|(table, _)| *table).collect::<Vec<_>>());
+
+        let tables = store.for_steps(&[StepConfig::new(StepKind::EO), StepConfig::new(StepKind::DR)]);
+        assert!([Table::EO, Table::DR, Table::HTR].iter().all(|table| table.is_loaded(&tables)));
+        assert!(!Table::FR.is_loaded(&tables));
+        // The EO table was kept instead of being loaded again
+        let load_times = store.load_times();
+        assert_eq!(vec![Table::EO, Table::DR, Table::HTR], load_times.iter().map(|(table, _)| *table).collect::<Vec<_>>());
+        assert_eq!(eo_load_time[0], load_times[0]);
+    }
+}
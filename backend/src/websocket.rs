@@ -0,0 +1,354 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use actix_web::{get, web, HttpRequest, HttpResponse};
+use actix_ws::{Message, MessageStream, ProtocolError, Session};
+use cubelib::cube::Cube333;
+use cubelib::cube::turn::ApplyAlgorithm;
+use cubelib::solver::backend::SolveRequest;
+use cubelib::solver::df_search::CancelToken;
+use cubelib::solver::scoring::Ranking;
+use cubelib_interface::{ApiError, ClientMessage, ServerMessage, SolveLimits, SolverBackend, SolverRequest};
+use futures::channel::mpsc::{unbounded, UnboundedSender};
+use futures::{stream, StreamExt};
+use log::info;
+
+use crate::{AppData, db};
+use crate::cache::SolutionCache;
+use crate::controller::{add_comments, client_address, create_solver, solve_rounds_quality_doubling, BestSolution, RequestRecord};
+use crate::error::{internal, parse_scramble, solver_error};
+
+pub struct RunningSolve {
+    pub cancel_token: Arc<CancelToken>,
+    pub limits: Arc<Mutex<SolveLimits>>,
+}
+
+enum Event {
+    Client(Result<Message, ProtocolError>),
+    Server(ServerMessage),
+    Closed,
+}
+
+/// Lets a client run several solves over one connection, see [`ClientMessage`] and [`ServerMessage`].
+/// Closing the connection cancels all solves of the client
+#[get("/solve_ws")]
+pub async fn solve_ws(req: HttpRequest, body: web::Payload, app_data: web::Data<AppData>) -> actix_web::Result<HttpResponse> {
+    let (response, session, messages) = actix_ws::handle(&req, body)?;
+    actix_rt::spawn(run_session(session, messages, client_address(&req, &app_data.trusted_proxies), app_data));
+    Ok(response)
+}
+
+async fn run_session(mut session: Session, messages: MessageStream, client: String, app_data: web::Data<AppData>) {
+    // The solver threads report back through this channel, so only this task writes to the session
+    let (tx, rx) = unbounded();
+    let mut solves: HashMap<u64, RunningSolve> = HashMap::new();
+    let client_events = messages.map(Event::Client).chain(stream::iter([Event::Closed]));
+    let mut events = stream::select(client_events, rx.map(Event::Server));
+
+    while let Some(event) = events.next().await {
+        let reply = match event {
+            Event::Client(Ok(Message::Text(text))) => handle_message(&text, &mut solves, &tx, &client, &app_data),
+            Event::Client(Ok(Message::Ping(bytes))) => {
+                if session.pong(&bytes).await.is_err() {
+                    break;
+                }
+                None
+            },
+            Event::Client(Ok(Message::Close(_))) | Event::Client(Err(_)) | Event::Closed => break,
+            Event::Client(Ok(_)) => None,
+            Event::Server(message) => {
+                forget_finished(&message, &mut solves);
+                Some(message)
+            },
+        };
+        if let Some(reply) = reply {
+            if session.text(serde_json::to_string(&reply).unwrap()).await.is_err() {
+                break;
+            }
+        }
+    }
+
+    for solve in solves.values() {
+        solve.cancel_token.cancel();
+    }
+    let _ = session.close(None).await;
+}
+
+fn handle_message(text: &str, solves: &mut HashMap<u64, RunningSolve>, tx: &UnboundedSender<ServerMessage>, client: &str, app_data: &AppData) -> Option<ServerMessage> {
+    let message = match serde_json::from_str::<ClientMessage>(text) {
+        Ok(message) => message,
+        Err(err) => return Some(error_message(None, ApiError::InvalidRequest { message: err.to_string() })),
+    };
+    match message {
+        ClientMessage::Solve { id, request, backend, rank_by, limits } => {
+            if solves.contains_key(&id) {
+                return Some(error_message(Some(id), ApiError::InvalidRequest { message: format!("Solve {id} is already running") }));
+            }
+            let tx = tx.clone();
+            match start_solve(id, request, backend, rank_by, limits, "websocket", client, app_data, move |message| tx.unbounded_send(message).is_ok()) {
+                Ok(solve) => {
+                    solves.insert(id, solve);
+                    None
+                },
+                Err(err) => Some(error_message(Some(id), err)),
+            }
+        },
+        ClientMessage::UpdateLimits { id, limits } => match solves.get(&id) {
+            Some(solve) => {
+                *solve.limits.lock().unwrap() = limits;
+                None
+            },
+            None => Some(error_message(Some(id), ApiError::InvalidRequest { message: format!("Solve {id} is not running") })),
+        },
+        ClientMessage::Cancel { id } => match solves.get(&id) {
+            Some(solve) => {
+                solve.cancel_token.cancel();
+                None
+            },
+            None => Some(error_message(Some(id), ApiError::InvalidRequest { message: format!("Solve {id} is not running") })),
+        },
+    }
+}
+
+// Ids of solves that are done can be used again
+fn forget_finished(message: &ServerMessage, solves: &mut HashMap<u64, RunningSolve>) {
+    if let ServerMessage::Done { id, .. } = message {
+        solves.remove(id);
+    }
+}
+
+pub fn error_message(id: Option<u64>, error: ApiError) -> ServerMessage {
+    ServerMessage::Error { id, message: error.to_string(), error }
+}
+
+/// Starts a solve in a new thread, which reports its results to `send` with the same messages as the WebSocket.
+/// `send` returns false once nobody listens anymore, which stops the solve. `transport` is only used for metrics and logs
+pub fn start_solve<S: FnMut(ServerMessage) -> bool + Send + 'static>(id: u64, request: SolverRequest, backend: SolverBackend, rank_by: Option<String>, limits: SolveLimits, transport: &'static str, client: &str, app_data: &AppData, mut send: S) -> Result<RunningSolve, ApiError> {
+    let SolverRequest { steps, scramble } = request;
+    let scramble = parse_scramble(scramble.as_str())?;
+    let ranking = rank_by.as_deref()
+        .map(Ranking::from_str)
+        .transpose()
+        .map_err(|message| ApiError::InvalidRequest { message })?
+        .map(Arc::new);
+
+    let mut cube = Cube333::default();
+    cube.apply_alg(&scramble);
+
+    create_solver(backend, app_data.tables.current())
+        .check(&SolveRequest::new(cube, steps.clone()))
+        .map_err(solver_error)?;
+
+    let cancel_token = Arc::new(CancelToken::default());
+    let mut slot = app_data.scheduler.enqueue(client.to_string(), cancel_token.clone())
+        .map_err(|message| ApiError::Overloaded { message })?;
+
+    let conn = app_data.pool.get().map_err(internal)?;
+    let request_id = db::record_request(&conn, &scramble, &steps).map_err(internal)?;
+    let mut record = RequestRecord::new(app_data.pool.clone(), request_id);
+    let mut solve_metrics = app_data.metrics.start_solve(backend, transport);
+
+    info!("Solve request {id} for {scramble} using backend {backend:?} over {transport}");
+
+    let limits = Arc::new(Mutex::new(limits));
+    let table_store = app_data.tables.clone();
+    let cache = SolutionCache::new(app_data.pool.clone(), &cube, &steps, rank_by.as_deref());
+
+    let search_cancel_token = cancel_token.clone();
+    let search_limits = limits.clone();
+    thread::spawn(move || {
+        let tables = table_store.for_steps(&steps);
+        let solver = create_solver(backend, tables.clone());
+        let mut best = BestSolution::new(cube, ranking.clone());
+        let mut sent = 0;
+        // Earlier results are sent right away, even if the solve has to wait for other solves
+        let max_length = search_limits.lock().unwrap().max_length;
+        for mut solution in cache.solutions() {
+            record.add_solution(&solution);
+            if max_length.is_none_or(|max| solution.len() <= max) && best.improves(&solution) {
+                add_comments(cube, &mut solution, tables.as_ref());
+                sent += 1;
+                solve_metrics.cached_solution_sent();
+                send(ServerMessage::Solution { id, solution });
+            }
+        }
+        if search_limits.lock().unwrap().solution_count.is_some_and(|count| sent >= count) {
+            record.finish();
+            solve_metrics.finish(false);
+            send(ServerMessage::Done { id, cancelled: false });
+            return;
+        }
+        let started = slot.wait_for_start(|position| {
+            send(ServerMessage::Queued { id, position });
+        });
+        if !started {
+            solve_metrics.finish(true);
+            send(ServerMessage::Done { id, cancelled: true });
+            return;
+        }
+        send(ServerMessage::Started { id });
+        let first_quality = cache.searched_quality().map_or(0, |quality| quality * 2);
+        let round_limits = search_limits.clone();
+        let rounds = solve_rounds_quality_doubling(solver, cube, steps, ranking, first_quality, move || round_limits.lock().unwrap().max_length, search_cancel_token.clone());
+        for round in rounds {
+            let (quality, solution) = match round {
+                Ok(round) => round,
+                Err(err) => {
+                    send(error_message(Some(id), solver_error(err)));
+                    break;
+                }
+            };
+            if let Some(solution) = &solution {
+                cache.add_solution(solution);
+                record.add_solution(solution);
+            }
+            // A cancelled round might not have searched everything
+            if search_cancel_token.is_cancelled() {
+                break;
+            }
+            // The limits might have changed while the round was running
+            let limits = *search_limits.lock().unwrap();
+            if limits.max_length.is_none() {
+                cache.add_searched_quality(quality);
+            }
+            if let Some(mut solution) = solution {
+                if limits.max_length.is_none_or(|max| solution.len() <= max) && best.improves(&solution) {
+                    add_comments(cube, &mut solution, tables.as_ref());
+                    sent += 1;
+                    if !send(ServerMessage::Solution { id, solution }) {
+                        break;
+                    }
+                    solve_metrics.solution_sent();
+                }
+            }
+            if !send(ServerMessage::Progress { id, quality, solutions: sent }) {
+                break;
+            }
+            if limits.solution_count.is_some_and(|count| sent >= count) {
+                break;
+            }
+        }
+        drop(slot);
+        record.finish();
+        solve_metrics.finish(search_cancel_token.is_cancelled());
+        send(ServerMessage::Done { id, cancelled: search_cancel_token.is_cancelled() });
+    });
+
+    Ok(RunningSolve { cancel_token, limits })
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use cubelib::defs::StepKind;
+    use cubelib::steps::step::StepConfig;
+    use cubelib_interface::{ApiError, ClientMessage, ServerMessage, SolveLimits, SolverRequest};
+    use futures::channel::mpsc::{unbounded, UnboundedReceiver};
+    use futures::executor::block_on;
+    use futures::StreamExt;
+
+    use crate::scheduler::SchedulerConfig;
+    use crate::websocket::{forget_finished, handle_message, RunningSolve};
+
+    fn solve(id: u64, scramble: &str) -> String {
+        serde_json::to_string(&ClientMessage::Solve {
+            id,
+            request: SolverRequest {
+                scramble: scramble.to_string(),
+                steps: vec![StepConfig::new(StepKind::EO)],
+            },
+            backend: Default::default(),
+            rank_by: None,
+            limits: SolveLimits { max_length: None, solution_count: Some(1) },
+        }).unwrap()
+    }
+
+    // Returns the messages of a solve up to and including Done, like the session would send them
+    fn receive_until_done(rx: &mut UnboundedReceiver<ServerMessage>, solves: &mut HashMap<u64, RunningSolve>) -> Vec<ServerMessage> {
+        let mut messages = vec![];
+        while let Some(message) = block_on(rx.next()) {
+            forget_finished(&message, solves);
+            let done = matches!(message, ServerMessage::Done { .. });
+            messages.push(message);
+            if done {
+                break;
+            }
+        }
+        messages
+    }
+
+    fn not_running(reply: Option<ServerMessage>, expected_id: u64) {
+        match reply {
+            Some(ServerMessage::Error { id, error: ApiError::InvalidRequest { .. }, .. }) => assert_eq!(Some(expected_id), id),
+            reply => panic!("Expected an error, got {reply:?}"),
+        }
+    }
+
+    #[test]
+    fn test_solve() {
+        let app_data = crate::test_app_data(SchedulerConfig::default());
+        let (tx, mut rx) = unbounded();
+        let mut solves = HashMap::new();
+
+        assert_eq!(None, handle_message(&solve(1, "R U F"), &mut solves, &tx, "client", &app_data));
+        assert!(solves.contains_key(&1));
+        // Ids have to be unique while a solve runs
+        let duplicate = handle_message(&solve(1, "R U F"), &mut solves, &tx, "client", &app_data);
+        assert!(matches!(duplicate, Some(ServerMessage::Error { id: Some(1), .. })), "{duplicate:?}");
+
+        let messages = receive_until_done(&mut rx, &mut solves);
+        assert_eq!(Some(&ServerMessage::Done { id: 1, cancelled: false }), messages.last());
+        assert!(messages.contains(&ServerMessage::Started { id: 1 }));
+        assert_eq!(1, messages.iter().filter(|message| matches!(message, ServerMessage::Solution { id: 1, .. })).count());
+        assert!(solves.is_empty());
+
+        // The id is free again once the solve is done
+        assert_eq!(None, handle_message(&solve(1, "R U F"), &mut solves, &tx, "client", &app_data));
+        receive_until_done(&mut rx, &mut solves);
+    }
+
+    #[test]
+    fn test_invalid_messages() {
+        let app_data = crate::test_app_data(SchedulerConfig::default());
+        let (tx, _rx) = unbounded();
+        let mut solves = HashMap::new();
+
+        let reply = handle_message("{\"type\": \"bogus\"}", &mut solves, &tx, "client", &app_data);
+        assert!(matches!(reply, Some(ServerMessage::Error { id: None, error: ApiError::InvalidRequest { .. }, .. })), "{reply:?}");
+        let reply = handle_message(&solve(2, "R U X"), &mut solves, &tx, "client", &app_data);
+        assert!(matches!(reply, Some(ServerMessage::Error { id: Some(2), error: ApiError::InvalidScramble { .. }, .. })), "{reply:?}");
+        assert!(solves.is_empty());
+
+        let update = serde_json::to_string(&ClientMessage::UpdateLimits { id: 3, limits: SolveLimits::default() }).unwrap();
+        not_running(handle_message(&update, &mut solves, &tx, "client", &app_data), 3);
+        let cancel = serde_json::to_string(&ClientMessage::Cancel { id: 3 }).unwrap();
+        not_running(handle_message(&cancel, &mut solves, &tx, "client", &app_data), 3);
+    }
+
+    #[test]
+    fn test_update_limits_and_cancel() {
+        // Nothing can start, so the solve stays in the queue until it is cancelled
+        let app_data = crate::test_app_data(SchedulerConfig {
+            max_concurrent: 0,
+            ..SchedulerConfig::default()
+        });
+        let (tx, mut rx) = unbounded();
+        let mut solves = HashMap::new();
+        assert_eq!(None, handle_message(&solve(1, "R U F"), &mut solves, &tx, "client", &app_data));
+        assert_eq!(Some(ServerMessage::Queued { id: 1, position: 0 }), block_on(rx.next()));
+
+        let limits = SolveLimits { max_length: Some(5), solution_count: None };
+        let update = serde_json::to_string(&ClientMessage::UpdateLimits { id: 1, limits }).unwrap();
+        assert_eq!(None, handle_message(&update, &mut solves, &tx, "client", &app_data));
+        assert_eq!(limits, *solves[&1].limits.lock().unwrap());
+
+        let cancel = serde_json::to_string(&ClientMessage::Cancel { id: 1 }).unwrap();
+        assert_eq!(None, handle_message(&cancel, &mut solves, &tx, "client", &app_data));
+        let messages = receive_until_done(&mut rx, &mut solves);
+        assert_eq!(vec![ServerMessage::Done { id: 1, cancelled: true }], messages);
+        assert!(solves.is_empty());
+    }
+}
@@ -1,4 +1,6 @@
+use std::path::PathBuf;
 use clap::{Parser, Subcommand, ValueEnum};
+use cubelib::solver_new::trigger::RZPCase;
 use log::LevelFilter;
 use serde::Deserialize;
 
@@ -20,7 +22,9 @@ pub enum Commands {
     Solve(SolveCommand),
     Scramble,
     Invert(InvertCommand),
+    Show(ShowCommand),
     Download(DownloadCommand),
+    Triggers(TriggersCommand),
     Update
 }
 
@@ -30,6 +34,18 @@ pub struct InvertCommand {
     pub scramble: String,
 }
 
+#[derive(Parser)]
+pub struct ShowCommand {
+    #[arg(long = "style", help = "Terminal color style. Detected from the environment by default")]
+    pub style: Option<RenderStyle>,
+    #[arg(long = "svg", help = "Write the cube as an SVG image to this file instead of printing it")]
+    pub svg: Option<PathBuf>,
+    #[arg(long = "sticker-size", default_value_t = 30, help = "Sticker size in pixels for SVG output")]
+    pub sticker_size: usize,
+    #[arg(help = "Scramble to show (use '-' to read from stdin)")]
+    pub scramble: String,
+}
+
 #[derive(Parser)]
 pub struct SolveCommand {
     #[arg(short, long = "format", help="Solution output format")]
@@ -50,6 +66,30 @@ pub struct SolveCommand {
     pub scramble: String,
     #[arg(long = "backend", help = "Solver backend to use")]
     pub backend: Option<SolverBackend>,
+    #[arg(long = "show-cube", help = "Render the cube state after each step of every solution")]
+    pub show_cube: bool,
+    #[arg(long = "stats", help = "Print search statistics after solving. Only supported by the multi-path-channel backend")]
+    pub stats: bool,
+    #[arg(long = "threads", help = "Number of threads used by the multi-path-channel backend. Defaults to the number of available cores")]
+    pub threads: Option<usize>,
+    #[arg(long = "single-threaded", conflicts_with = "threads", help = "Run the multi-path-channel backend on a single thread. Solutions are always returned in the same order")]
+    pub single_threaded: bool,
+    #[arg(long = "deterministic", help = "Return solutions of the same length in a fixed order that doesn't depend on thread timing. Only supported by the multi-path-channel backend")]
+    pub deterministic: bool,
+    #[arg(long = "shortest", help = "Only return the shortest solutions for the given steps. Quality is ignored. Only supported by the multi-path-channel backend")]
+    pub shortest: bool,
+    #[arg(long = "rank-by", help = "Order solutions by the given criteria instead of by length, e.g. 'niss,eo-length' or '2*niss+dr-trigger'. Available scorers: length, niss, eo-length, dr-trigger, dr-subset")]
+    pub rank_by: Option<String>,
+    #[arg(long = "remote", help = "Solve on a cubelib backend at this URL instead of locally, e.g. http://localhost:8049. Solutions are printed as the backend finds better ones. Steps must be a plain sequence without step prototypes")]
+    pub remote: Option<String>,
+}
+
+#[derive(Parser)]
+pub struct TriggersCommand {
+    #[arg(short = 'l', long = "max-length", default_value_t = 4, help = "Maximum trigger length")]
+    pub max_length: usize,
+    #[arg(short = 'c', long = "case", help = "Only list triggers for this RZP case, e.g. 4c2e")]
+    pub case: Option<RZPCase>,
 }
 
 #[derive(Parser)]
@@ -95,5 +135,17 @@ pub enum SolutionFormat {
     #[default]
     Detailed,
     Compact,
-    Plain
+    Plain,
+    AlgCubing,
+    Twizzle,
+    Markdown,
+    Html,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RenderStyle {
+    Truecolor,
+    Ansi256,
+    Plain,
 }
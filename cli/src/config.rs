@@ -1,13 +1,18 @@
 use core::fmt;
 use std::collections::HashMap;
+use std::env;
+use std::io::IsTerminal;
 use std::str::FromStr;
 use std::string::ToString;
+use cubelib::cube::CubeColor;
 use cubelib::defs::StepKind;
+use cubelib::render::{ColorScheme, NetRenderer, Rgb, TerminalStyle};
+use cubelib::solver_new::executor::ExecutionMode;
 use serde::{Deserialize, Deserializer};
 use serde::de::Visitor;
 use serde_with::{serde_as, KeyValueMap, DeserializeAs};
 use serde_with::__private__::DeError;
-use crate::cli::{LogLevel, SolutionFormat, SolveCommand, SolverBackend};
+use crate::cli::{LogLevel, RenderStyle, SolutionFormat, SolveCommand, SolverBackend};
 
 #[derive(Debug, Deserialize)]
 pub struct CubelibConfig {
@@ -17,6 +22,8 @@ pub struct CubelibConfig {
     pub check_update: bool,
     #[serde(rename = "solver")]
     pub solver_config: SolverConfig,
+    #[serde(default)]
+    pub render: RenderConfig,
 }
 
 fn default_log_level() -> LogLevel {
@@ -37,6 +44,7 @@ impl Default for CubelibConfig {
             log: default_log_level(),
             check_update: true,
             solver_config: default_solver_config(),
+            render: RenderConfig::default(),
         }
     }
 }
@@ -58,9 +66,22 @@ pub struct SolverConfig {
     pub steps: String,
     #[serde(default = "default_backend")]
     pub backend: SolverBackend,
+    pub threads: Option<usize>,
+    pub rank_by: Option<String>,
+    pub remote: Option<String>,
     #[serde_as(as = "KeyValueMap<_>")]
     #[serde(default)]
-    prototypes: Vec<StepOverrideInternal>
+    prototypes: Vec<StepOverrideInternal>,
+    #[serde(skip)]
+    pub show_cube: bool,
+    #[serde(skip)]
+    pub stats: bool,
+    #[serde(skip)]
+    pub single_threaded: bool,
+    #[serde(default)]
+    pub deterministic: bool,
+    #[serde(skip)]
+    pub shortest: bool,
 }
 
 fn default_quality() -> usize {
@@ -99,6 +120,14 @@ impl Default for SolverConfig {
             steps: default_steps(),
             backend: default_backend(),
             prototypes: vec![],
+            show_cube: false,
+            stats: false,
+            threads: None,
+            single_threaded: false,
+            deterministic: false,
+            shortest: false,
+            rank_by: None,
+            remote: None,
         }
     }
 }
@@ -161,6 +190,82 @@ impl SolverConfig {
         if let Some(backend) = cmd.backend {
             self.backend = backend;
         }
+        if cmd.show_cube {
+            self.show_cube = true;
+        }
+        if cmd.stats {
+            self.stats = true;
+        }
+        if let Some(threads) = cmd.threads {
+            self.threads = Some(threads);
+        }
+        if cmd.single_threaded {
+            self.single_threaded = true;
+        }
+        if cmd.deterministic {
+            self.deterministic = true;
+        }
+        if cmd.shortest {
+            self.shortest = true;
+        }
+        if let Some(rank_by) = cmd.rank_by {
+            self.rank_by = Some(rank_by);
+        }
+        if let Some(remote) = cmd.remote {
+            self.remote = Some(remote);
+        }
+    }
+
+    pub fn execution_mode(&self) -> ExecutionMode {
+        if self.single_threaded {
+            ExecutionMode::SingleThreaded
+        } else {
+            self.threads
+                .map(ExecutionMode::Threads)
+                .unwrap_or_default()
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct RenderConfig {
+    pub style: Option<RenderStyle>,
+    #[serde(default)]
+    pub colors: HashMap<String, Rgb>,
+}
+
+impl RenderConfig {
+    pub fn color_scheme(&self) -> Result<ColorScheme, String> {
+        let mut scheme = ColorScheme::default();
+        for (name, rgb) in &self.colors {
+            let color = match name.to_lowercase().as_str() {
+                "white" => CubeColor::White,
+                "yellow" => CubeColor::Yellow,
+                "green" => CubeColor::Green,
+                "blue" => CubeColor::Blue,
+                "orange" => CubeColor::Orange,
+                "red" => CubeColor::Red,
+                x => return Err(format!("Unknown color '{x}'. Expected one of white, yellow, green, blue, orange, red")),
+            };
+            scheme.set(color, *rgb);
+        }
+        Ok(scheme)
+    }
+
+    // Explicit settings win, otherwise we follow the common NO_COLOR and COLORTERM conventions
+    pub fn terminal_style(&self, override_style: Option<RenderStyle>) -> TerminalStyle {
+        match override_style.or(self.style) {
+            Some(RenderStyle::Truecolor) => TerminalStyle::TrueColor,
+            Some(RenderStyle::Ansi256) => TerminalStyle::Ansi256,
+            Some(RenderStyle::Plain) => TerminalStyle::Plain,
+            None if env::var_os("NO_COLOR").is_some() || !std::io::stdout().is_terminal() => TerminalStyle::Plain,
+            None if env::var("COLORTERM").is_ok_and(|x| x == "truecolor" || x == "24bit") => TerminalStyle::TrueColor,
+            None => TerminalStyle::Ansi256,
+        }
+    }
+
+    pub fn renderer(&self, override_style: Option<RenderStyle>) -> Result<NetRenderer, String> {
+        Ok(NetRenderer::new(self.color_scheme()?, self.terminal_style(override_style)))
     }
 }
 
@@ -8,7 +8,7 @@ use std::fs::File;
 use std::io::{BufWriter, ErrorKind, Read, Write};
 use std::path::PathBuf;
 use std::str::FromStr;
-use std::sync::{LazyLock, Mutex};
+use std::sync::{Arc, LazyLock, Mutex};
 use std::time::Instant;
 use chrono::{TimeDelta, Utc};
 use clap::Parser;
@@ -16,14 +16,19 @@ use cubelib::algs::Algorithm;
 use cubelib::cube::*;
 use cubelib::cube::turn::InvertibleMut;
 use cubelib::defs::{NissSwitchType, StepKind};
-use cubelib::solver::df_search::CancelToken;
+use cubelib::render::{render_svg, NetRenderer};
+use cubelib::solver::backend::{IterStreamSolver, SolveRequest, Solver, SolverSteps};
 use cubelib::solver::lookup_table::InMemoryIndexTable;
-use cubelib::solver::solution::Solution;
-use cubelib::solver::stream;
-use cubelib::solver_new::util_steps::{FilterDup, FilterLastMoveNotPrime};
-use cubelib::steps::{eo, solver};
+use cubelib::solver::scoring::Ranking;
+use cubelib::solver::solution::{ApplySolution, Solution};
+use cubelib::solver_new::stats::SolverStats;
+use cubelib::solver_new::thread_util::WorkerContext;
+use cubelib::solver_new::backend::MultiPathChannelSolver;
+use cubelib::solver_new::trigger::TriggerLibrary;
+use cubelib::steps::solver;
 use cubelib::steps::step::StepConfig;
 use cubelib::steps::tables::PruningTables333;
+use cubelib_interface::SolverRequest;
 use indicatif::ProgressStyle;
 use log::{debug, error, info, log, warn};
 use regex::Regex;
@@ -34,8 +39,9 @@ use tempfile::TempDir;
 use zip::read::root_dir_common_filter;
 use zip::ZipArchive;
 use crate::cache::Cache;
-use crate::cli::{Cli, Commands, DownloadCommand, InvertCommand, LogLevel, SolutionFormat, SolveCommand, SolverBackend};
-use crate::config::{SolverConfig, CubelibConfig};
+use crate::cli::{Cli, Commands, DownloadCommand, InvertCommand, LogLevel, ShowCommand, SolutionFormat, SolveCommand, SolverBackend, TriggersCommand};
+use crate::config::{SolverConfig, CubelibConfig, RenderConfig};
+use crate::progress::SearchProgress;
 use crate::update::{fetch_latest, GithubRelease, UpdateError};
 
 mod cli;
@@ -43,6 +49,8 @@ mod steps;
 mod config;
 mod update;
 mod cache;
+mod progress;
+mod remote;
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 const CACHE: LazyLock<Mutex<Cache>> = LazyLock::new(||Mutex::new(Cache::default()));
@@ -131,10 +139,15 @@ fn main() {
     };
 
     match cli.command {
-        Commands::Solve(cmd) => solve(cmd, config.solver_config),
+        Commands::Solve(cmd) => if let Err(e) = solve(cmd, config.solver_config, &config.render) {
+            error!("{e}");
+            std::process::exit(1);
+        },
         Commands::Invert(cmd) => invert(cmd),
+        Commands::Show(cmd) => show(cmd, &config.render),
         Commands::Scramble => scramble(),
         Commands::Download(cmd) => download(cmd),
+        Commands::Triggers(cmd) => triggers(cmd),
         Commands::Update => update(latest_version),
     }
 }
@@ -147,8 +160,12 @@ fn scramble() {
     solver_config.solution_count = Some(1);
     solver_config.quality = 1000;
     solver_config.steps = "EO[max=7;niss=never] > DR[niss=never] > HTR[niss=never] > FIN[niss=never]".to_string();
+    solver_config.backend = SolverBackend::IterStream;
 
-    find_and_print_solutions_iter_stream(cube, solver_config);
+    if let Err(e) = find_and_print_solutions(cube, None, solver_config, None) {
+        error!("{e}");
+        std::process::exit(1);
+    }
 }
 
 fn check_update() -> Result<GithubRelease, UpdateError> {
@@ -336,80 +353,216 @@ fn invert(cmd: InvertCommand) {
     println!("{}", scramble);
 }
 
-fn solve(cmd: SolveCommand, mut config: SolverConfig) {
+fn triggers(cmd: TriggersCommand) {
+    let library = TriggerLibrary::generate(cmd.max_length);
+    for (case, triggers) in library.cases() {
+        if cmd.case.is_some_and(|c| c != *case) {
+            continue;
+        }
+        println!("{case}: {}", triggers.iter().map(|t| t.to_string()).collect::<Vec<_>>().join(", "));
+    }
+    if let Some(case) = cmd.case.filter(|c| library.case(*c).is_empty()) {
+        println!("No triggers for {case} up to length {}", cmd.max_length);
+    }
+}
+
+fn show(cmd: ShowCommand, render_config: &RenderConfig) {
     let scramble = read_scramble(&cmd.scramble);
-    let cube = scramble.into();
+    let cube: Cube333 = scramble.into();
+
+    if let Some(path) = cmd.svg {
+        let colors = match render_config.color_scheme() {
+            Ok(colors) => colors,
+            Err(e) => {
+                error!("Invalid color scheme. {e}");
+                return;
+            }
+        };
+        match fs::write(&path, render_svg(&cube, &colors, cmd.sticker_size)) {
+            Ok(_) => info!("Wrote cube to {path:?}"),
+            Err(e) => error!("Failed to write {path:?}. {e}"),
+        }
+    } else {
+        match render_config.renderer(cmd.style) {
+            Ok(renderer) => print!("{}", renderer.render(&cube)),
+            Err(e) => error!("Invalid color scheme. {e}"),
+        }
+    }
+}
+
+fn solve(cmd: SolveCommand, mut config: SolverConfig, render_config: &RenderConfig) -> Result<(), String> {
+    let scramble = read_scramble(&cmd.scramble);
+    let cube = (&scramble).into();
 
     config.merge_cli_parameters(cmd);
 
-    match config.backend {
-        SolverBackend::IterStream => find_and_print_solutions_iter_stream(cube, config),
-        SolverBackend::MultiPathChannel => find_and_print_solutions_multi_path_channel(cube, config),
+    let renderer = if config.show_cube {
+        Some(render_config.renderer(None).map_err(|e| format!("Invalid color scheme. {e}"))?)
+    } else {
+        None
+    };
+
+    if let Some(url) = config.remote.clone() {
+        return find_and_print_remote_solutions(&url, cube, &scramble, config, renderer.as_ref());
+    }
+    if config.stats && matches!(config.backend, SolverBackend::IterStream) {
+        warn!("--stats is not supported by the iter-stream backend");
+    }
+    if (config.threads.is_some() || config.single_threaded) && matches!(config.backend, SolverBackend::IterStream) {
+        warn!("Thread settings are ignored by the iter-stream backend");
     }
+    find_and_print_solutions(cube, Some(&scramble), config, renderer.as_ref())
 }
 
-fn find_and_print_solutions_iter_stream(cube: Cube333, config: SolverConfig) {
-    let mut tables = PruningTables333::new();
-
-    let steps = match parse_step_configs_iter_stream(&config) {
-        Ok(step_configs) => {
-              solver::gen_tables(&step_configs, &mut tables);
-                match solver::build_steps(step_configs, &tables) {
-                    Ok(steps) => steps,
-                    Err(e) => {
-                        error!("{e}");
-                        return;
-                    }
-                }
-        },
-        Err(e) => {
-            error!("Unable to parse steps config. {e}");
-            return;
+fn print_solution(cube: &Cube333, scramble: Option<&Algorithm>, solution: Solution, config: &SolverConfig, renderer: Option<&NetRenderer>) {
+    if let Some(renderer) = renderer {
+        print_cube_per_step(cube, &solution, renderer);
+    }
+    // Without a scramble the inverse of the solution gives us an equivalent setup
+    let scramble = scramble.cloned().unwrap_or_else(|| {
+        let mut alg: Algorithm = solution.clone().into();
+        alg.invert();
+        alg
+    });
+    match config.format {
+        SolutionFormat::Plain =>
+            println!("{}", Into::<Algorithm>::into(solution)),
+        SolutionFormat::Compact => {
+            let alg = Into::<Algorithm>::into(solution);
+            println!("{alg} ({})", alg.len());
         }
+        SolutionFormat::Detailed =>
+            println!("{}", solution),
+        SolutionFormat::AlgCubing =>
+            println!("{}", solution.to_alg_cubing_net_url(&scramble)),
+        SolutionFormat::Twizzle =>
+            println!("{}", solution.to_twizzle_url(&scramble)),
+        SolutionFormat::Markdown =>
+            println!("{}", solution.to_markdown(&scramble)),
+        SolutionFormat::Html =>
+            println!("{}", solution.to_html(&scramble)),
+    }
+}
+
+fn print_cube_per_step(cube: &Cube333, solution: &Solution, renderer: &NetRenderer) {
+    let mut partial = Solution::new();
+    for step in solution.steps.iter() {
+        partial.add_step(step.clone());
+        let mut state = *cube;
+        state.apply_solution(&partial);
+        println!("{} ({})", step.variant, step.alg);
+        println!("{}", renderer.render(&state));
+    }
+}
+
+fn find_and_print_solutions(cube: Cube333, scramble: Option<&Algorithm>, config: SolverConfig, renderer: Option<&NetRenderer>) -> Result<(), String> {
+    let stats = matches!(config.backend, SolverBackend::MultiPathChannel).then(SolverStats::new);
+    let solver = match config.backend {
+        SolverBackend::IterStream => iter_stream_solver(&config),
+        SolverBackend::MultiPathChannel => multi_path_channel_solver(cube, &config, stats.clone()),
     };
+    let (solver, steps) = solver.map_err(|e| format!("Unable to parse steps config. {e}"))?;
 
-    let cancel_token = CancelToken::default();
-    let solutions = cubelib::solver::solve_steps(cube, &steps, &cancel_token);
+    let ranking = config.rank_by.as_deref().map(Ranking::from_str).transpose()
+        .map_err(|e| format!("Unable to parse ranking. {e}"))?
+        .map(Arc::new);
+
+    let request = SolveRequest {
+        quality: config.quality,
+        min_length: config.min,
+        max_length: config.max,
+        solution_count: config.solution_count.or(if config.max.is_some() {
+            None
+        } else {
+            Some(1)
+        }),
+        all_solutions: config.all_solutions,
+        shortest_only: config.shortest,
+        ranking,
+        ..SolveRequest::new(cube, steps)
+    };
 
     info!("Generating solutions\n");
     let time = Instant::now();
 
-    let mut solutions: Box<dyn Iterator<Item=Solution>> = Box::new(solutions
-        .skip_while(|alg| alg.len() < config.min)
-        .take_while(|alg| config.max.map_or(true, |max| alg.len() <= max)));
+    let solutions = solver.solve(request).map_err(|e| e.to_string())?;
+    let progress = stats.clone().map(SearchProgress::start);
 
-    // For e.g. FR the direction of the last move always matters, so we can't filter if we're doing FR
-    let can_filter_last_move = steps.last().map(|(s, _)| s.kind() != StepKind::FR && s.kind() != StepKind::FIN).unwrap_or(true);
-    if !config.all_solutions && can_filter_last_move {
-        solutions = Box::new(solutions
-            .filter(|alg| eo::eo_config::filter_eo_last_moves_pure(&alg.clone().into())));
+    //The solutions are always sorted, so this just prints the shortest or best ranked ones
+    for solution in solutions {
+        match progress.as_ref() {
+            Some(progress) => progress.suspend(|| print_solution(&cube, scramble, solution, &config, renderer)),
+            None => print_solution(&cube, scramble, solution, &config, renderer),
+        }
     }
 
-    //We already generate a mostly duplicate free iterator, but sometimes the same solution is valid for different stages and that can cause duplicates.
-    let solutions = stream::distinct_algorithms(solutions);
-
-    let mut solutions: Box<dyn Iterator<Item=Solution>> = Box::new(solutions);
+    if let Some(progress) = progress {
+        progress.finish();
+    }
+    info!("Took {}ms", time.elapsed().as_millis());
+    if let Some(stats) = stats.filter(|_| config.stats) {
+        progress::print_stats(&stats.snapshot());
+    }
+    Ok(())
+}
 
-    if config.max.is_none() || config.solution_count.is_some() {
-        solutions = Box::new(solutions
-            .take(config.solution_count.unwrap_or(1)))
+fn find_and_print_remote_solutions(url: &str, cube: Cube333, scramble: &Algorithm, config: SolverConfig, renderer: Option<&NetRenderer>) -> Result<(), String> {
+    if config.stats || config.threads.is_some() || config.single_threaded || config.deterministic || config.shortest || config.all_solutions {
+        warn!("--stats, --threads, --single-threaded, --deterministic, --shortest and --all are ignored by remote solves");
     }
+    let steps = parse_step_configs_remote(&config).map_err(|e| format!("Unable to parse steps config. {e}"))?;
+    let request = SolverRequest {
+        scramble: scramble.to_string(),
+        steps,
+    };
 
-    //The iterator is always sorted, so this just prints the shortest solutions
-    for solution in solutions {
-        match config.format {
-            SolutionFormat::Plain =>
-                println!("{}", Into::<Algorithm>::into(solution)),
-            SolutionFormat::Compact => {
-                let alg = Into::<Algorithm>::into(solution);
-                println!("{alg} ({})", alg.len());
-            }
-            SolutionFormat::Detailed =>
-                println!("{}", solution)
+    info!("Generating solutions on {url}\n");
+    let time = Instant::now();
+    let mut printed = 0;
+    remote::solve_stream(url, &request, &config.backend, config.rank_by.as_deref(), |solution| {
+        let length = solution.len();
+        if length < config.min || config.max.is_some_and(|max| length > max) {
+            return true;
         }
+        print_solution(&cube, Some(scramble), solution, &config, renderer);
+        printed += 1;
+        config.solution_count.is_none_or(|count| printed < count)
+    })?;
+    info!("Took {}ms", time.elapsed().as_millis());
+    Ok(())
+}
+
+// Requests contain a plain sequence of steps, so only the iter-stream syntax works for both backends
+fn parse_step_configs_remote(config: &SolverConfig) -> Result<Vec<StepConfig>, String> {
+    if config.steps.contains(['|', '(', ')']) {
+        return Err("Parallel steps and groups are not supported by remote solves. Use a sequence like 'EO > DR > HTR > FIN'".to_string());
+    }
+    // Prototypes are only known locally, and the parser would send their names as unknown steps
+    let prototypes = config.get_merged_overrides();
+    if let Some(name) = config.steps.split('>')
+        .map(|step| step.split('[').next().unwrap_or_default().trim())
+        .find(|name| prototypes.contains_key(*name)) {
+        return Err(format!("Step prototype '{name}' from the config file is not supported by remote solves"));
     }
+    parse_step_configs_iter_stream(config)
+}
 
-    info!("Took {}ms", time.elapsed().as_millis());
+fn iter_stream_solver(config: &SolverConfig) -> Result<(Box<dyn Solver>, SolverSteps), String> {
+    let step_configs = parse_step_configs_iter_stream(config)?;
+    let mut tables = PruningTables333::new();
+    solver::gen_tables(&step_configs, &mut tables);
+    Ok((Box::new(IterStreamSolver::new(Arc::new(tables))), step_configs.into()))
+}
+
+fn multi_path_channel_solver(cube: Cube333, config: &SolverConfig, stats: Option<Arc<SolverStats>>) -> Result<(Box<dyn Solver>, SolverSteps), String> {
+    let (steps, last_step) = steps::parse_steps(&config.steps, &config.get_merged_overrides(), cube.get_cube_state())?;
+    let mut ctx = WorkerContext::default()
+        .with_execution(config.execution_mode())
+        .with_deterministic(config.deterministic);
+    if let Some(stats) = stats {
+        ctx = ctx.with_stats(stats);
+    }
+    Ok((Box::new(MultiPathChannelSolver::with_context(ctx)), SolverSteps::Group(steps, last_step)))
 }
 
 fn parse_step_configs_iter_stream(conf: &SolverConfig) -> Result<Vec<StepConfig>, String> {
@@ -490,70 +643,3 @@ fn parse_step_configs_iter_stream(conf: &SolverConfig) -> Result<Vec<StepConfig>
         })
         .collect()
 }
-
-fn find_and_print_solutions_multi_path_channel(cube: Cube333, config: SolverConfig) {
-    let cube_state = cube.get_cube_state();
-    let (mut steps, last_step) = match steps::parse_steps(&config.steps, &config.get_merged_overrides(), cube_state) {
-        Ok(x) => x,
-        Err(e) => {
-            error!("Unable to parse steps config. {e}");
-            return;
-        }
-    };
-
-    info!("Generating solutions\n");
-    let time = Instant::now();
-
-    let mut predicates = vec![];
-
-    let last_qt_diretion_relevant = match last_step {
-        StepKind::EO | StepKind::RZP | StepKind::DR | StepKind::HTR => false,
-        _ => true
-    };
-
-    if !config.all_solutions && !last_qt_diretion_relevant  {
-        predicates.push(FilterLastMoveNotPrime::new());
-    }
-    predicates.push(FilterDup::new());
-    steps.with_predicates(predicates);
-
-    if config.quality > 0 {
-        steps.apply_step_limit(config.quality);
-    }
-
-    let mut worker = steps.into_worker(cube);
-
-    let mut count = 0;
-    let max_length = config.solution_count.or(if config.max.is_some() {
-        None
-    } else {
-        Some(1)
-    });
-    while max_length.is_none() || max_length.unwrap() > count {
-        match worker.next() {
-            Some(solution) => {
-                if solution.len() < config.min {
-                    continue;
-                }
-                if config.max.map(|max|solution.len() > max).unwrap_or(false) {
-                    break
-                }
-                match config.format {
-                    SolutionFormat::Plain =>
-                        println!("{}", Into::<Algorithm>::into(solution)),
-                    SolutionFormat::Compact => {
-                        let alg = Into::<Algorithm>::into(solution);
-                        println!("{alg} ({})", alg.len());
-                    }
-                    SolutionFormat::Detailed =>
-                        println!("{}", solution)
-                }
-                count += 1;
-            },
-            None => break
-        }
-    }
-
-    info!("Took {}ms", time.elapsed().as_millis());
-}
-
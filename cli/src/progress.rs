@@ -0,0 +1,99 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::Duration;
+use cubelib::defs::StepKind;
+use cubelib::solver_new::stats::{SolverStats, SolverStatsSnapshot};
+use indicatif::{ProgressBar, ProgressStyle};
+
+pub struct SearchProgress {
+    bar: ProgressBar,
+    done: Arc<AtomicBool>,
+    updater: Option<JoinHandle<()>>,
+}
+
+impl SearchProgress {
+    // The spinner is drawn to stderr and indicatif hides it automatically if stderr is not a terminal
+    pub fn start(stats: Arc<SolverStats>) -> Self {
+        let bar = ProgressBar::new_spinner();
+        bar.set_style(ProgressStyle::with_template("{spinner} [{elapsed_precise}] {msg}").unwrap());
+        bar.enable_steady_tick(Duration::from_millis(100));
+        let done = Arc::new(AtomicBool::new(false));
+        let updater = {
+            let bar = bar.clone();
+            let done = done.clone();
+            thread::spawn(move || {
+                while !done.load(Ordering::Relaxed) {
+                    bar.set_message(progress_message(&stats.snapshot()));
+                    thread::sleep(Duration::from_millis(100));
+                }
+            })
+        };
+        Self {
+            bar,
+            done,
+            updater: Some(updater),
+        }
+    }
+
+    pub fn suspend<F: FnOnce() -> R, R>(&self, f: F) -> R {
+        self.bar.suspend(f)
+    }
+
+    pub fn finish(mut self) {
+        self.stop();
+    }
+
+    fn stop(&mut self) {
+        self.done.store(true, Ordering::Relaxed);
+        if let Some(updater) = self.updater.take() {
+            let _ = updater.join();
+        }
+        self.bar.finish_and_clear();
+    }
+}
+
+impl Drop for SearchProgress {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn progress_message(snapshot: &SolverStatsSnapshot) -> String {
+    // Parallel variants of the same step are summarized, otherwise wide configurations don't fit on one line
+    let mut lengths: BTreeMap<String, usize> = BTreeMap::new();
+    let mut order = vec![];
+    for step in &snapshot.steps {
+        let kind = StepKind::from(step.variant).to_string();
+        if !lengths.contains_key(&kind) {
+            order.push(kind.clone());
+        }
+        let length = lengths.entry(kind).or_default();
+        *length = (*length).max(step.length);
+    }
+    let lengths = order.iter()
+        .map(|kind| format!("{kind} {}", lengths[kind]))
+        .collect::<Vec<String>>()
+        .join(" · ");
+    format!("{lengths} | {} nodes", snapshot.total_nodes())
+}
+
+pub fn print_stats(snapshot: &SolverStatsSnapshot) {
+    let name_width = snapshot.steps.iter()
+        .map(|s| s.variant.to_string().len())
+        .chain(snapshot.predicates.iter().map(|p| p.name.len()))
+        .chain([9])
+        .max()
+        .unwrap_or(0);
+    eprintln!("{:name_width$}  {:>6}  {:>12}  {:>12}  {:>9}", "Step", "Length", "Nodes", "Pruned", "Solutions");
+    for step in &snapshot.steps {
+        eprintln!("{:name_width$}  {:>6}  {:>12}  {:>12}  {:>9}", step.variant.to_string(), step.length, step.nodes, step.pruned, step.solutions);
+    }
+    eprintln!();
+    eprintln!("{:name_width$}  {:>9}  {:>9}  {:>6}", "Predicate", "Accepted", "Rejected", "Closed");
+    for predicate in &snapshot.predicates {
+        eprintln!("{:name_width$}  {:>9}  {:>9}  {:>6}", predicate.name, predicate.accepted, predicate.rejected, predicate.closed);
+    }
+}
@@ -0,0 +1,71 @@
+use std::io::{BufRead, BufReader};
+use cubelib::solver::solution::Solution;
+use cubelib_interface::{ErrorResponse, SolverRequest, SolverResponse};
+use log::{debug, info};
+use serde::Serialize;
+use crate::cli::SolverBackend;
+
+#[derive(Serialize)]
+struct SolveStreamParameters<'a> {
+    backend: cubelib_interface::SolverBackend,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rank_by: Option<&'a str>,
+}
+
+/// Solves on a cubelib backend through its `/solve_stream` endpoint.
+/// The backend only sends solutions that are better than the previous one, and keeps searching until it hits its time limit.
+/// Returning false from `on_solution` closes the connection, which cancels the solve on the backend
+pub fn solve_stream<F: FnMut(Solution) -> bool>(url: &str, request: &SolverRequest, backend: &SolverBackend, rank_by: Option<&str>, mut on_solution: F) -> Result<(), String> {
+    let client = reqwest::blocking::Client::builder()
+        .user_agent(format!("cubelib/{}", crate::VERSION))
+        // Solves can take minutes
+        .timeout(None)
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    let url = format!("{}/solve_stream", url.trim_end_matches('/'));
+    let backend = match backend {
+        SolverBackend::IterStream => cubelib_interface::SolverBackend::IterStream,
+        SolverBackend::MultiPathChannel => cubelib_interface::SolverBackend::MultiPathChannel,
+    };
+    debug!("Sending solve request to {url}");
+    let resp = client.post(&url)
+        .query(&SolveStreamParameters { backend, rank_by })
+        .json(request)
+        .send()
+        .map_err(|e| format!("Request to {url} failed. {e}"))?;
+
+    if !resp.status().is_success() {
+        let status = resp.status();
+        return match resp.json::<ErrorResponse>() {
+            Ok(err) => Err(err.message),
+            Err(_) => Err(format!("HTTP error: {status}")),
+        };
+    }
+
+    for line in BufReader::new(resp).lines() {
+        let line = line.map_err(|e| format!("Connection to {url} lost. {e}"))?;
+        // The backend sends spaces to keep the connection alive
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let response: SolverResponse = serde_json::from_str(line)
+            .map_err(|e| format!("Unexpected response from the backend. {e}"))?;
+        if let Some(err) = response.error {
+            return Err(err.to_string());
+        }
+        if let Some(position) = response.queue_position {
+            info!("Waiting for other solves on the backend, position {position} in the queue");
+        }
+        if let Some(solution) = response.solution {
+            if !on_solution(solution) {
+                return Ok(());
+            }
+        }
+        if response.done {
+            break;
+        }
+    }
+    Ok(())
+}
@@ -1,19 +1,11 @@
 use std::collections::HashMap;
 use std::str::FromStr;
 use std::time::Duration;
-use cubelib::algs::Algorithm;
 use cubelib::defs::{NissSwitchType, StepKind};
 use cubelib::solver::lookup_table::{LoadFromDisk, MemoryMappedIndexTable, TableError};
-use cubelib::solver_new::ar::ARBuilder;
-use cubelib::solver_new::dr::{DRBuilder, RZPBuilder, RZPStep};
-use cubelib::solver_new::eo::EOBuilder;
-use cubelib::solver_new::finish::{DRFinishBuilder, FRFinishBuilder, HTRFinishBuilder};
-use cubelib::solver_new::fr::FRBuilder;
-use cubelib::solver_new::group::{StepGroup, StepPredicate};
-use cubelib::solver_new::htr::HTRBuilder;
+use cubelib::solver_new::build_step;
+use cubelib::solver_new::group::StepGroup;
 use cubelib::solver_new::util_cube::CubeState;
-use cubelib::solver_new::util_steps::{FilterFirstN, FilterFirstNStepVariant};
-use cubelib::solver_new::vr::VRStep;
 use cubelib::steps::finish::coords::{DR_FINISH_LS_SIZE, DR_FINISH_SIZE, DRFinishCoord, DRLeaveSliceFinishCoord};
 use cubelib::steps::step::StepConfig;
 use log::{debug, warn};
@@ -132,10 +124,6 @@ fn parse_step(p: Pair<Rule>, previous: Option<StepConfig>, prototypes: &HashMap<
     if !variants.is_empty() {
         step_prototype.substeps = Some(variants);
     }
-    let mut step_prototype_c = step_prototype.clone();
-    let limit = step_prototype.params.remove("step-limit");
-    let max_use = step_prototype.params.remove("max-use");
-
     let mut previous_kind = previous.as_ref().map(|s|s.kind.clone());
     debug!("{:?} -> {} (current state is {:?})", previous_kind, kind, cube_state);
     if previous_kind.is_none() {
@@ -152,100 +140,18 @@ fn parse_step(p: Pair<Rule>, previous: Option<StepConfig>, prototypes: &HashMap<
             debug!("Replacing previous state with {}", k);
         }
     }
-    let mut step = match (previous_kind, kind.clone()) {
-        (_, StepKind::EO) => Some(EOBuilder::try_from(step_prototype).map_err(|_|"Failed to parse EO step")?.build()),
-        (Some(StepKind::EO), StepKind::RZP) => None,
-        (Some(StepKind::RZP), StepKind::DR) => {
-            let triggers = step_prototype.params.remove("triggers").ok_or("Found RZP, but DR step has no triggers".to_string())?;
-            let rzp_builder = RZPBuilder::try_from(previous.unwrap()).map_err(|_|"Failed to parse RZP step")?;
-            Some(DRBuilder::try_from(step_prototype).map_err(|_|"Failed to parse DR step")?
-                .triggers(triggers.split(",")
-                    .map(Algorithm::from_str)
-                    .collect::<Result<_, _>>()
-                    .map_err(|_|"Unable to parse algorithm")?)
-                .rzp(rzp_builder)
-                .build())
-        },
-        (Some(StepKind::EO), StepKind::AR) => Some(ARBuilder::try_from(step_prototype).map_err(|_|"Failed to parse ARM step")?.build()),
-        (Some(StepKind::AR), StepKind::DR) => {
-            Some(DRBuilder::try_from(step_prototype).map_err(|_|"Failed to parse DR step")?
-                .from_ar()
-                .build())
-        },
-        (Some(StepKind::EO), StepKind::DR) => {
-            Some(match step_prototype.params.remove("triggers") {
-                None => DRBuilder::try_from(step_prototype).map_err(|_|"Failed to parse DR step")?.build(),
-                Some(triggers) => {
-                    let rzp = RZPStep::builder()
-                        .max_length(step_prototype.max.unwrap_or(3).min(3) as usize)
-                        .max_absolute_length(step_prototype.absolute_max.unwrap_or(6).min(6) as usize);
-                    DRBuilder::try_from(step_prototype).map_err(|_|"Failed to parse DR step")?
-                        .triggers(triggers.split(",")
-                            .map(Algorithm::from_str)
-                            .collect::<Result<_, _>>()
-                            .map_err(|_|"Unable to parse algorithm")?)
-                        .rzp(rzp)
-                        .build()
-                }
-            })
-        },
-        (Some(StepKind::DR), StepKind::HTR) => Some(HTRBuilder::try_from(step_prototype).map_err(|_|"Failed to parse HTR step")?.build()),
-        (Some(StepKind::HTR), StepKind::FR) | (Some(StepKind::HTR), StepKind::FRLS)  => Some(FRBuilder::try_from(step_prototype).map_err(|_|"Failed to parse FR step")?.build()),
-        (Some(StepKind::DR), StepKind::FIN) | (Some(StepKind::DR), StepKind::FINLS) => {
-            step_prototype.params.remove("htr-breaking");
+    let htr_breaking = step_prototype.params.get("htr-breaking").map(|x|bool::from_str(x.to_lowercase().as_str()).unwrap_or(false)).unwrap_or(false);
+    match (previous_kind.as_ref(), &kind) {
+        (Some(StepKind::DR), StepKind::FIN | StepKind::FINLS) => check_dr_table_preload(kind),
+        (Some(StepKind::HTR), StepKind::FIN | StepKind::FINLS) if htr_breaking => {
             check_dr_table_preload(kind);
-            Some(DRFinishBuilder::try_from(step_prototype).map_err(|_|"Failed to parse FIN step")?.build())
-        },
-        (Some(StepKind::FR), StepKind::FIN) => Some(FRFinishBuilder::try_from(step_prototype).map_err(|_|"Failed to parse FIN step")?.build()),
-        (Some(StepKind::FRLS), StepKind::FINLS) => Some(FRFinishBuilder::try_from(step_prototype).map_err(|_|"Failed to parse FIN step")?.build()),
-        (Some(StepKind::HTR), StepKind::FIN) | (Some(StepKind::HTR), StepKind::FINLS) => {
-            let dr_breaking = step_prototype.params.remove("htr-breaking").map(|x|bool::from_str(x.to_lowercase().as_str()).unwrap_or(false)).unwrap_or(false);
-            if dr_breaking {
-                check_dr_table_preload(kind);
-                debug!("Using HTR breaking finish");
-                Some(DRFinishBuilder::try_from(step_prototype).map_err(|_|"Failed to parse FIN step")?
-                    .from_htr()
-                    .build())
-            } else {
-                Some(HTRFinishBuilder::try_from(step_prototype).map_err(|_|"Failed to parse FIN step")?.build())
-            }
+            debug!("Using HTR breaking finish");
         },
-        (Some(StepKind::FINLS), StepKind::VR) => {
-            Some(VRStep::new(step_prototype.params.get("vr-in").and_then(|x|usize::from_str(x).ok()).unwrap_or(2), false))
-        },
-        // A bit cursed, as this just replaces the previously found VR by looking for another one
-        (Some(StepKind::VR), StepKind::FIN) => Some(VRStep::new(previous.unwrap().params.get("vr-in").and_then(|x|usize::from_str(x).ok()).unwrap_or(2), true)),
-        (Some(StepKind::FINLS), StepKind::FIN) => Some(VRStep::new(2, true)),
-        (None, x) => return Err(format!("{x:?} is not supported as a first step", )),
-        (Some(a), b) => return Err(format!("Step order {a:?} > {b:?} is not supported")),
-    };
-    if let Some(step) = step.as_mut() {
-        if let Some(max_use) = max_use {
-            let filters: Result<Vec<Box<dyn StepPredicate>>, String> = max_use.split(",")
-                .flat_map(|x|{
-                    x.split_once(":")
-                        .map(|(a, b)|{
-                            let kind = StepKind::from_str(a).unwrap();
-                            let n = usize::from_str(b).map_err(|_|"Failed to parse max use limit".to_string());
-                            n.map(|n|FilterFirstNStepVariant::new(kind, n))
-                        })
-                })
-                .collect();
-            step.with_predicates(filters?);
-        }
-        if let Some(limit) = limit {
-            step.with_predicates(vec![FilterFirstN::new(usize::from_str(limit.as_str()).map_err(|_|"Failed to parse step limit")?)]);
-        }
-    } else {
-        if let Some(limit) = limit {
-            step_prototype_c.params.insert("step-limit".to_string(), limit);
-        }
-        if let Some(max_use) = max_use {
-            step_prototype_c.params.insert("max-use".to_string(), max_use);
-        }
+        _ => {}
     }
-
-    Ok((step, step_prototype_c))
+    // EO is always the first step, even if the scramble already has EO
+    let previous = previous.or_else(|| previous_kind.filter(|_| step_prototype.kind != StepKind::EO).map(StepConfig::new));
+    build_step(previous.as_ref(), step_prototype)
 }
 
 fn check_dr_table_preload(kind: StepKind) {
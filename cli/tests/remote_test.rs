@@ -0,0 +1,136 @@
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::path::Path;
+use std::process::{Command, Output};
+use std::str::FromStr;
+use std::thread;
+use std::thread::JoinHandle;
+use cubelib::algs::Algorithm;
+use cubelib::cube::CubeAxis;
+use cubelib::defs::{StepKind, StepVariant};
+use cubelib::solver::solution::{Solution, SolutionStep};
+use cubelib_interface::{ApiError, ErrorResponse, SolverRequest, SolverResponse};
+use tempfile::TempDir;
+
+struct ReceivedRequest {
+    path: String,
+    body: SolverRequest,
+}
+
+// Answers a single request like the `/solve_stream` endpoint of the backend
+fn start_backend(status: &'static str, body: String) -> (String, JoinHandle<ReceivedRequest>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let handle = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream);
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line).unwrap();
+        let mut content_length = 0;
+        loop {
+            let mut header = String::new();
+            reader.read_line(&mut header).unwrap();
+            let header = header.trim();
+            if header.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    content_length = value.trim().parse().unwrap();
+                }
+            }
+        }
+        let mut request = vec![0; content_length];
+        reader.read_exact(&mut request).unwrap();
+
+        let mut stream = reader.into_inner();
+        write!(stream, "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}", body.len()).unwrap();
+        ReceivedRequest {
+            path: request_line.split_whitespace().nth(1).unwrap().to_string(),
+            body: serde_json::from_slice(&request).unwrap(),
+        }
+    });
+    (url, handle)
+}
+
+// The config file is read from the home directory, so every run gets its own
+fn run_cli(home: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_cubelib-cli"))
+        .env("HOME", home)
+        .env("USERPROFILE", home)
+        .args(["--log", "error"])
+        .arg("--no-check-update")
+        .arg("solve")
+        .args(args)
+        .output()
+        .expect("Failed to execute command")
+}
+
+fn eo_solution(alg: &str) -> Solution {
+    let mut solution = Solution::new();
+    solution.add_step(SolutionStep {
+        variant: StepVariant::EO(CubeAxis::FB),
+        alg: Algorithm::from_str(alg).unwrap(),
+        comment: String::new(),
+        slice_insertions: vec![],
+    });
+    solution
+}
+
+fn response_lines(responses: &[SolverResponse]) -> String {
+    responses.iter()
+        .map(|response| serde_json::to_string(response).unwrap() + "\n")
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[test]
+fn test_remote_solve() {
+    let responses = [
+        SolverResponse { solution: None, done: false, queue_position: Some(0), error: None },
+        SolverResponse { solution: Some(eo_solution("F R U")), done: false, queue_position: None, error: None },
+        SolverResponse { solution: Some(eo_solution("F")), done: false, queue_position: None, error: None },
+        SolverResponse { solution: None, done: true, queue_position: None, error: None },
+    ];
+    let (url, backend) = start_backend("200 OK", response_lines(&responses));
+    let home = TempDir::new().unwrap();
+
+    let output = run_cli(home.path(), &["--remote", &url, "--steps", "EO[fb;max=5] > DR[niss=never]", "--format", "compact", "--backend", "multi-path-channel", "--", "F'"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!("F R U (3)\nF (1)\n", String::from_utf8(output.stdout).unwrap());
+
+    let request = backend.join().unwrap();
+    assert!(request.path.starts_with("/solve_stream?backend=multi_path_channel"), "{}", request.path);
+    assert_eq!("F'", request.body.scramble);
+    assert_eq!(vec![StepKind::EO, StepKind::DR], request.body.steps.iter().map(|step| step.kind.clone()).collect::<Vec<_>>());
+    assert_eq!(Some(vec!["fb".to_string()]), request.body.steps[0].substeps);
+    assert_eq!(Some(5), request.body.steps[0].max);
+}
+
+#[test]
+fn test_remote_error() {
+    let error = ApiError::InvalidRequest { message: "Unsupported steps".to_string() };
+    let (url, backend) = start_backend("400 Bad Request", serde_json::to_string(&ErrorResponse::from(error)).unwrap());
+    let home = TempDir::new().unwrap();
+
+    let output = run_cli(home.path(), &["--remote", &url, "--steps", "EO", "--", "F'"]);
+    assert!(!output.status.success());
+    let log = String::from_utf8_lossy(&output.stdout) + String::from_utf8_lossy(&output.stderr);
+    assert!(log.contains("Unsupported steps"), "{log}");
+    backend.join().unwrap();
+}
+
+#[test]
+fn test_unsupported_steps_are_rejected() {
+    let home = TempDir::new().unwrap();
+    fs::create_dir_all(home.path().join(".cubelib")).unwrap();
+    fs::write(home.path().join(".cubelib/config.toml"), "[solver.prototypes.eoshort]\nparent = \"EO\"\nmax = 4\n").unwrap();
+    // Nothing listens on port 9, but the steps are checked before connecting
+    for steps in ["EO > (DR | AR > DR)", "eoshort > DR", "EO > DR[niss=never] | AR"] {
+        let output = run_cli(home.path(), &["--remote", "http://127.0.0.1:9", "--steps", steps, "--", "F'"]);
+        assert!(!output.status.success(), "{steps}");
+        let log = String::from_utf8_lossy(&output.stdout) + String::from_utf8_lossy(&output.stderr);
+        assert!(log.contains("not supported by remote solves"), "{steps}: {log}");
+    }
+}
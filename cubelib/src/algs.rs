@@ -4,7 +4,7 @@ use std::str::FromStr;
 
 use itertools::Itertools;
 use crate::cube::*;
-use crate::cube::turn::{ApplyAlgorithm, Invertible, InvertibleMut, Transformable, TransformableMut, TurnableMut};
+use crate::cube::turn::{ApplyAlgorithm, ApplySymmetry, Invertible, InvertibleMut, Transformable, TransformableMut, TurnableMut};
 
 #[derive(PartialEq, Eq, Hash)]
 pub struct Algorithm {
@@ -243,6 +243,9 @@ impl FromStr for Algorithm {
         if !current.is_empty() {
             moves.push(Turn333::from_str(current.as_str())?);
         }
+        if inverse {
+            return Err(());
+        }
         Ok(Algorithm {
             normal_moves: moves,
             inverse_moves,
@@ -271,6 +274,18 @@ impl TransformableMut for Algorithm {
     }
 }
 
+impl ApplySymmetry for Algorithm {
+    fn apply_symmetry<T: AsRef<Symmetry>>(&mut self, s: T) {
+        let s = s.as_ref();
+        for t in s.transformations().iter().cloned() {
+            self.transform(t);
+        }
+        if s.is_mirrored() {
+            self.mirror(CubeAxis::LR);
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use std::str::FromStr;
@@ -293,4 +308,13 @@ mod test {
         let alg = Algorithm::from_str("F U2 F F' U2 F").unwrap().canonicalize();
         assert_eq!("F2", alg.to_string())
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_parentheses() {
+        let alg = Algorithm::from_str("R (U) F").unwrap();
+        assert_eq!("R F (U)", alg.to_string());
+        assert!(Algorithm::from_str("R (U F").is_err());
+        assert!(Algorithm::from_str("R U) F").is_err());
+        assert!(Algorithm::from_str("R ((U) F)").is_err());
+    }
+}
@@ -0,0 +1,182 @@
+use crate::algs::Algorithm;
+use crate::cube::{Cube333, CubeAxis, CubeFace, Direction, Symmetry, Turn333};
+use crate::cube::turn::{ApplySymmetry, Invertible, InvertibleMut, TransformableMut};
+
+/// Describes how a cube state was mapped onto its canonical representative.
+/// The canonical state is obtained by optionally inverting the state, and then applying `symmetry`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct CanonicalTransformation {
+    pub symmetry: Symmetry,
+    pub inverted: bool,
+}
+
+impl CanonicalTransformation {
+    pub const IDENTITY: CanonicalTransformation = CanonicalTransformation {
+        symmetry: Symmetry::I,
+        inverted: false,
+    };
+
+    #[cfg(any(target_feature = "avx2", target_feature = "neon"))]
+    pub fn apply_to_cube(&self, cube: &mut Cube333) {
+        if self.inverted {
+            cube.invert();
+        }
+        cube.apply_symmetry(self.symmetry);
+    }
+
+    /// Maps an algorithm from the original orientation into the canonical one.
+    /// Applying the result of this to a solved cube yields the canonical state if `alg` was the original scramble.
+    pub fn map_to_canonical(&self, mut alg: Algorithm) -> Algorithm {
+        if self.inverted {
+            alg = swap_sides(alg);
+        }
+        alg.apply_symmetry(self.symmetry);
+        alg
+    }
+
+    /// Maps an algorithm from the canonical orientation back into the original one.
+    /// A solution to the canonical state becomes a solution to the original state.
+    pub fn map_from_canonical(&self, mut alg: Algorithm) -> Algorithm {
+        if self.symmetry.is_mirrored() {
+            alg.mirror(CubeAxis::LR);
+        }
+        for t in self.symmetry.transformations().iter().rev() {
+            alg.transform(t.invert());
+        }
+        if self.inverted {
+            alg = swap_sides(alg);
+        }
+        alg
+    }
+
+    /// The axis that `axis` of the original orientation becomes in the canonical one, e.g. for the EO axis of a step
+    pub fn map_axis_to_canonical(&self, axis: CubeAxis) -> CubeAxis {
+        axis_of(self.map_to_canonical(axis_turn(axis)))
+    }
+
+    pub fn map_axis_from_canonical(&self, axis: CubeAxis) -> CubeAxis {
+        axis_of(self.map_from_canonical(axis_turn(axis)))
+    }
+}
+
+// Axes are mapped the same way as the moves on them
+fn axis_turn(axis: CubeAxis) -> Algorithm {
+    let face = match axis {
+        CubeAxis::UD => CubeFace::Up,
+        CubeAxis::FB => CubeFace::Front,
+        CubeAxis::LR => CubeFace::Right,
+    };
+    Algorithm { normal_moves: vec![Turn333::new(face, Direction::Clockwise)], inverse_moves: vec![] }
+}
+
+fn axis_of(alg: Algorithm) -> CubeAxis {
+    alg.normal_moves.iter().chain(alg.inverse_moves.iter()).next().unwrap().face.get_axis()
+}
+
+// Solving the inverse state with normal moves is the same as solving the normal state with inverse moves
+fn swap_sides(alg: Algorithm) -> Algorithm {
+    Algorithm {
+        normal_moves: alg.inverse_moves,
+        inverse_moves: alg.normal_moves,
+    }
+}
+
+#[cfg(any(target_feature = "avx2", target_feature = "neon"))]
+impl Cube333 {
+    /// Returns the canonical representative of this state under all 48 symmetries and inversion,
+    /// together with the transformation that maps this state onto it.
+    /// All states that are equivalent up to rotation, mirroring and inversion share the same representative.
+    pub fn canonical(&self) -> (Cube333, CanonicalTransformation) {
+        self.canonical_with(true)
+    }
+
+    /// Like [`Cube333::canonical`], but only considers the inverse state if `allow_inversion` is set.
+    /// Needed if solutions to the canonical state can't be transferred with inversion, e.g. because they may not use NISS
+    pub fn canonical_with(&self, allow_inversion: bool) -> (Cube333, CanonicalTransformation) {
+        let mut inverse = *self;
+        inverse.invert();
+        [(self, false), (&inverse, true)].into_iter()
+            .filter(|(_, inverted)| allow_inversion || !inverted)
+            .flat_map(|(cube, inverted)| Symmetry::ALL.iter().map(move |s| {
+                let mut cube = *cube;
+                cube.apply_symmetry(s);
+                (cube, CanonicalTransformation { symmetry: *s, inverted })
+            }))
+            .min_by_key(|(cube, _)| cube.canonical_key())
+            .unwrap()
+    }
+
+    fn canonical_key(&self) -> [u64; 3] {
+        let [e0, e1] = self.edges.get_edges_raw();
+        [e0, e1, self.corners.get_corners_raw()]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+    use crate::algs::Algorithm;
+    use crate::cube::{Cube333, CubeAxis, Symmetry, Transformation333};
+    use crate::cube::turn::{ApplyAlgorithm, ApplySymmetry, InvertibleMut, TransformableMut};
+
+    const SCRAMBLE: &str = "R' U' F L U2 F2 U2 R2 D2 B' F' R2 F R2 U2 L2 U' B R B' L U B' R' U' F";
+
+    // The scramble rotated, mirrored, inverted and all of these combined
+    fn equivalent_scrambles(scramble: &Algorithm) -> [Algorithm; 4] {
+        let mut rotated = scramble.clone();
+        rotated.transform(Transformation333::X);
+        rotated.transform(Transformation333::Y);
+        let mut mirrored = scramble.clone();
+        mirrored.mirror(CubeAxis::LR);
+        let mut inverted = scramble.clone();
+        inverted.invert();
+        let mut all = scramble.clone();
+        all.apply_symmetry(Symmetry::BM2);
+        all.invert();
+        [rotated, mirrored, inverted, all]
+    }
+
+    #[test]
+    fn test_equivalent_states_share_representative() {
+        let scramble = Algorithm::from_str(SCRAMBLE).unwrap();
+        let (canonical, _) = Into::<Cube333>::into(&scramble).canonical();
+
+        for alg in equivalent_scrambles(&scramble) {
+            assert_eq!(canonical, Into::<Cube333>::into(&alg).canonical().0);
+        }
+    }
+
+    #[test]
+    fn test_transfer_solution() {
+        let scramble = Algorithm::from_str(SCRAMBLE).unwrap();
+        let [rotated, mirrored, inverted, all] = equivalent_scrambles(&scramble);
+        for (name, scramble) in [("original", scramble), ("rotated", rotated), ("mirrored", mirrored), ("inverted", inverted), ("all", all)] {
+            let (canonical, trans) = Into::<Cube333>::into(&scramble).canonical();
+
+            let canonical_scramble = trans.map_to_canonical(scramble.clone());
+            assert_eq!(canonical, Into::<Cube333>::into(&canonical_scramble), "{name}");
+
+            let mut canonical_solution = canonical_scramble.to_uninverted();
+            canonical_solution.invert();
+            let solution = trans.map_from_canonical(canonical_solution);
+
+            let mut cube = Cube333::default();
+            cube.apply_alg(&scramble);
+            cube.apply_alg(&solution);
+            assert_eq!(Cube333::default(), cube, "{name}");
+        }
+    }
+
+    #[test]
+    fn test_map_axis() {
+        let mut scramble = Algorithm::from_str(SCRAMBLE).unwrap();
+        scramble.transform(Transformation333::X);
+        let (_, trans) = Into::<Cube333>::into(&scramble).canonical_with(false);
+        assert!(!trans.inverted);
+        for axis in [CubeAxis::UD, CubeAxis::FB, CubeAxis::LR] {
+            assert_eq!(axis, trans.map_axis_from_canonical(trans.map_axis_to_canonical(axis)));
+        }
+        let x = super::CanonicalTransformation { symmetry: Symmetry::F0, inverted: false };
+        assert_eq!(CubeAxis::FB, x.map_axis_to_canonical(CubeAxis::UD));
+    }
+}
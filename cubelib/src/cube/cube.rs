@@ -135,7 +135,7 @@ impl Default for Cube333 {
     }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct Symmetry(pub(crate) bool, pub(crate) &'static [Transformation333]);
 
 impl Symmetry {
@@ -201,6 +201,29 @@ impl Symmetry {
 
     pub const I: Symmetry = Self::U0;
     pub const M: Symmetry = Self::UM0;
+
+    pub const ALL: [Symmetry; 48] = [
+        Self::U0, Self::U1, Self::U2, Self::U3,
+        Self::F0, Self::F1, Self::F2, Self::F3,
+        Self::B0, Self::B1, Self::B2, Self::B3,
+        Self::D0, Self::D1, Self::D2, Self::D3,
+        Self::L0, Self::L1, Self::L2, Self::L3,
+        Self::R0, Self::R1, Self::R2, Self::R3,
+        Self::UM0, Self::UM1, Self::UM2, Self::UM3,
+        Self::FM0, Self::FM1, Self::FM2, Self::FM3,
+        Self::BM0, Self::BM1, Self::BM2, Self::BM3,
+        Self::DM0, Self::DM1, Self::DM2, Self::DM3,
+        Self::LM0, Self::LM1, Self::LM2, Self::LM3,
+        Self::RM0, Self::RM1, Self::RM2, Self::RM3,
+    ];
+
+    pub fn is_mirrored(&self) -> bool {
+        self.0
+    }
+
+    pub fn transformations(&self) -> &'static [Transformation333] {
+        self.1
+    }
 }
 
 impl AsRef<Symmetry> for Symmetry {
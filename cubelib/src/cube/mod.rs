@@ -1,6 +1,7 @@
 use crate::cube::turn::{CubeOuterTurn, CubeTransformation};
 
 mod cube;
+mod canonical;
 mod cube_corners;
 mod cube_edges;
 pub mod turn;
@@ -10,8 +11,11 @@ pub type Turn333 = CubeOuterTurn;
 pub type Transformation333 = CubeTransformation;
 pub type CornerCube333 = cube_corners::CubeCornersOdd;
 pub type Symmetry = cube::Symmetry;
+pub type CanonicalTransformation = canonical::CanonicalTransformation;
 pub type Edge = turn::Edge;
 pub type Corner = turn::Corner;
+pub type EdgePosition = cube::EdgePosition;
+pub type CornerPosition = cube::CornerPosition;
 pub type EdgeCube333 = cube_edges::CenterEdgeCube;
 pub type CubeFace = turn::CubeFace;
 pub type CubeColor = turn::CubeColor;
@@ -2,6 +2,7 @@ use std::cmp::Ordering;
 use std::fmt::{Display, Formatter};
 use std::str::FromStr;
 use std::string::ToString;
+use std::sync::RwLock;
 use crate::cube::CubeAxis;
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -17,6 +18,7 @@ pub enum StepKind {
     FIN,
     FINLS,
     VR,
+    GEN,
     Other(String)
 }
 
@@ -86,11 +88,39 @@ pub enum StepVariant {
     HTRFINLS(CubeAxis),
     DRFIN(CubeAxis),
     DRFINLS(CubeAxis),
+    GEN,
+    /// A user defined step, see [`StepVariant::custom`]
+    Custom(u16),
 }
 
+static CUSTOM_STEP_NAMES: RwLock<Vec<String>> = RwLock::new(vec![]);
+
 impl StepVariant {
+    /// Returns the variant for a custom step with the given name. Calling this twice with the same name returns the same variant.
+    pub fn custom(name: &str) -> StepVariant {
+        let mut names = CUSTOM_STEP_NAMES.write().unwrap();
+        let id = match names.iter().position(|n| n == name) {
+            Some(id) => id,
+            None => {
+                names.push(name.to_string());
+                names.len() - 1
+            }
+        };
+        StepVariant::Custom(id as u16)
+    }
+
+    fn custom_name(id: u16) -> String {
+        CUSTOM_STEP_NAMES.read().unwrap()
+            .get(id as usize)
+            .cloned()
+            .unwrap_or_else(|| format!("custom{id}"))
+    }
+
     pub(crate) fn can_solve_next(&self, other: &Self) -> bool {
         match (self, other) {
+            // We don't know anything about custom steps, so we rely on the cube state checks of the next step
+            (StepVariant::Custom(_), _) | (_, StepVariant::Custom(_)) => true,
+            (_, StepVariant::GEN) => true,
             (StepVariant::EO(x), StepVariant::DR { eo_axis, .. }) if eo_axis == x => true,
             (StepVariant::EO(x), StepVariant::RZP { eo_axis, .. }) if eo_axis == x => true,
             (StepVariant::EO(x), StepVariant::AR { eo_axis, .. }) if eo_axis == x => true,
@@ -136,6 +166,8 @@ impl Display for StepVariant {
             StepVariant::FRLS(fr) => write!(f, "frls{}", fr.name()),
             StepVariant::FRFINLS(ls) | StepVariant::HTRFINLS(ls) | StepVariant::DRFINLS(ls) => write!(f, "finls-{}", ls.name()),
             StepVariant::HTRFIN | StepVariant::DRFIN(_) | StepVariant::FRFIN(_) => write!(f, "fin"),
+            StepVariant::GEN => write!(f, "gen"),
+            StepVariant::Custom(id) => write!(f, "{}", Self::custom_name(*id)),
         }
     }
 }
@@ -156,6 +188,8 @@ impl From<StepVariant> for StepKind {
             StepVariant::HTRFINLS(_) => Self::FINLS,
             StepVariant::DRFIN(_) => Self::FIN,
             StepVariant::DRFINLS(_) => Self::FINLS,
+            StepVariant::GEN => Self::GEN,
+            StepVariant::Custom(id) => Self::Other(StepVariant::custom_name(id)),
         }
     }
 }
@@ -175,6 +209,7 @@ impl FromStr for StepKind {
             "finish" | "fin" => Ok(Self::FIN),
             "finls" => Ok(Self::FINLS),
             "vr" => Ok(Self::VR),
+            "gen" => Ok(Self::GEN),
             x=> Ok(Self::Other(x.to_string()))
         }
     }
@@ -193,6 +228,7 @@ impl Into<String> for StepKind {
             StepKind::FIN => "finish".to_string(),
             StepKind::FINLS => "finls".to_string(),
             StepKind::VR => "vr".to_string(),
+            StepKind::GEN => "gen".to_string(),
             StepKind::Other(x) => x,
         }
     }
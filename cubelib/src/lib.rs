@@ -1,6 +1,7 @@
 #![feature(lazy_type_alias)]
 #![feature(lock_value_accessors)]
 #![feature(portable_simd)]
+#![feature(coroutines, coroutine_trait)]
 #![allow(incomplete_features)]
 extern crate core;
 
@@ -19,3 +20,4 @@ pub mod solver;
 mod wasm_util;
 pub mod cube;
 pub mod solver_new;
+pub mod render;
@@ -0,0 +1,235 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use crate::cube::{Cube333, CubeColor, CubeFace};
+
+// Position of each face in the unfolded net, in units of faces.
+//       U
+//    L  F  R  B
+//       D
+const NET_LAYOUT: [(CubeFace, usize, usize); 6] = [
+    (CubeFace::Up, 1, 0),
+    (CubeFace::Left, 0, 1),
+    (CubeFace::Front, 1, 1),
+    (CubeFace::Right, 2, 1),
+    (CubeFace::Back, 3, 1),
+    (CubeFace::Down, 1, 2),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde_support", derive(serde_with::DeserializeFromStr, serde_with::SerializeDisplay))]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl Rgb {
+    fn to_ansi256(self) -> u8 {
+        let scale = |c: u8| ((c as u16 * 5 + 127) / 255) as u8;
+        16 + 36 * scale(self.0) + 6 * scale(self.1) + scale(self.2)
+    }
+}
+
+impl FromStr for Rgb {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = s.strip_prefix('#').unwrap_or(s);
+        if hex.len() != 6 || !hex.is_ascii() {
+            return Err(format!("Invalid color '{s}'. Expected format #RRGGBB"));
+        }
+        let channel = |idx: usize| u8::from_str_radix(&hex[idx..idx + 2], 16)
+            .map_err(|_| format!("Invalid color '{s}'. Expected format #RRGGBB"));
+        Ok(Rgb(channel(0)?, channel(2)?, channel(4)?))
+    }
+}
+
+impl Display for Rgb {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.0, self.1, self.2)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ColorScheme {
+    colors: [Rgb; 7],
+}
+
+impl ColorScheme {
+    pub const STANDARD: ColorScheme = ColorScheme {
+        colors: [
+            Rgb(0xFF, 0xFF, 0xFF), // White
+            Rgb(0xFF, 0xD5, 0x00), // Yellow
+            Rgb(0x00, 0x9B, 0x48), // Green
+            Rgb(0x00, 0x46, 0xAD), // Blue
+            Rgb(0xFF, 0x58, 0x00), // Orange
+            Rgb(0xB7, 0x12, 0x34), // Red
+            Rgb(0x40, 0x40, 0x40), // None
+        ],
+    };
+
+    pub fn get(&self, color: CubeColor) -> Rgb {
+        self.colors[color as usize]
+    }
+
+    pub fn set(&mut self, color: CubeColor, rgb: Rgb) {
+        self.colors[color as usize] = rgb;
+    }
+}
+
+impl Default for ColorScheme {
+    fn default() -> Self {
+        Self::STANDARD
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TerminalStyle {
+    #[default]
+    TrueColor,
+    Ansi256,
+    Plain,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct NetRenderer {
+    pub colors: ColorScheme,
+    pub style: TerminalStyle,
+}
+
+impl NetRenderer {
+    pub fn new(colors: ColorScheme, style: TerminalStyle) -> Self {
+        Self { colors, style }
+    }
+
+    /// Renders the cube as an unfolded net for terminal output. Every line ends with a newline.
+    pub fn render(&self, cube: &Cube333) -> String {
+        let facelets = cube.get_facelets();
+        let mut out = String::new();
+        for row in 0..9 {
+            let (face_row, sticker_row) = (row / 3, row % 3);
+            let faces: Vec<_> = NET_LAYOUT.iter()
+                .filter(|(_, _, y)| *y == face_row)
+                .collect();
+            let last_column = faces.iter().map(|(_, x, _)| *x).max().unwrap_or(0);
+            for column in 0..=last_column {
+                match faces.iter().find(|(_, x, _)| *x == column) {
+                    Some((face, _, _)) => {
+                        for sticker in 0..3 {
+                            out.push_str(&self.sticker(facelets[*face][sticker_row * 3 + sticker]));
+                        }
+                    },
+                    None => out.push_str("      "),
+                }
+                if column < last_column {
+                    out.push(' ');
+                }
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    fn sticker(&self, color: CubeColor) -> String {
+        let Rgb(r, g, b) = self.colors.get(color);
+        match self.style {
+            TerminalStyle::TrueColor => format!("\x1b[48;2;{r};{g};{b}m  \x1b[0m"),
+            TerminalStyle::Ansi256 => format!("\x1b[48;5;{}m  \x1b[0m", self.colors.get(color).to_ansi256()),
+            TerminalStyle::Plain => format!("{} ", color_letter(color)),
+        }
+    }
+}
+
+fn color_letter(color: CubeColor) -> char {
+    match color {
+        CubeColor::White => 'W',
+        CubeColor::Yellow => 'Y',
+        CubeColor::Green => 'G',
+        CubeColor::Blue => 'B',
+        CubeColor::Orange => 'O',
+        CubeColor::Red => 'R',
+        CubeColor::None => '.',
+    }
+}
+
+/// Renders the cube as an unfolded net in SVG format. `sticker_size` is the edge length of a single sticker in pixels.
+pub fn render_svg(cube: &Cube333, colors: &ColorScheme, sticker_size: usize) -> String {
+    let facelets = cube.get_facelets();
+    let gap = (sticker_size / 10).max(1);
+    let face_size = sticker_size * 3 + gap * 2;
+    let width = face_size * 4 + gap * 5;
+    let height = face_size * 3 + gap * 4;
+    let mut svg = format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" viewBox=\"0 0 {width} {height}\">\n");
+    for (face, x, y) in NET_LAYOUT {
+        let face_x = gap + x * (face_size + gap);
+        let face_y = gap + y * (face_size + gap);
+        for (idx, color) in facelets[face].iter().enumerate() {
+            let sticker_x = face_x + (idx % 3) * (sticker_size + gap);
+            let sticker_y = face_y + (idx / 3) * (sticker_size + gap);
+            svg.push_str(&format!(
+                "  <rect x=\"{sticker_x}\" y=\"{sticker_y}\" width=\"{sticker_size}\" height=\"{sticker_size}\" fill=\"{}\" stroke=\"#000000\"/>\n",
+                colors.get(*color)
+            ));
+        }
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+    use crate::algs::Algorithm;
+    use crate::cube::Cube333;
+    use crate::cube::turn::ApplyAlgorithm;
+    use crate::render::{NetRenderer, Rgb, TerminalStyle};
+
+    #[test]
+    fn test_plain_solved_net() {
+        let renderer = NetRenderer { style: TerminalStyle::Plain, ..Default::default() };
+        let net = renderer.render(&Cube333::default());
+        let lines: Vec<&str> = net.lines().collect();
+        assert_eq!(9, lines.len());
+        assert_eq!("       W W W ", lines[0]);
+        assert_eq!("O O O  G G G  R R R  B B B ", lines[4]);
+        assert_eq!("       Y Y Y ", lines[8]);
+    }
+
+    #[test]
+    fn test_plain_net_after_moves() {
+        let renderer = NetRenderer { style: TerminalStyle::Plain, ..Default::default() };
+        let mut cube = Cube333::default();
+        cube.apply_alg(&Algorithm::from_str("R").unwrap());
+        let expected = [
+            "       W W G ",
+            "       W W G ",
+            "       W W G ",
+            "O O O  G G Y  R R R  W B B ",
+            "O O O  G G Y  R R R  W B B ",
+            "O O O  G G Y  R R R  W B B ",
+            "       Y Y B ",
+            "       Y Y B ",
+            "       Y Y B ",
+        ];
+        assert_eq!(expected.to_vec(), renderer.render(&cube).lines().collect::<Vec<_>>());
+
+        // F moves rows of U and D and columns of L and R
+        let mut cube = Cube333::default();
+        cube.apply_alg(&Algorithm::from_str("F").unwrap());
+        let expected = [
+            "       W W W ",
+            "       W W W ",
+            "       O O O ",
+            "O O Y  G G G  W R R  B B B ",
+            "O O Y  G G G  W R R  B B B ",
+            "O O Y  G G G  W R R  B B B ",
+            "       R R R ",
+            "       Y Y Y ",
+            "       Y Y Y ",
+        ];
+        assert_eq!(expected.to_vec(), renderer.render(&cube).lines().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_parse_rgb() {
+        assert_eq!(Ok(Rgb(0xFF, 0x58, 0x00)), Rgb::from_str("#ff5800"));
+        assert!(Rgb::from_str("#ff58").is_err());
+    }
+}
@@ -0,0 +1,339 @@
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+use std::thread;
+
+use crate::cube::Cube333;
+use crate::defs::StepKind;
+use crate::solver::df_search::CancelToken;
+use crate::solver::scoring::Ranking;
+use crate::solver::solution::Solution;
+use crate::solver::stream;
+#[cfg(feature = "333eo")]
+use crate::steps::eo;
+use crate::steps::solver::build_steps;
+use crate::steps::step::StepConfig;
+use crate::steps::tables::PruningTables333;
+
+/// The steps a solver should go through
+pub enum SolverSteps {
+    /// A linear list of steps. Supported by all solvers
+    Configs(Vec<StepConfig>),
+    /// An already built step group, e.g. with steps running in parallel, and the kind of its last step.
+    /// Only supported by the multi-path-channel solver
+    #[cfg(feature = "multi-path-channel-solver")]
+    Group(crate::solver_new::group::StepGroup, StepKind),
+}
+
+impl From<Vec<StepConfig>> for SolverSteps {
+    fn from(value: Vec<StepConfig>) -> Self {
+        SolverSteps::Configs(value)
+    }
+}
+
+pub struct SolveRequest {
+    pub cube: Cube333,
+    pub steps: SolverSteps,
+    /// Influences how many solutions are calculated per step. 0 means there is no limit, which finds optimal solutions
+    pub quality: usize,
+    pub min_length: usize,
+    pub max_length: Option<usize>,
+    /// The maximum number of solutions returned. `None` returns solutions until the search space is exhausted
+    pub solution_count: Option<usize>,
+    /// Also return solutions that would usually get filtered out. E.g. an EO ending in F'
+    pub all_solutions: bool,
+    /// Stops the search once cancelled. Solvers never cancel this token themselves, so it can be shared between requests
+    pub cancel_token: Arc<CancelToken>,
+    /// Only return the shortest solutions for the given steps. Steps stop searching once they can't beat the best solution found so far.
+    /// `quality` is ignored, as limiting the number of solutions per step could skip the shortest solution
+    pub shortest_only: bool,
+    /// Orders the solutions by the ranking instead of by length. With a `max_length` all solutions up to that length are collected
+    /// before the first one is returned. Otherwise only solutions of the same length are ranked, and each length is returned
+    /// as soon as it has been searched. `solution_count` doesn't limit the search, so it doesn't change this
+    pub ranking: Option<Arc<Ranking>>,
+}
+
+impl SolveRequest {
+    pub fn new<S: Into<SolverSteps>>(cube: Cube333, steps: S) -> Self {
+        Self {
+            cube,
+            steps: steps.into(),
+            quality: 100,
+            min_length: 0,
+            max_length: None,
+            solution_count: None,
+            all_solutions: false,
+            cancel_token: Arc::new(CancelToken::default()),
+            shortest_only: false,
+            ranking: None,
+        }
+    }
+
+    fn last_step(&self) -> Option<StepKind> {
+        match &self.steps {
+            SolverSteps::Configs(steps) => steps.last().map(|x| x.kind.clone()),
+            #[cfg(feature = "multi-path-channel-solver")]
+            SolverSteps::Group(_, kind) => Some(kind.clone()),
+        }
+    }
+
+    // For e.g. FR the direction of the last move always matters, so we can only filter in some cases
+    pub(crate) fn filter_last_move(&self) -> bool {
+        !self.all_solutions && matches!(self.last_step(), Some(StepKind::EO | StepKind::RZP | StepKind::DR | StepKind::HTR))
+    }
+}
+
+/// Solutions found by a [`Solver`], shortest first unless the request has a [`Ranking`] (see [`SolveRequest::ranking`]). Dropping the stream stops the search.
+pub struct SolutionStream {
+    solutions: Box<dyn Iterator<Item = Solution> + Send>,
+    cancel_token: Arc<CancelToken>,
+}
+
+impl SolutionStream {
+    /// `solutions` has to stop once `cancel_token` is cancelled. Length and count limits of the request are applied here.
+    pub fn new<I: Iterator<Item = Solution> + Send + 'static>(solutions: I, request: &SolveRequest, cancel_token: Arc<CancelToken>) -> Self {
+        let min_length = request.min_length;
+        let max_length = request.max_length;
+        let solutions = solutions
+            .skip_while(move |s| s.len() < min_length)
+            .take_while(move |s| max_length.is_none_or(|max| s.len() <= max));
+        // Only a maximum length makes the search end on its own, the solution count is applied after ranking
+        let solutions: Box<dyn Iterator<Item = Solution> + Send> = match request.ranking.clone() {
+            Some(ranking) if max_length.is_some() => {
+                let cube = request.cube;
+                Box::new(std::iter::once(solutions).flat_map(move |solutions| ranking.sort(&cube, solutions.collect())))
+            },
+            Some(ranking) => Box::new(RankedByLength {
+                solutions: solutions.peekable(),
+                ranked: vec![].into_iter(),
+                ranking,
+                cube: request.cube,
+            }),
+            None => Box::new(solutions),
+        };
+        let solutions: Box<dyn Iterator<Item = Solution> + Send> = match request.solution_count {
+            Some(count) => Box::new(solutions.take(count)),
+            None => Box::new(solutions),
+        };
+        Self {
+            solutions,
+            cancel_token,
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.cancel_token.cancel();
+    }
+}
+
+/// Solutions arrive shortest first, so all solutions of one length can be ranked as soon as the first longer one is found
+struct RankedByLength<I: Iterator<Item = Solution>> {
+    solutions: std::iter::Peekable<I>,
+    ranked: std::vec::IntoIter<Solution>,
+    ranking: Arc<Ranking>,
+    cube: Cube333,
+}
+
+impl <I: Iterator<Item = Solution>> Iterator for RankedByLength<I> {
+    type Item = Solution;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(solution) = self.ranked.next() {
+            return Some(solution);
+        }
+        let first = self.solutions.next()?;
+        let len = first.len();
+        let mut bucket = vec![first];
+        while let Some(solution) = self.solutions.next_if(|s| s.len() == len) {
+            bucket.push(solution);
+        }
+        self.ranked = self.ranking.sort(&self.cube, bucket).into_iter();
+        self.ranked.next()
+    }
+}
+
+impl Iterator for SolutionStream {
+    type Item = Solution;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.cancel_token.is_cancelled() {
+            return None;
+        }
+        self.solutions.next()
+    }
+}
+
+impl Drop for SolutionStream {
+    fn drop(&mut self) {
+        self.cancel_token.cancel();
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Unsupported {
+    StepOrder(Option<StepKind>, StepKind),
+    Parameter(StepKind, String),
+    StepGroups,
+    ShortestOnly,
+}
+
+impl Display for Unsupported {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Unsupported::StepOrder(None, step) => write!(f, "{step} is not supported as a first step"),
+            Unsupported::StepOrder(Some(previous), step) => write!(f, "Step order {previous} > {step} is not supported"),
+            Unsupported::Parameter(step, name) => write!(f, "Parameter '{name}' is not supported for {step}"),
+            Unsupported::StepGroups => write!(f, "Parallel step groups are not supported"),
+            Unsupported::ShortestOnly => write!(f, "Searching only for the shortest solutions is not supported"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SolverError {
+    Unsupported(&'static str, Unsupported),
+    InvalidSteps(String),
+    /// The steps can't solve the cube at all, e.g. because it isn't in the group of a gen step
+    UnsolvableCube(String),
+}
+
+impl Display for SolverError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SolverError::Unsupported(solver, reason) => write!(f, "{reason} by the {solver} solver"),
+            SolverError::InvalidSteps(e) => write!(f, "{e}"),
+            SolverError::UnsolvableCube(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+/// Describes which step configurations a solver can handle, so callers can check a request before solving it
+#[derive(Debug, Clone)]
+pub struct SolverCapabilities {
+    pub step_orders: Vec<(Option<StepKind>, StepKind)>,
+    /// Step specific parameters in [`StepConfig::params`] the solver understands
+    pub parameters: Vec<&'static str>,
+    pub step_groups: bool,
+    pub shortest_only: bool,
+}
+
+impl SolverCapabilities {
+    pub fn supports_step_order(&self, previous: Option<&StepKind>, step: &StepKind) -> bool {
+        self.step_orders.iter().any(|(p, s)| p.as_ref() == previous && s == step)
+    }
+
+    pub fn check(&self, request: &SolveRequest) -> Result<(), Unsupported> {
+        if request.shortest_only && !self.shortest_only {
+            return Err(Unsupported::ShortestOnly);
+        }
+        match &request.steps {
+            SolverSteps::Configs(steps) => {
+                let mut previous = None;
+                for step in steps {
+                    if !self.supports_step_order(previous, &step.kind) {
+                        return Err(Unsupported::StepOrder(previous.cloned(), step.kind.clone()));
+                    }
+                    if let Some(name) = step.params.keys().find(|k| !self.parameters.contains(&k.as_str())) {
+                        return Err(Unsupported::Parameter(step.kind.clone(), name.clone()));
+                    }
+                    previous = Some(&step.kind);
+                }
+                Ok(())
+            }
+            #[cfg(feature = "multi-path-channel-solver")]
+            SolverSteps::Group(_, _) if self.step_groups => Ok(()),
+            #[cfg(feature = "multi-path-channel-solver")]
+            SolverSteps::Group(_, _) => Err(Unsupported::StepGroups),
+        }
+    }
+}
+
+pub trait Solver {
+    fn name(&self) -> &'static str;
+    fn capabilities(&self) -> SolverCapabilities;
+    fn solve(&self, request: SolveRequest) -> Result<SolutionStream, SolverError>;
+
+    fn check(&self, request: &SolveRequest) -> Result<(), SolverError> {
+        self.capabilities().check(request)
+            .map_err(|e| SolverError::Unsupported(self.name(), e))
+    }
+}
+
+/// The original solver. It evaluates steps lazily one after another, which keeps memory usage low,
+/// but doesn't support parallel step groups.
+pub struct IterStreamSolver {
+    tables: Arc<PruningTables333>,
+}
+
+impl IterStreamSolver {
+    /// `tables` must contain all tables required by the steps that will be solved, see [`crate::steps::solver::gen_tables`]
+    pub fn new(tables: Arc<PruningTables333>) -> Self {
+        Self {
+            tables
+        }
+    }
+}
+
+impl Solver for IterStreamSolver {
+    fn name(&self) -> &'static str {
+        "iter-stream"
+    }
+
+    fn capabilities(&self) -> SolverCapabilities {
+        SolverCapabilities {
+            step_orders: vec![
+                (None, StepKind::EO),
+                (Some(StepKind::EO), StepKind::RZP),
+                (Some(StepKind::EO), StepKind::DR),
+                (Some(StepKind::RZP), StepKind::DR),
+                (Some(StepKind::DR), StepKind::HTR),
+                (Some(StepKind::HTR), StepKind::FR),
+                (Some(StepKind::HTR), StepKind::FRLS),
+                (Some(StepKind::HTR), StepKind::FIN),
+                (Some(StepKind::HTR), StepKind::FINLS),
+                (Some(StepKind::FR), StepKind::FIN),
+                (Some(StepKind::FRLS), StepKind::FINLS),
+            ],
+            parameters: vec!["triggers", "subsets"],
+            step_groups: false,
+            shortest_only: false,
+        }
+    }
+
+    fn solve(&self, request: SolveRequest) -> Result<SolutionStream, SolverError> {
+        self.check(&request)?;
+        let mut configs = match &request.steps {
+            SolverSteps::Configs(steps) => steps.clone(),
+            #[cfg(feature = "multi-path-channel-solver")]
+            SolverSteps::Group(_, _) => unreachable!(),
+        };
+        for config in configs.iter_mut() {
+            config.quality = request.quality;
+        }
+        // Catch invalid configurations here, the search thread can't report them
+        build_steps(configs.clone(), &self.tables).map_err(SolverError::InvalidSteps)?;
+
+        let cancel_token = Arc::new(CancelToken::child_of(request.cancel_token.clone()));
+        let filter_last_move = request.filter_last_move();
+        let (tx, rc) = crossbeam::channel::bounded(1);
+        let tables = self.tables.clone();
+        let cube = request.cube;
+        let search_cancel_token = cancel_token.clone();
+        // The steps borrow the tables, so we run the whole search on its own thread
+        thread::spawn(move || {
+            let steps = build_steps(configs, &tables).unwrap();
+            let mut solutions: Box<dyn Iterator<Item = Solution>> = Box::new(crate::solver::solve_steps(cube, &steps, &search_cancel_token));
+            #[cfg(feature = "333eo")]
+            if filter_last_move {
+                solutions = Box::new(solutions
+                    .filter(|alg| eo::eo_config::filter_eo_last_moves_pure(&alg.clone().into())));
+            }
+            //We already generate a mostly duplicate free iterator, but sometimes the same solution is valid for different stages and that can cause duplicates.
+            for solution in stream::distinct_algorithms(solutions) {
+                if search_cancel_token.is_cancelled() || tx.send(solution).is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(SolutionStream::new(rc.into_iter(), &request, cancel_token))
+    }
+}
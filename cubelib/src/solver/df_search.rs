@@ -1,3 +1,4 @@
+use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use log::trace;
 use crate::algs::Algorithm;
@@ -8,10 +9,18 @@ use crate::solver::moveset::{Transition, TransitionTable};
 use crate::steps::step::{DefaultStepOptions, StepVariant};
 
 pub struct CancelToken {
-    cancelled: AtomicBool
+    cancelled: AtomicBool,
+    parent: Option<Arc<CancelToken>>,
 }
 
 impl CancelToken {
+    /// Creates a token that is cancelled together with `parent`, but can also be cancelled on its own
+    pub fn child_of(parent: Arc<CancelToken>) -> Self {
+        CancelToken {
+            cancelled: AtomicBool::new(false),
+            parent: Some(parent),
+        }
+    }
     pub fn cancel(&self) {
         self.cancelled.store(true, Ordering::Relaxed)
     }
@@ -19,7 +28,7 @@ impl CancelToken {
         self.cancelled.store(false, Ordering::Relaxed)
     }
     pub fn is_cancelled(&self) -> bool {
-        self.cancelled.load(Ordering::Relaxed)
+        self.cancelled.load(Ordering::Relaxed) || self.parent.as_ref().is_some_and(|p| p.is_cancelled())
     }
 }
 
@@ -27,6 +36,7 @@ impl Default for CancelToken {
     fn default() -> Self {
         CancelToken {
             cancelled: AtomicBool::new(false),
+            parent: None,
         }
     }
 }
@@ -0,0 +1,236 @@
+use std::fmt::Write;
+use crate::algs::Algorithm;
+use crate::cube::turn::Invertible;
+use crate::cube::Turn333;
+use crate::defs::StepKind;
+use crate::solver::solution::Solution;
+
+const ALG_CUBING_NET_URL: &str = "https://alg.cubing.net/";
+const TWIZZLE_URL: &str = "https://alpha.twizzle.net/edit/";
+
+struct StepLine {
+    name: String,
+    alg: Algorithm,
+    length: usize,
+    cancelled: usize,
+    total: usize,
+}
+
+impl Solution {
+    /// Builds an alg.cubing.net link that replays the scramble and this solution step by step.
+    /// Moves done on inverse are moved into the setup, so the viewer shows the same state as the detailed output.
+    pub fn to_alg_cubing_net_url(&self, scramble: &Algorithm) -> String {
+        let (setup, alg) = self.viewer_setup_and_alg(scramble);
+        // alg.cubing.net turns every '-' into a prime after decoding the URL, so comments like "5-2" need the HTML entity it reads as a hyphen
+        let alg = alg.replace('-', "&#45;");
+        format!("{ALG_CUBING_NET_URL}?setup={}&alg={}", percent_encode(&setup), percent_encode(&alg))
+    }
+
+    /// Builds a cubing.js twizzle link that replays the scramble and this solution step by step.
+    /// Moves done on inverse are moved into the setup, so the viewer shows the same state as the detailed output.
+    pub fn to_twizzle_url(&self, scramble: &Algorithm) -> String {
+        let (setup, alg) = self.viewer_setup_and_alg(scramble);
+        format!("{TWIZZLE_URL}?setup-alg={}&alg={}", percent_encode(&setup), percent_encode(&alg))
+    }
+
+    /// Renders a reconstruction with per step lengths as a Markdown document.
+    pub fn to_markdown(&self, scramble: &Algorithm) -> String {
+        let final_alg: Algorithm = self.clone().into();
+        let mut out = String::new();
+        writeln!(out, "**Scramble:** `{scramble}`\n").unwrap();
+        writeln!(out, "| Step | Moves | Length | Total |").unwrap();
+        writeln!(out, "|---|---|---|---|").unwrap();
+        for line in self.step_lines() {
+            writeln!(out, "| {} | `{}` | {} | {} |", line.name.replace('|', "\\|"), line.alg, line.length_string(), line.total).unwrap();
+        }
+        writeln!(out, "\n**Solution ({}):** `{final_alg}`\n", final_alg.len()).unwrap();
+        writeln!(out, "[alg.cubing.net]({}) · [twizzle]({})", self.to_alg_cubing_net_url(scramble), self.to_twizzle_url(scramble)).unwrap();
+        out
+    }
+
+    /// Renders a reconstruction with per step lengths as an HTML fragment.
+    pub fn to_html(&self, scramble: &Algorithm) -> String {
+        let final_alg: Algorithm = self.clone().into();
+        let mut out = String::new();
+        writeln!(out, "<div class=\"reconstruction\">").unwrap();
+        writeln!(out, "  <p><b>Scramble:</b> <code>{}</code></p>", escape_html(&scramble.to_string())).unwrap();
+        writeln!(out, "  <table>").unwrap();
+        writeln!(out, "    <tr><th>Step</th><th>Moves</th><th>Length</th><th>Total</th></tr>").unwrap();
+        for line in self.step_lines() {
+            writeln!(out, "    <tr><td>{}</td><td><code>{}</code></td><td>{}</td><td>{}</td></tr>",
+                     escape_html(&line.name), escape_html(&line.alg.to_string()), line.length_string(), line.total).unwrap();
+        }
+        writeln!(out, "  </table>").unwrap();
+        writeln!(out, "  <p><b>Solution ({}):</b> <code>{}</code></p>", final_alg.len(), escape_html(&final_alg.to_string())).unwrap();
+        writeln!(out, "  <p><a href=\"{}\">alg.cubing.net</a> &middot; <a href=\"{}\">twizzle</a></p>",
+                 escape_html(&self.to_alg_cubing_net_url(scramble)), escape_html(&self.to_twizzle_url(scramble))).unwrap();
+        writeln!(out, "</div>").unwrap();
+        out
+    }
+
+    fn step_lines(&self) -> Vec<StepLine> {
+        let mut collected = Algorithm::new();
+        let step_count = self.steps.len();
+        self.steps.iter().enumerate()
+            .map(|(idx, step)| {
+                let previous_length = collected.len();
+                let kind = StepKind::from(step.variant);
+                collected = if idx + 1 == step_count && (kind == StepKind::FINLS || kind == StepKind::FIN) {
+                    (collected.clone() + step.alg.clone()).to_uninverted()
+                } else {
+                    collected.clone() + step.alg.clone()
+                }.canonicalize();
+                let name = if step.comment.is_empty() {
+                    step.variant.to_string()
+                } else {
+                    format!("{} [{}]", step.variant, step.comment)
+                };
+                StepLine {
+                    name,
+                    alg: step.alg.clone(),
+                    length: step.alg.len(),
+                    cancelled: previous_length + step.alg.len() - collected.len(),
+                    total: collected.len(),
+                }
+            })
+            .collect()
+    }
+
+    fn viewer_setup_and_alg(&self, scramble: &Algorithm) -> (String, String) {
+        if self.vr {
+            // Slice insertions don't map onto single steps, so we only show the final solution
+            let alg: Algorithm = self.clone().into();
+            return (fmt_moves(&premoves_first(scramble)), format!("{} // solution", fmt_moves(&alg.normal_moves)));
+        }
+        let mut setup = scramble.clone();
+        for step in self.steps.iter() {
+            setup.inverse_moves.extend_from_slice(&step.alg.inverse_moves);
+        }
+        let alg = self.step_lines().into_iter()
+            .map(|line| {
+                let inverse = if line.alg.inverse_moves.is_empty() {
+                    "".to_string()
+                } else {
+                    format!(" ({})", fmt_moves(&line.alg.inverse_moves))
+                };
+                format!("{} // {}{inverse} ({}/{})", fmt_moves(&line.alg.normal_moves), line.name, line.length_string(), line.total)
+                    .trim_start()
+                    .to_string()
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+        (fmt_moves(&premoves_first(&setup)), alg)
+    }
+}
+
+impl StepLine {
+    fn length_string(&self) -> String {
+        if self.cancelled == 0 {
+            self.length.to_string()
+        } else {
+            format!("{}-{}", self.length, self.cancelled)
+        }
+    }
+}
+
+// Applying the inverted inverse moves before the normal moves yields the same state as the NISS algorithm
+fn premoves_first(alg: &Algorithm) -> Vec<Turn333> {
+    alg.inverse_moves.iter().rev()
+        .map(|m| m.invert())
+        .chain(alg.normal_moves.iter().cloned())
+        .collect()
+}
+
+fn fmt_moves(moves: &[Turn333]) -> String {
+    moves.iter().map(|m| m.to_string()).collect::<Vec<String>>().join(" ")
+}
+
+fn percent_encode(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            b => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+    use crate::algs::Algorithm;
+    use crate::cube::Cube333;
+    use crate::cube::turn::ApplyAlgorithm;
+    use crate::defs::StepVariant;
+    use crate::solver::solution::{Solution, SolutionStep};
+    use crate::cube::CubeAxis;
+
+    #[test]
+    fn test_niss_moves_become_setup() {
+        let scramble = Algorithm::from_str("R U F").unwrap();
+        let mut solution = Solution::new();
+        solution.add_step(SolutionStep {
+            variant: StepVariant::EO(CubeAxis::UD),
+            alg: Algorithm::from_str("F' (U')").unwrap(),
+            comment: "".to_string(),
+            slice_insertions: vec![],
+        });
+        let (setup, alg) = solution.viewer_setup_and_alg(&scramble);
+        assert_eq!("U R U F", setup);
+        assert_eq!("F' // eoud (U') (2/2)", alg);
+
+        let mut cube = Cube333::default();
+        cube.apply_alg(&Algorithm::from_str(&setup).unwrap());
+        cube.apply_alg(&Algorithm::from_str("F'").unwrap());
+        let mut expected = Cube333::default();
+        expected.apply_alg(&Algorithm::from_str("R U F F' (U')").unwrap());
+        assert_eq!(expected, cube);
+
+        assert_eq!("https://alg.cubing.net/?setup=U%20R%20U%20F&alg=F%27%20%2F%2F%20eoud%20%28U%27%29%20%282%2F2%29", solution.to_alg_cubing_net_url(&scramble));
+    }
+
+    #[test]
+    fn test_comments_are_escaped() {
+        let scramble = Algorithm::from_str("R U F").unwrap();
+        let mut solution = Solution::new();
+        solution.add_step(SolutionStep {
+            variant: StepVariant::EO(CubeAxis::UD),
+            alg: Algorithm::from_str("F'").unwrap(),
+            comment: "a|b&c=5-2#%+".to_string(),
+            slice_insertions: vec![],
+        });
+        let url = solution.to_alg_cubing_net_url(&scramble);
+        let query = url.strip_prefix("https://alg.cubing.net/?").unwrap();
+        let params: Vec<(&str, String)> = query.split('&')
+            .map(|param| param.split_once('=').unwrap())
+            .map(|(key, value)| (key, percent_decode(value)))
+            .collect();
+        assert_eq!(vec![
+            ("setup", "R U F".to_string()),
+            ("alg", "F' // eoud [a|b&c=5&#45;2#%+] (1/1)".to_string()),
+        ], params);
+        assert!(solution.to_markdown(&scramble).contains("a\\|b&c=5-2#%+"));
+    }
+
+    fn percent_decode(s: &str) -> String {
+        let mut bytes = vec![];
+        let mut rest = s.as_bytes();
+        while let Some((&b, tail)) = rest.split_first() {
+            if b == b'%' {
+                bytes.push(u8::from_str_radix(std::str::from_utf8(&tail[..2]).unwrap(), 16).unwrap());
+                rest = &tail[2..];
+            } else {
+                bytes.push(b);
+                rest = tail;
+            }
+        }
+        String::from_utf8(bytes).unwrap()
+    }
+}
@@ -10,7 +10,9 @@ use std::ops::{Deref, DerefMut, Index, IndexMut};
 #[cfg(feature = "fs")]
 use home::home_dir;
 use log::{debug, info, warn};
-use memmap2::{Mmap, MmapOptions};
+use memmap2::Mmap;
+#[cfg(feature = "fs")]
+use memmap2::MmapOptions;
 use num_traits::{FromPrimitive};
 use crate::cube::*;
 use crate::cube::turn::{Invertible, TurnableMut};
@@ -79,6 +81,25 @@ pub struct IndexTable<const C_SIZE: usize, C: Coord<C_SIZE>, T: Index<usize, Out
 pub struct NissIndexTable<const C_SIZE: usize, C: Coord<C_SIZE>, T: Index<usize, Output = u8> + ?Sized + Send + Sync, F: Deref<Target = T> + Send + Sync> {
     table: IndexTable<C_SIZE, C, T, F>,
 }
+
+// Derive would require the coordinate to be Clone as well
+impl <const C_SIZE: usize, C: Coord<C_SIZE>, T: Index<usize, Output = u8> + ?Sized + Send + Sync, F: Deref<Target = T> + Send + Sync + Clone> Clone for IndexTable<C_SIZE, C, T, F> {
+    fn clone(&self) -> Self {
+        Self {
+            data: self.data.clone(),
+            coord_type: PhantomData,
+            compressed: self.compressed,
+        }
+    }
+}
+
+impl <const C_SIZE: usize, C: Coord<C_SIZE>, T: Index<usize, Output = u8> + ?Sized + Send + Sync, F: Deref<Target = T> + Send + Sync + Clone> Clone for NissIndexTable<C_SIZE, C, T, F> {
+    fn clone(&self) -> Self {
+        Self {
+            table: self.table.clone(),
+        }
+    }
+}
 #[derive(Debug)]
 pub enum TableError {
     OutdatedVersion,
@@ -165,6 +186,118 @@ impl <const C_SIZE: usize, C: Coord<C_SIZE>> MemoryMappedIndexTable<C_SIZE, C> {
     }
 }
 
+/// Without a file system (e.g. in wasm), tables are loaded from and saved to this in-memory store instead.
+/// The application seeds it with [`preload_table`] and persists whatever [`take_generated_tables`] returns,
+/// so tables don't have to be generated again on the next start.
+#[cfg(not(feature = "fs"))]
+mod table_store {
+    use std::collections::HashMap;
+    use std::sync::{LazyLock, Mutex};
+
+    static PRELOADED: LazyLock<Mutex<HashMap<String, Vec<u8>>>> = LazyLock::new(Default::default);
+    static GENERATED: LazyLock<Mutex<HashMap<String, Vec<u8>>>> = LazyLock::new(Default::default);
+
+    pub fn preload_table(key: &str, bytes: Vec<u8>) {
+        PRELOADED.lock().unwrap().insert(key.to_string(), bytes);
+    }
+
+    pub fn take_generated_tables() -> Vec<(String, Vec<u8>)> {
+        GENERATED.lock().unwrap().drain().collect()
+    }
+
+    pub(crate) fn take_preloaded(key: &str) -> Option<Vec<u8>> {
+        PRELOADED.lock().unwrap().remove(key)
+    }
+
+    pub(crate) fn store_generated(key: &str, bytes: Vec<u8>) {
+        GENERATED.lock().unwrap().insert(key.to_string(), bytes);
+    }
+}
+
+#[cfg(not(feature = "fs"))]
+pub use table_store::{preload_table, take_generated_tables};
+#[cfg(not(feature = "fs"))]
+pub(crate) use table_store::store_generated;
+
+#[cfg(not(feature = "fs"))]
+impl <const C_SIZE: usize, C: Coord<C_SIZE>> InMemoryIndexTable<C_SIZE, C> {
+    pub fn load_and_save<FN: FnMut() -> InMemoryIndexTable<C_SIZE, C>>(key: &str, mut gen_f: FN) -> (Self, bool) {
+        match table_store::take_preloaded(key).map(Self::from_bytes) {
+            Some(Ok(t)) => {
+                debug!("Loaded preloaded {key} table");
+                (t, false)
+            },
+            other => {
+                if let Some(Err(e)) = other {
+                    warn!("Failed to load preloaded {key} table. {e}");
+                }
+                info!("Generating {key} table...");
+                let table = gen_f();
+                store_generated(key, table.to_bytes());
+                (table, true)
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "fs"))]
+impl <const C_SIZE: usize, C: Coord<C_SIZE>> InMemoryNissIndexTable<C_SIZE, C> {
+    pub fn load_and_save<FN: FnMut() -> InMemoryNissIndexTable<C_SIZE, C>>(key: &str, mut gen_f: FN) -> Self {
+        let (table, _) = InMemoryIndexTable::load_and_save(key, || gen_f().table);
+        Self {
+            table
+        }
+    }
+}
+
+impl <const C_SIZE: usize, C: Coord<C_SIZE>> InMemoryIndexTable<C_SIZE, C> {
+    /// Serializes the table in the same format that is used for table files
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let header: [u8; 2] = TableHeader {
+            version: VERSION,
+            table_type: if self.compressed {
+                TableType::Compressed
+            } else {
+                TableType::Uncompressed
+            }
+        }.into();
+        let mut bytes = Vec::with_capacity(header.len() + self.data.len());
+        bytes.extend_from_slice(&header);
+        bytes.extend_from_slice(&self.data);
+        bytes
+    }
+
+    pub fn from_bytes(mut bytes: Vec<u8>) -> Result<Self, TableError> {
+        if bytes.len() < TableHeader::size() as usize {
+            return Err(TableError::InvalidHeader);
+        }
+        let header = TableHeader::try_from([bytes[0], bytes[1]])?;
+        if header.version != VERSION {
+            return Err(TableError::OutdatedVersion);
+        }
+        let expected_len = match header.table_type {
+            TableType::Compressed => C_SIZE.div_ceil(2),
+            TableType::Uncompressed => C_SIZE,
+            _ => return Err(TableError::InvalidFormat),
+        };
+        bytes.drain(0..TableHeader::size() as usize);
+        if bytes.len() != expected_len {
+            return Err(TableError::InvalidFormat);
+        }
+        Ok(Self {
+            data: bytes,
+            coord_type: PhantomData,
+            compressed: header.table_type == TableType::Compressed,
+        })
+    }
+}
+
+impl <const C_SIZE: usize, C: Coord<C_SIZE>> InMemoryNissIndexTable<C_SIZE, C> {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.table.to_bytes()
+    }
+}
+
 #[cfg(feature = "fs")]
 impl <const C_SIZE: usize, C: Coord<C_SIZE>, T: Index<usize, Output = u8> + ?Sized + Send + Sync, F: Deref<Target = T> + Send + Sync> NissIndexTable<C_SIZE, C, T, F> where Self: LoadFromDisk + SaveToDisk {
     pub fn load_and_save<FN: FnMut() -> InMemoryNissIndexTable<C_SIZE, C>>(key: &str, mut gen_f: FN) -> Self {
@@ -299,7 +432,6 @@ impl <const C_SIZE: usize, C: Coord<C_SIZE>> InMemoryIndexTable<C_SIZE, C> {
     }
 }
 
-#[cfg(feature = "fs")]
 impl <const C_SIZE: usize, C: Coord<C_SIZE>> InMemoryNissIndexTable<C_SIZE, C> {
     pub fn new() -> Self {
         Self {
@@ -578,3 +710,30 @@ pub fn generate_large_table<
     }
     table
 }
+
+#[cfg(test)]
+mod test {
+    use crate::solver::lookup_table::{DepthEstimate, InMemoryIndexTable, TableError};
+    use crate::steps::eo::coords::EOCoordFB;
+
+    #[test]
+    fn test_table_bytes_roundtrip() {
+        for compressed in [true, false] {
+            let mut table: InMemoryIndexTable<2048, EOCoordFB> = InMemoryIndexTable::new(compressed);
+            table.set_direct(0usize, 0);
+            table.set_direct(1usize, 3);
+            table.set_direct(2047usize, 7);
+            let bytes = table.to_bytes();
+            let loaded: InMemoryIndexTable<2048, EOCoordFB> = InMemoryIndexTable::from_bytes(bytes.clone()).unwrap();
+            for id in [0usize, 1, 2, 2047] {
+                assert_eq!(table.get_direct(id), loaded.get_direct(id));
+            }
+            assert_eq!(table.get(EOCoordFB(1)), 3);
+
+            assert!(matches!(InMemoryIndexTable::<2048, EOCoordFB>::from_bytes(bytes[..bytes.len() - 1].to_vec()), Err(TableError::InvalidFormat)));
+            let mut outdated = bytes;
+            outdated[0] += 1;
+            assert!(matches!(InMemoryIndexTable::<2048, EOCoordFB>::from_bytes(outdated), Err(TableError::OutdatedVersion)));
+        }
+    }
+}
@@ -12,6 +12,9 @@ pub mod solution;
 pub mod df_search;
 pub mod moveset;
 pub mod move_table;
+pub mod export;
+pub mod backend;
+pub mod scoring;
 
 use crate::solver::df_search::CancelToken;
 use crate::steps::step::{DefaultStepOptions, Step};
@@ -0,0 +1,300 @@
+use std::cmp::Ordering;
+use std::str::FromStr;
+
+use itertools::Itertools;
+
+use crate::algs::Algorithm;
+use crate::cube::{Cube333, CubeFace, Direction, Turn333};
+use crate::defs::StepKind;
+use crate::solver::solution::Solution;
+
+/// Rates a solution, e.g. by how hard it is for a human to find. Lower scores rank first.
+/// `cube` is the state the solution starts from.
+pub trait SolutionScorer: Send + Sync {
+    fn score(&self, cube: &Cube333, solution: &Solution) -> f64;
+}
+
+impl <F: Fn(&Cube333, &Solution) -> f64 + Send + Sync> SolutionScorer for F {
+    fn score(&self, cube: &Cube333, solution: &Solution) -> f64 {
+        self(cube, solution)
+    }
+}
+
+/// The number of moves of the solution
+pub struct Length;
+
+impl SolutionScorer for Length {
+    fn score(&self, _: &Cube333, solution: &Solution) -> f64 {
+        solution.len() as f64
+    }
+}
+
+/// How often the solution switches between the normal and the inverse scramble
+pub struct NissSwitches;
+
+impl SolutionScorer for NissSwitches {
+    fn score(&self, _: &Cube333, solution: &Solution) -> f64 {
+        let mut on_normal = true;
+        let mut switches = 0;
+        for step in solution.steps.iter() {
+            match (step.alg.normal_moves.is_empty(), step.alg.inverse_moves.is_empty()) {
+                (true, true) => {},
+                (false, false) => {
+                    switches += 1;
+                    on_normal = !on_normal;
+                },
+                (true, false) | (false, true) => {
+                    let normal = !step.alg.normal_moves.is_empty();
+                    if normal != on_normal {
+                        switches += 1;
+                        on_normal = normal;
+                    }
+                }
+            }
+        }
+        switches as f64
+    }
+}
+
+/// The number of moves spent on EO
+pub struct EOLength;
+
+impl SolutionScorer for EOLength {
+    fn score(&self, _: &Cube333, solution: &Solution) -> f64 {
+        solution.steps.iter()
+            .filter(|s| StepKind::from(s.variant) == StepKind::EO)
+            .map(|s| s.alg.len())
+            .sum::<usize>() as f64
+    }
+}
+
+/// The number of DR moves that are not part of a known trigger. The triggers are matched in any orientation,
+/// and the direction of their last move is ignored, just like for the `triggers` parameter of the DR step.
+pub struct DRTrigger {
+    // Faces are numbered in order of appearance and directions are mirrored so the first move is clockwise
+    triggers: Vec<Vec<(usize, Direction)>>,
+}
+
+impl DRTrigger {
+    pub fn new(triggers: &[Algorithm]) -> Self {
+        Self {
+            triggers: triggers.iter()
+                .map(|t| Self::shape(&t.normal_moves))
+                .filter(|t| !t.is_empty())
+                .collect()
+        }
+    }
+
+    fn shape(moves: &[Turn333]) -> Vec<(usize, Direction)> {
+        let mut faces: Vec<CubeFace> = vec![];
+        let mirror = moves.first().is_some_and(|m| m.dir == Direction::CounterClockwise);
+        moves.iter().enumerate()
+            .map(|(idx, m)| {
+                let face = faces.iter().position(|f| *f == m.face).unwrap_or_else(|| {
+                    faces.push(m.face);
+                    faces.len() - 1
+                });
+                let dir = match m.dir {
+                    Direction::Half => Direction::Half,
+                    _ if idx == moves.len() - 1 => Direction::Clockwise,
+                    Direction::Clockwise if mirror => Direction::CounterClockwise,
+                    Direction::CounterClockwise if mirror => Direction::Clockwise,
+                    dir => dir,
+                };
+                (face, dir)
+            })
+            .collect()
+    }
+
+    fn trigger_length(&self, moves: &[Turn333]) -> usize {
+        self.triggers.iter()
+            .filter(|t| t.len() <= moves.len())
+            .filter(|t| Self::shape(&moves[moves.len() - t.len()..]) == **t)
+            .map(|t| t.len())
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+impl Default for DRTrigger {
+    fn default() -> Self {
+        let triggers = ["R", "R U2 R", "R U R", "R U' R"].iter()
+            .map(|t| Algorithm::from_str(t).unwrap())
+            .collect_vec();
+        Self::new(&triggers)
+    }
+}
+
+impl SolutionScorer for DRTrigger {
+    fn score(&self, _: &Cube333, solution: &Solution) -> f64 {
+        solution.steps.iter()
+            .filter(|s| StepKind::from(s.variant) == StepKind::DR)
+            .map(|s| {
+                // The trigger is the end of whichever side the DR was finished on
+                let trigger = self.trigger_length(&s.alg.normal_moves).max(self.trigger_length(&s.alg.inverse_moves));
+                s.alg.len() - trigger
+            })
+            .sum::<usize>() as f64
+    }
+}
+
+/// The number of bad corners and edges of the DR subset. Solutions without a DR score 0
+#[cfg(feature = "multi-path-channel-solver")]
+pub struct DRSubset;
+
+#[cfg(feature = "multi-path-channel-solver")]
+impl SolutionScorer for DRSubset {
+    fn score(&self, cube: &Cube333, solution: &Solution) -> f64 {
+        use crate::solver::solution::ApplySolution;
+        let Some(dr_step) = solution.steps.iter().position(|s| StepKind::from(s.variant) == StepKind::DR) else {
+            return 0.0;
+        };
+        let mut partial = Solution::new();
+        for step in solution.steps[0..=dr_step].iter() {
+            partial.add_step(step.clone());
+        }
+        let mut cube = *cube;
+        cube.apply_solution(&partial);
+        cube.get_dr_subset()
+            .map(|subset| (subset.corners + subset.edges) as f64)
+            .unwrap_or(0.0)
+    }
+}
+
+/// Returns the built-in scorer with the given name
+pub fn scorer(name: &str) -> Result<Box<dyn SolutionScorer>, String> {
+    match name {
+        "length" => Ok(Box::new(Length)),
+        "niss" => Ok(Box::new(NissSwitches)),
+        "eo-length" => Ok(Box::new(EOLength)),
+        "dr-trigger" => Ok(Box::new(DRTrigger::default())),
+        #[cfg(feature = "multi-path-channel-solver")]
+        "dr-subset" => Ok(Box::new(DRSubset)),
+        x => Err(format!("Unknown scorer '{x}'. Expected one of {}", SCORERS.join(", "))),
+    }
+}
+
+#[cfg(feature = "multi-path-channel-solver")]
+const SCORERS: &[&str] = &["length", "niss", "eo-length", "dr-trigger", "dr-subset"];
+#[cfg(not(feature = "multi-path-channel-solver"))]
+const SCORERS: &[&str] = &["length", "niss", "eo-length", "dr-trigger"];
+
+/// Orders solutions by a list of criteria. Later criteria only break ties of the earlier ones,
+/// and solutions that are still tied keep their order, so shorter solutions come first.
+/// Each criterion is a weighted sum of scorers.
+#[derive(Default)]
+pub struct Ranking {
+    criteria: Vec<Vec<(f64, Box<dyn SolutionScorer>)>>,
+}
+
+impl Ranking {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn then_by<S: SolutionScorer + 'static>(self, scorer: S) -> Self {
+        self.then_by_weighted(vec![(1.0, Box::new(scorer))])
+    }
+
+    pub fn then_by_weighted(mut self, terms: Vec<(f64, Box<dyn SolutionScorer>)>) -> Self {
+        self.criteria.push(terms);
+        self
+    }
+
+    /// The score of each criterion. Compare them with [`Ranking::compare`]
+    pub fn scores(&self, cube: &Cube333, solution: &Solution) -> Vec<f64> {
+        self.criteria.iter()
+            .map(|terms| terms.iter()
+                .map(|(weight, scorer)| weight * scorer.score(cube, solution))
+                .sum())
+            .collect()
+    }
+
+    pub fn compare(a: &[f64], b: &[f64]) -> Ordering {
+        a.iter().zip(b.iter())
+            .map(|(a, b)| a.total_cmp(b))
+            .find(|o| o.is_ne())
+            .unwrap_or(Ordering::Equal)
+    }
+
+    pub fn sort(&self, cube: &Cube333, solutions: Vec<Solution>) -> Vec<Solution> {
+        let mut scored = solutions.into_iter()
+            .map(|s| (self.scores(cube, &s), s))
+            .collect_vec();
+        scored.sort_by(|(a, _), (b, _)| Self::compare(a, b));
+        scored.into_iter()
+            .map(|(_, s)| s)
+            .collect()
+    }
+}
+
+/// Parses rankings like `niss,eo-length` or `2*niss+dr-trigger,length`.
+/// Criteria are separated by `,` and each of them is a sum of optionally weighted scorers
+impl FromStr for Ranking {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut ranking = Ranking::new();
+        for criterion in s.split(',') {
+            let terms = criterion.split('+')
+                .map(str::trim)
+                .map(|term| {
+                    let (weight, name) = match term.split_once('*') {
+                        Some((weight, name)) => (f64::from_str(weight.trim()).map_err(|_| format!("Invalid weight '{weight}'"))?, name.trim()),
+                        None => (1.0, term),
+                    };
+                    scorer(name).map(|s| (weight, s))
+                })
+                .collect::<Result<Vec<_>, String>>()?;
+            ranking = ranking.then_by_weighted(terms);
+        }
+        Ok(ranking)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+    use crate::algs::Algorithm;
+    use crate::cube::{Cube333, CubeAxis};
+    use crate::defs::StepVariant;
+    use crate::solver::scoring::{DRTrigger, NissSwitches, Ranking, SolutionScorer};
+    use crate::solver::solution::{Solution, SolutionStep};
+
+    fn solution(steps: Vec<(StepVariant, &str)>) -> Solution {
+        let mut solution = Solution::new();
+        for (variant, alg) in steps {
+            solution.add_step(SolutionStep {
+                variant,
+                alg: Algorithm::from_str(alg).unwrap(),
+                comment: "".to_string(),
+                slice_insertions: vec![],
+            });
+        }
+        solution
+    }
+
+    #[test]
+    fn test_ranking() {
+        let eo = StepVariant::EO(CubeAxis::FB);
+        let dr = StepVariant::DR { eo_axis: CubeAxis::FB, dr_axis: CubeAxis::UD };
+        let niss = solution(vec![(eo, "F (U)"), (dr, "(R U2 R')")]);
+        let trigger = solution(vec![(eo, "F U"), (dr, "F2 L' D2 L")]);
+        let no_trigger = solution(vec![(eo, "F U"), (dr, "L' D2 F2 L")]);
+        let cube = Cube333::default();
+
+        assert_eq!(1.0, NissSwitches.score(&cube, &niss));
+        assert_eq!(0.0, NissSwitches.score(&cube, &trigger));
+        assert_eq!(0.0, DRTrigger::default().score(&cube, &niss));
+        assert_eq!(1.0, DRTrigger::default().score(&cube, &trigger));
+        assert_eq!(3.0, DRTrigger::default().score(&cube, &no_trigger));
+
+        let ranking = Ranking::from_str("dr-trigger + 2*niss, eo-length").unwrap();
+        assert_eq!(vec![2.0, 2.0], ranking.scores(&cube, &niss));
+        let ranked = ranking.sort(&cube, vec![no_trigger.clone(), niss.clone(), trigger.clone()]);
+        assert_eq!(vec![trigger, niss, no_trigger], ranked);
+
+        assert!(Ranking::from_str("length,unknown").is_err());
+        assert!(Ranking::from_str("x*niss").is_err());
+    }
+}
@@ -1,7 +1,5 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::HashSet;
 use std::fmt::{Debug, Display, Formatter};
-#[cfg(feature = "serde_support")]
-use serde_with::serde_as;
 use itertools::Itertools;
 use crate::algs::Algorithm;
 use crate::cube::turn::{ApplyAlgorithm, CubeAxis, CubeFace, Direction, Invertible, InvertibleMut, Transformable, TurnableMut};
@@ -10,40 +8,165 @@ use crate::defs::{StepKind, StepVariant};
 use crate::solver_new::vr::VRInsertions;
 
 #[cfg_attr(feature = "serde_support", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde_support", serde(from = "compat::SolutionData"))]
 #[derive(Eq, PartialEq)]
 pub struct Solution {
     pub steps: Vec<SolutionStep>,
     pub ends_on_normal: bool,
-    pub insertion_direction: Option<Transformation333>,
+    /// Set once a VR step resolved the slice insertions, even if none were needed
+    pub vr: bool,
 }
 
 #[derive(Clone, Eq, PartialEq)]
-#[cfg_attr(feature = "serde_support", serde_as)]
 #[cfg_attr(feature = "serde_support", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde_support", serde(from = "compat::SolutionStepData"))]
 pub struct SolutionStep {
     pub variant: StepVariant,
     pub alg: Algorithm,
     pub comment: String,
-    #[cfg_attr(feature = "serde_support", serde_as(as = "Vec<(_, _)>"))]
-    pub e_insertions: HashMap<usize, Direction>,
+    /// Slice turns inserted before the move at the given index. Indices past the normal moves count the inverse moves from the back.
+    /// Insertions at the same index are applied in order
+    pub slice_insertions: Vec<(usize, SliceInsertion)>,
 }
 
-impl Solution {
+/// Solutions used to only support E insertions. They were stored as `e_insertions` per step, with the slice
+/// given by `insertion_direction` instead of `vr`. Those are still accepted when deserializing.
+#[cfg(feature = "serde_support")]
+mod compat {
+    use crate::algs::Algorithm;
+    use crate::cube::turn::{CubeAxis, Direction};
+    use crate::cube::Transformation333;
+    use crate::defs::StepVariant;
+    use crate::solver::solution::{SliceInsertion, Solution, SolutionStep};
+
+    #[derive(serde::Deserialize)]
+    pub(super) struct SolutionData {
+        steps: Vec<SolutionStepData>,
+        ends_on_normal: bool,
+        #[serde(default)]
+        vr: bool,
+        #[serde(default)]
+        insertion_direction: Option<Transformation333>,
+    }
+
+    #[derive(serde::Deserialize)]
+    pub(super) struct SolutionStepData {
+        variant: StepVariant,
+        alg: Algorithm,
+        comment: String,
+        #[serde(default)]
+        slice_insertions: Vec<(usize, SliceInsertion)>,
+        #[serde(default)]
+        e_insertions: Vec<(usize, Direction)>,
+    }
+
+    impl SolutionStepData {
+        fn into_step(self, legacy_slice: CubeAxis) -> SolutionStep {
+            let mut slice_insertions = self.slice_insertions;
+            slice_insertions.extend(self.e_insertions.into_iter().map(|(idx, dir)| (idx, SliceInsertion::new(legacy_slice, dir))));
+            slice_insertions.sort_by_key(|(idx, _)| *idx);
+            SolutionStep {
+                variant: self.variant,
+                alg: self.alg,
+                comment: self.comment,
+                slice_insertions,
+            }
+        }
+    }
 
-    const E_INSERTION_FOOTNOTE_SYMBOL: [&'static str; 3] = ["^", "@", "#"];
+    impl From<SolutionStepData> for SolutionStep {
+        fn from(value: SolutionStepData) -> Self {
+            value.into_step(CubeAxis::UD)
+        }
+    }
+
+    impl From<SolutionData> for Solution {
+        fn from(value: SolutionData) -> Self {
+            // The old insertions were turned with the same outer layer moves as the slice that is used now
+            let legacy_slice = match value.insertion_direction.map(|t| t.axis) {
+                Some(CubeAxis::FB) => CubeAxis::LR,
+                Some(CubeAxis::LR) => CubeAxis::FB,
+                _ => CubeAxis::UD,
+            };
+            Solution {
+                steps: value.steps.into_iter().map(|s| s.into_step(legacy_slice)).collect(),
+                ends_on_normal: value.ends_on_normal,
+                vr: value.vr || value.insertion_direction.is_some(),
+            }
+        }
+    }
+}
+
+/// A slice turn inserted into a solution. `slice` is the axis of the slice, i.e. `UD` for E, `LR` for M and `FB` for S.
+/// Directions follow the usual notation, E turns like D, M like L and S like F.
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "serde_support", derive(serde::Serialize, serde::Deserialize))]
+pub struct SliceInsertion {
+    pub slice: CubeAxis,
+    pub dir: Direction,
+}
+
+impl SliceInsertion {
+    const FOOTNOTE_SYMBOLS: [[&'static str; 3]; 3] = [["!", "?", "%"], ["^", "@", "#"], ["&", "*", "~"]];
+
+    pub fn new(slice: CubeAxis, dir: Direction) -> Self {
+        Self { slice, dir }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self.slice {
+            CubeAxis::LR => "M",
+            CubeAxis::UD => "E",
+            CubeAxis::FB => "S",
+        }
+    }
+
+    // The symbol used in the detailed solution output. Every slice and direction gets its own
+    fn footnote_symbol(&self) -> &'static str {
+        Self::FOOTNOTE_SYMBOLS[self.slice][self.dir.to_qt() as usize - 1]
+    }
+
+    /// The outer layer turns that are executed instead of the slice turn. Together they equal the slice turn followed by a rotation
+    pub fn outer_turns(&self) -> [Turn333; 2] {
+        let face = match self.slice {
+            CubeAxis::UD => CubeFace::Up,
+            CubeAxis::LR => CubeFace::Right,
+            CubeAxis::FB => CubeFace::Back,
+        };
+        [Turn333::new(face, self.dir), Turn333::new(face.opposite(), self.dir.invert())]
+    }
+
+    /// Moves after the insertion have to be transformed with this to make up for the rotation the outer turns introduce.
+    pub fn transformation(&self) -> Transformation333 {
+        // X Y Z follow R U F, while M E S follow L D F
+        match self.slice {
+            CubeAxis::FB => Transformation333::new(CubeAxis::FB, self.dir.invert()),
+            axis => Transformation333::new(axis, self.dir),
+        }
+    }
+}
+
+impl Display for SliceInsertion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", self.name(), self.dir.to_symbol())
+    }
+}
+
+impl Solution {
 
     pub fn new() -> Solution {
-        Solution { steps: vec![], ends_on_normal: true, insertion_direction: None }
+        Solution { steps: vec![], ends_on_normal: true, vr: false }
     }
 
+    /// Replaces the insertions on the slice of the VR. Insertions on the other slices are kept
     pub fn set_vr_insertions(&mut self, vr_solution: VRInsertions) {
-        let VRInsertions(mut insertions, trans) = vr_solution;
-        self.insertion_direction = trans;
+        let VRInsertions(mut insertions, slice) = vr_solution;
+        self.vr = true;
         for step in self.steps.iter_mut() {
+            step.slice_insertions.retain(|(_, insertion)| insertion.slice != slice);
             if let Some(insertion) = insertions.remove(&step.variant) {
-                step.e_insertions = insertion.into_iter().collect();
-            } else if !step.e_insertions.is_empty() {
-                step.e_insertions = Default::default();
+                step.slice_insertions.extend(insertion);
+                step.slice_insertions.sort_by_key(|(idx, _)| *idx);
             }
         }
     }
@@ -95,72 +218,46 @@ impl Solution {
         Solution {
             steps,
             ends_on_normal: self.ends_on_normal,
-            insertion_direction: self.insertion_direction
+            vr: self.vr,
         }
     }
 
-    fn to_compact_alg_with_insertions(mut self) -> Algorithm {
-        let trans = if let Some(trans) = self.insertion_direction {
-            trans
-        } else {
+    fn to_compact_alg_with_insertions(self) -> Algorithm {
+        if !self.vr {
             return self.into()
-        };
+        }
         // We assume that a Solution with a VR is always finished.
-        let mut trans_qt = 0;
-        let primary_face = match trans.axis {
-            CubeAxis::UD => CubeFace::Up,
-            CubeAxis::FB => CubeFace::Right,
-            CubeAxis::LR => CubeFace::Back,
-        };
-        let trans_axis = primary_face.get_axis();
+        // Every insertion rotates the cube, so all later moves are transformed by the insertions before them, starting with the latest one
+        let mut transformations: Vec<Transformation333> = vec![];
         let mut turns = vec![];
-        fn transform_turn(turn: Turn333, trans_axis: CubeAxis, y_trans_qt: u8) -> Turn333 {
-            Direction::from_qt(y_trans_qt)
-                .map(|x|{
-                    // The reason for this special case is that X Y Z follows R U F, while M E S follows R' U' F
-                    if trans_axis == CubeAxis::FB {
-                        turn.transform(Transformation333::new(trans_axis, x.invert()))
-                    } else {
-                        turn.transform(Transformation333::new(trans_axis, x))
-                    }
-                })
-                .unwrap_or(turn)
+        fn transform_turn(turn: Turn333, transformations: &[Transformation333]) -> Turn333 {
+            transformations.iter().rev().fold(turn, |turn, t| turn.transform(*t))
         }
-        for step in &mut self.steps {
-            for (idx, turn) in step.alg.normal_moves.iter().enumerate() {
-                if let Some(dir) = step.e_insertions.remove(&idx) {
-                    trans_qt = (trans_qt + dir.to_qt()) % 4;
-                    turns.push(Turn333::new(primary_face, dir));
-                    turns.push(Turn333::new(primary_face.opposite(), dir.invert()));
+        fn insert(step: &SolutionStep, idx: usize, turns: &mut Vec<Turn333>, transformations: &mut Vec<Transformation333>) {
+            for (_, insertion) in step.slice_insertions.iter().filter(|(i, _)| *i == idx) {
+                for turn in insertion.outer_turns() {
+                    turns.push(transform_turn(turn, transformations));
                 }
-                turns.push(transform_turn(*turn, trans_axis, trans_qt));
+                transformations.push(insertion.transformation());
             }
-            if step.alg.normal_moves.len() > 0 {
-                if let Some(dir) = step.e_insertions.remove(&step.alg.normal_moves.len()) {
-                    trans_qt = (trans_qt + dir.to_qt()) % 4;
-                    turns.push(Turn333::new(primary_face, dir));
-                    turns.push(Turn333::new(primary_face.opposite(), dir.invert()));
-                }
+        }
+        for step in &self.steps {
+            for (idx, turn) in step.alg.normal_moves.iter().enumerate() {
+                insert(step, idx, &mut turns, &mut transformations);
+                turns.push(transform_turn(*turn, &transformations));
+            }
+            if !step.alg.normal_moves.is_empty() {
+                insert(step, step.alg.normal_moves.len(), &mut turns, &mut transformations);
             }
         }
-        for step in self.steps.iter_mut().rev() {
+        for step in self.steps.iter().rev() {
             for (idx, turn) in step.alg.inverse_moves.iter().rev().enumerate() {
-                let idx = idx + step.alg.normal_moves.len();
-                if let Some(dir) = step.e_insertions.remove(&idx) {
-                    trans_qt = (trans_qt + dir.to_qt()) % 4;
-                    turns.push(Turn333::new(primary_face, dir));
-                    turns.push(Turn333::new(primary_face.opposite(), dir.invert()));
-                }
-                turns.push(transform_turn(turn.invert(), trans_axis, trans_qt));
+                insert(step, idx + step.alg.normal_moves.len(), &mut turns, &mut transformations);
+                turns.push(transform_turn(turn.invert(), &transformations));
             }
-            if step.alg.inverse_moves.len() > 0 {
-                if let Some(dir) = step.e_insertions.remove(&step.alg.inverse_moves.len()) {
-                    trans_qt = (trans_qt + dir.to_qt()) % 4;
-                    turns.push(Turn333::new(primary_face, dir));
-                    turns.push(Turn333::new(primary_face.opposite(), dir.invert()));
-                }
+            if !step.alg.inverse_moves.is_empty() {
+                insert(step, step.alg.inverse_moves.len(), &mut turns, &mut transformations);
             }
-            assert!(step.e_insertions.is_empty());
         }
         Algorithm {
             normal_moves: turns,
@@ -171,7 +268,7 @@ impl Solution {
 
 impl Into<Algorithm> for Solution {
     fn into(self) -> Algorithm {
-        if self.insertion_direction.is_some() {
+        if self.vr {
             self.to_compact_alg_with_insertions()
         } else {
             let mut start = Algorithm::new();
@@ -197,7 +294,7 @@ impl Clone for Solution {
         Solution {
             steps: self.steps.clone(),
             ends_on_normal: self.ends_on_normal,
-            insertion_direction: self.insertion_direction,
+            vr: self.vr,
         }
     }
 }
@@ -216,7 +313,7 @@ impl Debug for Solution {
     }
 }
 
-fn write_alg_with_insertion_placeholders(alg: &Algorithm, insertions: &HashMap<usize, Direction>) -> String {
+fn write_alg_with_insertion_placeholders(alg: &Algorithm, insertions: &[(usize, SliceInsertion)]) -> String {
     if alg.inverse_moves.is_empty() {
         write_alg_side_with_insertions_placeholders(&alg.normal_moves, insertions, None)
     } else if alg.normal_moves.is_empty() {
@@ -229,7 +326,11 @@ fn write_alg_with_insertion_placeholders(alg: &Algorithm, insertions: &HashMap<u
     }
 }
 
-fn write_alg_side_with_insertions_placeholders(moves: &Vec<Turn333>, insertions: &HashMap<usize, Direction>, offset: Option<usize>) -> String {
+fn write_alg_side_with_insertions_placeholders(moves: &Vec<Turn333>, insertions: &[(usize, SliceInsertion)], offset: Option<usize>) -> String {
+    let symbols_at = |insertion_idx: usize| insertions.iter()
+        .filter(|(idx, _)| *idx == insertion_idx)
+        .map(|(_, insertion)| insertion.footnote_symbol())
+        .collect_vec();
     let mut alg_string = String::new();
     for idx in 0..moves.len() {
         let insertion_idx = if let Some(offset) = offset {
@@ -237,8 +338,8 @@ fn write_alg_side_with_insertions_placeholders(moves: &Vec<Turn333>, insertions:
         } else {
             idx
         };
-        if let Some(dir) = insertions.get(&insertion_idx).cloned() {
-            alg_string.push_str(&Solution::E_INSERTION_FOOTNOTE_SYMBOL[dir.to_qt() as usize - 1]);
+        for symbol in symbols_at(insertion_idx) {
+            alg_string.push_str(symbol);
             alg_string.push_str(" ");
         }
         alg_string.push_str(moves[idx].to_string().as_str());
@@ -251,9 +352,9 @@ fn write_alg_side_with_insertions_placeholders(moves: &Vec<Turn333>, insertions:
     } else {
         moves.len()
     };
-    if let Some(dir) = insertions.get(&insertion_idx).cloned() {
+    for symbol in symbols_at(insertion_idx) {
         alg_string.push_str(" ");
-        alg_string.push_str(&Solution::E_INSERTION_FOOTNOTE_SYMBOL[dir.to_qt() as usize - 1]);
+        alg_string.push_str(symbol);
     }
     alg_string
 }
@@ -265,7 +366,7 @@ impl Display for Solution {
         let longest_alg_length = compact
             .steps
             .iter()
-            .map(|s| s.alg.to_string().len() + s.e_insertions.len() * 2)
+            .map(|s| s.alg.to_string().len() + s.slice_insertions.len() * 2)
             .max()
             .unwrap_or(0);
         let longest_name_length = compact
@@ -274,31 +375,19 @@ impl Display for Solution {
             .map(|s| s.variant.to_string().len() + if s.comment.is_empty() { 0 } else { s.comment.len() + 3 })
             .max()
             .unwrap_or(0);
-        let footnotes: HashSet<Direction> = self.steps.iter()
-            .flat_map(|x|x.e_insertions.iter().map(|x|x.1.clone()))
+        let footnotes: HashSet<SliceInsertion> = self.steps.iter()
+            .flat_map(|x|x.slice_insertions.iter().map(|x|x.1))
             .collect();
-        let slice_name = match self.insertion_direction {
-            Some(x) if x.axis == CubeAxis::Y => "E",
-            Some(x) if x.axis == CubeAxis::X => "S",
-            Some(x) if x.axis == CubeAxis::Z => "M",
-            _ => "",
-        };
-        let footnotes_line = [Direction::Clockwise, Direction::Half, Direction::CounterClockwise]
-            .into_iter()
+        let footnotes_line = [CubeAxis::UD, CubeAxis::LR, CubeAxis::FB].into_iter()
+            .cartesian_product([Direction::Clockwise, Direction::Half, Direction::CounterClockwise])
+            .map(|(slice, dir)|SliceInsertion::new(slice, dir))
             .filter(|x|footnotes.contains(x))
-            .map(|x|{
-                // let idx = match trans.map(|x|x.axis) {
-                //     Some(CubeAxis::LR) => x.invert().to_qt(),
-                //     _ => x.to_qt()
-                // } as usize - 1;
-                let idx = x.to_qt() as usize - 1;
-                format!("{} = {slice_name}{}", Self::E_INSERTION_FOOTNOTE_SYMBOL[idx], x.to_symbol()).to_string()
-            })
+            .map(|x|format!("{} = {x}", x.footnote_symbol()))
             .join(", ");
         let longest_alg_length = longest_alg_length.max(footnotes_line.len());
         let mut collected_alg = Algorithm::new();
         for (idx, step) in compact.steps.iter().enumerate() {
-            let alg_string = write_alg_with_insertion_placeholders(&step.alg, &step.e_insertions);
+            let alg_string = write_alg_with_insertion_placeholders(&step.alg, &step.slice_insertions);
 
             let alg_length = step.alg.len();
             let previous_length = collected_alg.len();
@@ -319,7 +408,7 @@ impl Display for Solution {
             } else {
                 format!("({alg_length}-{cancelled_moves}/{})", collected_alg.len())
             };
-            let variant_name = if self.insertion_direction.is_some() {
+            let variant_name = if self.vr {
                 match step.variant {
                     StepVariant::DRFINLS(x) => StepVariant::DRFIN(x),
                     StepVariant::HTRFINLS(x) => StepVariant::DRFIN(x),
@@ -332,7 +421,7 @@ impl Display for Solution {
             let name = format!("{}{comment}", variant_name.to_string());
             writeln!(f, "{:longest_alg_length$}  // {name:longest_name_length$} {length_comment}", alg_string)?;
         }
-        if self.insertion_direction.is_some() {
+        if self.vr {
             let collected_alg_with_insertions = self.clone().to_compact_alg_with_insertions();
             let len_diff = collected_alg_with_insertions.len() as isize - collected_alg.len() as isize;
 
@@ -363,7 +452,7 @@ pub trait ApplySolution<C: TurnableMut> {
 
 impl <C: TurnableMut + InvertibleMut> ApplySolution<C> for C {
     fn apply_solution(&mut self, solution: &Solution) {
-        if solution.insertion_direction.is_some() {
+        if solution.vr {
             let alg = solution.clone().to_compact_alg_with_insertions();
             self.apply_alg(&alg);
         } else {
@@ -381,4 +470,110 @@ impl <C: TurnableMut + InvertibleMut> ApplySolution<C> for C {
             self.invert();
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+    use crate::algs::Algorithm;
+    use crate::cube::{Cube333, CubeAxis, CubeFace, Direction, Transformation333, Turn333};
+    use crate::cube::turn::{ApplyAlgorithm, TransformableMut, TurnableMut};
+    use crate::defs::StepVariant;
+    use crate::solver::solution::{SliceInsertion, Solution, SolutionStep};
+
+    // Executes a slice turn the way a human would, i.e. the cube isn't rotated afterward
+    fn turn_slice(cube: &mut Cube333, insertion: SliceInsertion) {
+        let dir = insertion.dir;
+        match insertion.slice {
+            // M = R L' x'
+            CubeAxis::LR => {
+                cube.turn(Turn333::new(CubeFace::Right, dir));
+                cube.turn(Turn333::new(CubeFace::Left, dir.invert()));
+                cube.transform(Transformation333::new(CubeAxis::LR, dir.invert()));
+            }
+            // E = U D' y'
+            CubeAxis::UD => {
+                cube.turn(Turn333::new(CubeFace::Up, dir));
+                cube.turn(Turn333::new(CubeFace::Down, dir.invert()));
+                cube.transform(Transformation333::new(CubeAxis::UD, dir.invert()));
+            }
+            // S = F' B z
+            CubeAxis::FB => {
+                cube.turn(Turn333::new(CubeFace::Front, dir.invert()));
+                cube.turn(Turn333::new(CubeFace::Back, dir));
+                cube.transform(Transformation333::new(CubeAxis::FB, dir));
+            }
+        }
+    }
+
+    #[test]
+    fn test_insertions_on_multiple_slices() {
+        let alg = Algorithm::from_str("R U F D L B2 U' R2").unwrap();
+        let m = |dir| SliceInsertion::new(CubeAxis::LR, dir);
+        let e = |dir| SliceInsertion::new(CubeAxis::UD, dir);
+        let s = |dir| SliceInsertion::new(CubeAxis::FB, dir);
+        // The slice turns cancel out, so the centers end up solved again
+        let insertions = vec![
+            (1, m(Direction::Clockwise)),
+            (2, s(Direction::Half)),
+            (3, e(Direction::Clockwise)),
+            (5, e(Direction::CounterClockwise)),
+            (6, s(Direction::Half)),
+            (8, m(Direction::CounterClockwise)),
+        ];
+        let mut solution = Solution::new();
+        solution.add_step(SolutionStep {
+            variant: StepVariant::DRFIN(CubeAxis::UD),
+            alg: alg.clone(),
+            comment: "".to_string(),
+            slice_insertions: insertions.clone(),
+        });
+        solution.vr = true;
+
+        let mut expected = Cube333::default();
+        for idx in 0..=alg.normal_moves.len() {
+            for (_, insertion) in insertions.iter().filter(|(i, _)| *i == idx) {
+                turn_slice(&mut expected, *insertion);
+            }
+            if let Some(turn) = alg.normal_moves.get(idx) {
+                expected.turn(*turn);
+            }
+        }
+        let mut cube = Cube333::default();
+        cube.apply_alg(&solution.clone().into());
+        assert_eq!(expected, cube);
+
+        let output = solution.to_string();
+        assert!(output.contains("R ! U * F ^ D L # B2 * U' R2 %"));
+        assert!(output.contains("^ = E, # = E', ! = M, % = M', * = S2"));
+    }
+
+    #[test]
+    #[cfg(feature = "serde_support")]
+    fn test_deserialize_legacy_e_insertions() {
+        let insertion = |dir| SliceInsertion::new(CubeAxis::UD, dir);
+        let mut solution = Solution::new();
+        solution.add_step(SolutionStep {
+            variant: StepVariant::DRFIN(CubeAxis::UD),
+            alg: Algorithm::from_str("R U F D L").unwrap(),
+            comment: "".to_string(),
+            slice_insertions: vec![(1, insertion(Direction::Clockwise)), (4, insertion(Direction::Half))],
+        });
+        solution.vr = true;
+
+        let mut legacy = serde_json::to_value(&solution).unwrap();
+        let legacy_obj = legacy.as_object_mut().unwrap();
+        legacy_obj.remove("vr");
+        legacy_obj.insert("insertion_direction".to_string(), serde_json::to_value(Transformation333::Y).unwrap());
+        for step in legacy_obj["steps"].as_array_mut().unwrap() {
+            let step = step.as_object_mut().unwrap();
+            step.remove("slice_insertions");
+            let e_insertions = vec![(1, Direction::Clockwise), (4, Direction::Half)];
+            step.insert("e_insertions".to_string(), serde_json::to_value(e_insertions).unwrap());
+        }
+        let deserialized: Solution = serde_json::from_value(legacy).unwrap();
+        assert!(solution == deserialized);
+
+        let current: Solution = serde_json::from_value(serde_json::to_value(&solution).unwrap()).unwrap();
+        assert!(solution == current);
+    }
+}
@@ -40,7 +40,9 @@ impl ARStep {
 impl ARStep {
     pub fn new(dfs: DFSParameters, arm_eo_axis: HashMap<CubeAxis, Vec<CubeAxis>>) -> StepGroup {
         debug!("Step ar with options {dfs:?}");
+        // Sorted, so the order of the variants doesn't depend on the hash map
         let variants = arm_eo_axis.into_iter()
+            .sorted_by_key(|(axis, _)| *axis as usize)
             .flat_map(move |(ar, eo)|eo.into_iter().map(move |eo|(eo, ar.clone())))
             .filter_map(|(eo, ar)|match (eo, ar) {
                 (CubeAxis::UD, CubeAxis::FB) => Some((vec![Transformation333::X], StepVariant::AR { eo_axis: eo, dr_axis: ar })),
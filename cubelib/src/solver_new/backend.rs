@@ -0,0 +1,212 @@
+use std::sync::Arc;
+
+use crate::defs::StepKind;
+use crate::solver::backend::{SolutionStream, SolveRequest, Solver, SolverCapabilities, SolverError, SolverSteps};
+use crate::solver::df_search::CancelToken;
+use crate::solver_new::build_steps;
+use crate::solver_new::thread_util::{SolutionBound, ToWorker, WorkerContext};
+use crate::solver_new::util_steps::{FilterDup, FilterLastMoveNotPrime};
+
+/// The channel based solver. Steps run concurrently and can be combined into parallel step groups.
+#[derive(Clone, Default)]
+pub struct MultiPathChannelSolver {
+    context: WorkerContext,
+}
+
+impl MultiPathChannelSolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Statistics and execution settings of the context are used for every request
+    pub fn with_context(context: WorkerContext) -> Self {
+        Self {
+            context
+        }
+    }
+}
+
+impl Solver for MultiPathChannelSolver {
+    fn name(&self) -> &'static str {
+        "multi-path-channel"
+    }
+
+    fn capabilities(&self) -> SolverCapabilities {
+        SolverCapabilities {
+            step_orders: vec![
+                (None, StepKind::EO),
+                (Some(StepKind::EO), StepKind::RZP),
+                (Some(StepKind::EO), StepKind::AR),
+                (Some(StepKind::EO), StepKind::DR),
+                (Some(StepKind::RZP), StepKind::DR),
+                (Some(StepKind::AR), StepKind::DR),
+                (Some(StepKind::DR), StepKind::HTR),
+                (Some(StepKind::DR), StepKind::FIN),
+                (Some(StepKind::DR), StepKind::FINLS),
+                (Some(StepKind::HTR), StepKind::FR),
+                (Some(StepKind::HTR), StepKind::FRLS),
+                (Some(StepKind::HTR), StepKind::FIN),
+                (Some(StepKind::HTR), StepKind::FINLS),
+                (Some(StepKind::FR), StepKind::FIN),
+                (Some(StepKind::FRLS), StepKind::FINLS),
+                (Some(StepKind::FINLS), StepKind::VR),
+                (Some(StepKind::FINLS), StepKind::FIN),
+                (Some(StepKind::VR), StepKind::FIN),
+                (None, StepKind::GEN),
+                (Some(StepKind::EO), StepKind::GEN),
+                (Some(StepKind::DR), StepKind::GEN),
+                (Some(StepKind::HTR), StepKind::GEN),
+                (Some(StepKind::FR), StepKind::GEN),
+            ],
+            parameters: vec!["triggers", "subsets", "htr-breaking", "max-use", "step-limit", "vr-in", "moves"],
+            step_groups: true,
+            shortest_only: true,
+        }
+    }
+
+    fn solve(&self, mut request: SolveRequest) -> Result<SolutionStream, SolverError> {
+        self.check(&request)?;
+        let mut predicates = vec![];
+        if request.filter_last_move() {
+            predicates.push(FilterLastMoveNotPrime::new());
+        }
+        predicates.push(FilterDup::new());
+
+        let steps = std::mem::replace(&mut request.steps, SolverSteps::Configs(vec![]));
+        let mut steps = match steps {
+            SolverSteps::Configs(steps) => build_steps(steps).map_err(SolverError::InvalidSteps)?,
+            SolverSteps::Group(group, _) => group,
+        };
+        steps.check_cube(&request.cube).map_err(SolverError::UnsolvableCube)?;
+        steps.with_predicates(predicates);
+        if request.quality > 0 && !request.shortest_only {
+            steps.apply_step_limit(request.quality);
+        }
+
+        let cancel_token = Arc::new(CancelToken::child_of(request.cancel_token.clone()));
+        let mut context = self.context.clone()
+            .with_cancel_token(cancel_token.clone());
+        if request.shortest_only {
+            context = context.with_bound(Arc::new(SolutionBound::new(request.min_length)));
+        }
+        let worker = steps.into_worker_with_context(request.cube, context);
+        Ok(SolutionStream::new(worker, &request, cancel_token))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+    use std::sync::Arc;
+    use crate::algs::Algorithm;
+    use crate::defs::StepKind;
+    use crate::solver::backend::{SolveRequest, Solver, SolverError, Unsupported};
+    use crate::solver::scoring::{NissSwitches, Ranking};
+    use crate::solver_new::backend::MultiPathChannelSolver;
+    use crate::steps::step::StepConfig;
+
+    #[test]
+    fn test_solve_request() {
+        let cube = Algorithm::from_str("R' U' F L' B L' B2 U B2 D U2 L2 F2 L2 R2 D' L2 B' F' R' F L' F' U' R' U' F").unwrap().into();
+        let solver = MultiPathChannelSolver::new();
+        let request = SolveRequest {
+            min_length: 4,
+            solution_count: Some(3),
+            ..SolveRequest::new(cube, vec![StepConfig::new(StepKind::EO)])
+        };
+        let solutions: Vec<_> = solver.solve(request).unwrap().collect();
+        assert_eq!(3, solutions.len());
+        assert!(solutions.iter().all(|s| s.len() >= 4));
+
+        let request = SolveRequest::new(cube, vec![StepConfig::new(StepKind::DR)]);
+        assert_eq!(Some(SolverError::Unsupported("multi-path-channel", Unsupported::StepOrder(None, StepKind::DR))), solver.solve(request).err());
+
+        let request = SolveRequest::new(cube, vec![StepConfig::new(StepKind::EO), StepConfig::new(StepKind::DR), StepConfig::new(StepKind::EO)]);
+        assert_eq!(Some(SolverError::Unsupported("multi-path-channel", Unsupported::StepOrder(Some(StepKind::DR), StepKind::EO))), solver.check(&request).err());
+        assert!(crate::solver_new::build_step(Some(&StepConfig::new(StepKind::DR)), StepConfig::new(StepKind::EO)).is_err());
+    }
+
+    #[test]
+    fn test_shortest_only() {
+        let cube = Algorithm::from_str("R' U' F L' B L' B2 U B2 D U2 L2 F2 L2 R2 D' L2 B' F' R' F L' F' U' R' U' F").unwrap().into();
+        let solver = MultiPathChannelSolver::new();
+        let steps = || vec![StepConfig::new(StepKind::EO), StepConfig::new(StepKind::DR)];
+        let optimal = solver.solve(SolveRequest {
+            quality: 0,
+            solution_count: Some(1),
+            ..SolveRequest::new(cube, steps())
+        }).unwrap().next().unwrap();
+
+        // Without a solution count, the search only ends because nothing longer than the best solution is explored
+        let solutions: Vec<_> = solver.solve(SolveRequest {
+            shortest_only: true,
+            ..SolveRequest::new(cube, steps())
+        }).unwrap().collect();
+        assert!(!solutions.is_empty());
+        assert!(solutions.iter().all(|s| s.len() == optimal.len()));
+    }
+
+    #[test]
+    fn test_shortest_only_with_min_length() {
+        let cube = Algorithm::from_str("R' U' F L' B L' B2 U B2 D U2 L2 F2 L2 R2 D' L2 B' F' R' F L' F' U' R' U' F").unwrap().into();
+        let solver = MultiPathChannelSolver::new();
+        // The DR moves can cancel with the EO, so the shortest solution may be shorter than the two minimums added up
+        let steps = || vec![StepConfig::new(StepKind::EO), StepConfig { min: Some(4), ..StepConfig::new(StepKind::DR) }];
+        let optimal = solver.solve(SolveRequest {
+            quality: 0,
+            solution_count: Some(1),
+            ..SolveRequest::new(cube, steps())
+        }).unwrap().next().unwrap();
+
+        let solutions: Vec<_> = solver.solve(SolveRequest {
+            shortest_only: true,
+            ..SolveRequest::new(cube, steps())
+        }).unwrap().collect();
+        assert!(!solutions.is_empty());
+        assert!(solutions.iter().all(|s| s.len() == optimal.len()));
+    }
+
+    #[test]
+    fn test_ranking_without_bounds_streams() {
+        let cube = Algorithm::from_str("R' U' F L' B L' B2 U B2 D U2 L2 F2 L2 R2 D' L2 B' F' R' F L' F' U' R' U' F").unwrap().into();
+        let solver = MultiPathChannelSolver::new();
+        let ranking = Arc::new(Ranking::new().then_by(NissSwitches));
+        let request = SolveRequest {
+            quality: 0,
+            ranking: Some(ranking.clone()),
+            ..SolveRequest::new(cube, vec![StepConfig::new(StepKind::EO)])
+        };
+        // Without a length or count limit the search never ends, so the solutions must be ranked while it runs
+        let solutions: Vec<_> = solver.solve(request).unwrap().take(20).collect();
+        assert_eq!(20, solutions.len());
+        for (a, b) in solutions.iter().zip(solutions.iter().skip(1)) {
+            assert!(a.len() <= b.len());
+            if a.len() == b.len() {
+                assert!(Ranking::compare(&ranking.scores(&cube, a), &ranking.scores(&cube, b)).is_le());
+            }
+        }
+    }
+
+    #[test]
+    fn test_ranking_with_solution_count_streams() {
+        let cube = Algorithm::from_str("R' U' F L' B L' B2 U B2 D U2 L2 F2 L2 R2 D' L2 B' F' R' F L' F' U' R' U' F").unwrap().into();
+        let solver = MultiPathChannelSolver::new();
+        let ranking = Arc::new(Ranking::new().then_by(NissSwitches));
+        let request = SolveRequest {
+            quality: 0,
+            solution_count: Some(1),
+            ranking: Some(ranking.clone()),
+            ..SolveRequest::new(cube, vec![StepConfig::new(StepKind::EO), StepConfig::new(StepKind::DR)])
+        };
+        // The search is unbounded, so this only returns if the shortest solutions are ranked on their own
+        let best = solver.solve(request).unwrap().next().unwrap();
+        let shortest: Vec<_> = solver.solve(SolveRequest {
+            quality: 0,
+            ..SolveRequest::new(cube, vec![StepConfig::new(StepKind::EO), StepConfig::new(StepKind::DR)])
+        }).unwrap()
+            .take_while(|s| s.len() == best.len())
+            .collect();
+        assert!(!shortest.is_empty());
+        assert!(shortest.iter().all(|s| Ranking::compare(&ranking.scores(&cube, &best), &ranking.scores(&cube, s)).is_le()));
+    }
+}
@@ -79,7 +79,9 @@ impl DRStep {
 
     pub fn new(dfs: DFSParameters, axis: HashMap<CubeAxis, Vec<CubeAxis>>, subsets: Vec<Subset>, from_ar: bool) -> StepGroup {
         debug!("Step dr with options {dfs:?}");
+        // Sorted, so the order of the variants doesn't depend on the hash map
         let variants = axis.into_iter()
+            .sorted_by_key(|(axis, _)| *axis as usize)
             .flat_map(move |(dr, eo)|eo.into_iter().map(move |eo|(eo, dr.clone())))
             .filter_map(|(eo,dr)|match (eo, dr) {
                 (CubeAxis::UD, CubeAxis::FB) => Some((dr, vec![Transformation333::X], StepVariant::DR { eo_axis: eo, dr_axis: dr })),
@@ -142,7 +144,9 @@ impl DRStep {
             trigger_variants.append(&mut Self::generate_trigger_variations(trigger));
         }
 
+        // Sorted, so the order of the variants doesn't depend on the hash map
         let variants = axis.into_iter()
+            .sorted_by_key(|(axis, _)| *axis as usize)
             .flat_map(move |(dr, eo)|eo.into_iter().map(move |eo|(eo, dr.clone())))
             .filter_map(|(eo,dr)|match (eo, dr) {
                 (CubeAxis::UD, CubeAxis::FB) => Some((vec![Transformation333::X], (eo, dr))),
@@ -187,7 +191,7 @@ impl DRStep {
         StepGroup::parallel(variants)
     }
 
-    fn generate_trigger_variations(mut trigger: Algorithm) -> Vec<Vec<Turn333>> {
+    pub(crate) fn generate_trigger_variations(mut trigger: Algorithm) -> Vec<Vec<Turn333>> {
         if !trigger.inverse_moves.is_empty() {
             error!("Triggers with inverse components are not supported");
             return vec![];
@@ -431,7 +435,7 @@ impl Step for RZPStep {
     }
 }
 
-fn calc_rzp_state(cube: &Cube333) -> (u8, u8) {
+pub(crate) fn calc_rzp_state(cube: &Cube333) -> (u8, u8) {
     let eo_count_lr = cube.edges.count_bad_edges_lr();
     let co_count_ud = COCountUD::co_count(cube);
     (co_count_ud, eo_count_lr as u8)
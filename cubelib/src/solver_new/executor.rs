@@ -0,0 +1,394 @@
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::ops::{Coroutine, CoroutineState};
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use crossbeam::channel::{Select, TryRecvError, TrySendError};
+use crate::solver_new::{Receiver, Sender};
+
+/// A resumable unit of work. Tasks yield whenever they would otherwise block on a channel,
+/// which allows any number of them to share a fixed number of threads.
+pub type Task = Pin<Box<dyn Coroutine<Yield = (), Return = ()> + Send>>;
+
+/// Wraps a coroutine that is not `Send` only because it holds references into its own captured state.
+/// The coroutine is moved between threads as a whole and never resumed concurrently, so this is fine
+/// as long as everything it captures and everything it keeps across a yield is `Send`. See [`SelfContained::new`].
+pub(crate) struct SelfContained<C>(C);
+
+// Safety: the coroutine can only be created by `new`, whose callers guarantee that everything it holds is `Send`
+unsafe impl<C> Send for SelfContained<C> {}
+
+impl<C: Coroutine<Yield = (), Return = ()>> SelfContained<C> {
+    /// `coroutine` is a function pointer, so it can't capture anything besides `state`
+    ///
+    /// # Safety
+    /// The compiler only checks the captured state. The coroutine must not keep any value that isn't `Send`,
+    /// like an `Rc`, a `MutexGuard` or a `RefCell` borrow, alive across a yield, as it may be resumed on another thread.
+    /// This includes the coroutines it yields from.
+    pub(crate) unsafe fn new<S: Send>(state: S, coroutine: fn(S) -> C) -> Self {
+        Self(coroutine(state))
+    }
+}
+
+impl<C: Coroutine<Yield = (), Return = ()>> Coroutine for SelfContained<C> {
+    type Yield = ();
+    type Return = ();
+
+    fn resume(self: Pin<&mut Self>, arg: ()) -> CoroutineState<(), ()> {
+        // Structural pinning, we never move the inner coroutine out
+        unsafe { self.map_unchecked_mut(|s| &mut s.0) }.resume(arg)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ExecutionMode {
+    /// Runs all step workers on the thread that consumes the solutions. Results don't depend on thread timing,
+    /// which makes this useful for debugging.
+    SingleThreaded,
+    /// Runs all step workers on a pool with the given number of threads
+    Threads(usize),
+}
+
+impl ExecutionMode {
+    // Select picks a random operation if several are ready, which would make single threaded runs differ from each other
+    pub(crate) fn select<'a>(&self) -> Select<'a> {
+        match self {
+            ExecutionMode::SingleThreaded => Select::new_biased(),
+            ExecutionMode::Threads(_) => Select::new(),
+        }
+    }
+
+    /// Number of search nodes a step task visits before it yields to other tasks, even if it hasn't found a solution yet.
+    /// Threads get preempted anyway, so this is only needed when everything runs on one thread.
+    pub(crate) fn work_slice(&self) -> Option<usize> {
+        match self {
+            ExecutionMode::SingleThreaded => Some(WORK_SLICE_NODES),
+            ExecutionMode::Threads(_) => None,
+        }
+    }
+}
+
+const WORK_SLICE_NODES: usize = 10_000;
+
+impl Default for ExecutionMode {
+    #[cfg(not(target_arch = "wasm32"))]
+    fn default() -> Self {
+        ExecutionMode::Threads(thread::available_parallelism().map(|x| x.get()).unwrap_or(4))
+    }
+
+    // There are no threads in the browser, the solver runs on the web worker it was called from
+    #[cfg(target_arch = "wasm32")]
+    fn default() -> Self {
+        ExecutionMode::SingleThreaded
+    }
+}
+
+thread_local! {
+    // Counts successful channel operations on this thread. An executor uses this to tell if a task
+    // did anything useful before it yielded, so it can back off when all tasks are waiting.
+    static CHANNEL_OPS: Cell<u64> = const { Cell::new(0) };
+}
+
+fn channel_ops() -> u64 {
+    CHANNEL_OPS.with(|c| c.get())
+}
+
+pub(crate) fn try_send<T>(tx: &Sender<T>, item: T) -> Result<(), TrySendError<T>> {
+    let result = tx.try_send(item);
+    if result.is_ok() {
+        CHANNEL_OPS.with(|c| c.set(c.get() + 1));
+    }
+    result
+}
+
+pub(crate) fn try_recv<T>(rc: &Receiver<T>) -> Result<T, TryRecvError> {
+    let result = rc.try_recv();
+    if result.is_ok() {
+        CHANNEL_OPS.with(|c| c.set(c.get() + 1));
+    }
+    result
+}
+
+/// Sends `$item` from within a task, yielding while the channel is full
+macro_rules! send_or_yield {
+    ($tx:expr, $item:expr) => {{
+        let mut item = $item;
+        loop {
+            match $crate::solver_new::executor::try_send($tx, item) {
+                Ok(()) => break Ok(()),
+                Err(crossbeam::channel::TrySendError::Full(v)) => {
+                    item = v;
+                    yield;
+                }
+                Err(crossbeam::channel::TrySendError::Disconnected(v)) => break Err(crossbeam::channel::SendError(v)),
+            }
+        }
+    }};
+}
+
+/// Receives from within a task, yielding while the channel is empty
+macro_rules! recv_or_yield {
+    ($rc:expr) => {{
+        loop {
+            match $crate::solver_new::executor::try_recv($rc) {
+                Ok(v) => break Ok(v),
+                Err(crossbeam::channel::TryRecvError::Empty) => yield,
+                Err(crossbeam::channel::TryRecvError::Disconnected) => break Err(crossbeam::channel::RecvError),
+            }
+        }
+    }};
+}
+
+/// Waits for one of the operations of a `Select` to become ready from within a task
+macro_rules! ready_or_yield {
+    ($sel:expr) => {{
+        loop {
+            match $sel.try_ready() {
+                Ok(index) => break index,
+                Err(_) => yield,
+            }
+        }
+    }};
+}
+
+/// Runs a nested coroutine to completion from within a task, passing its yields through
+macro_rules! yield_from {
+    ($coroutine:expr) => {{
+        let mut coroutine = std::pin::pin!($coroutine);
+        loop {
+            match coroutine.as_mut().resume(()) {
+                std::ops::CoroutineState::Yielded(()) => yield,
+                std::ops::CoroutineState::Complete(result) => break result,
+            }
+        }
+    }};
+}
+
+pub(crate) use {ready_or_yield, recv_or_yield, send_or_yield, yield_from};
+
+pub(crate) enum Executor {
+    Local(LocalExecutor),
+    Pool(ThreadPool),
+}
+
+impl Executor {
+    pub fn new(mode: ExecutionMode, tasks: Vec<Task>) -> Self {
+        match mode {
+            ExecutionMode::SingleThreaded => Executor::Local(LocalExecutor::new(tasks)),
+            #[cfg(target_arch = "wasm32")]
+            ExecutionMode::Threads(_) => Executor::Local(LocalExecutor::new(tasks)),
+            #[cfg(not(target_arch = "wasm32"))]
+            ExecutionMode::Threads(threads) => Executor::Pool(ThreadPool::new(threads.max(1), tasks)),
+        }
+    }
+}
+
+pub(crate) struct LocalExecutor {
+    tasks: VecDeque<Task>,
+}
+
+impl LocalExecutor {
+    pub fn new(tasks: Vec<Task>) -> Self {
+        Self {
+            tasks: tasks.into(),
+        }
+    }
+
+    /// Resumes every task once, in a fixed order. Returns false once all tasks are finished.
+    pub fn run_round(&mut self) -> bool {
+        for _ in 0..self.tasks.len() {
+            let mut task = self.tasks.pop_front().unwrap();
+            if let CoroutineState::Yielded(()) = task.as_mut().resume(()) {
+                self.tasks.push_back(task);
+            }
+        }
+        !self.tasks.is_empty()
+    }
+}
+
+struct PoolState {
+    queue: Mutex<VecDeque<Task>>,
+    remaining: AtomicUsize,
+    stopped: AtomicBool,
+    wakeup: Condvar,
+}
+
+/// Threads are detached. Once stopped, they exit after resuming the task they are running at that moment,
+/// which is quick because stopping a worker also cancels its tasks.
+pub(crate) struct ThreadPool {
+    state: Arc<PoolState>,
+    started: bool,
+    size: usize,
+}
+
+impl ThreadPool {
+    fn new(size: usize, tasks: Vec<Task>) -> Self {
+        Self {
+            state: Arc::new(PoolState {
+                remaining: AtomicUsize::new(tasks.len()),
+                queue: Mutex::new(tasks.into()),
+                stopped: AtomicBool::new(false),
+                wakeup: Condvar::new(),
+            }),
+            started: false,
+            size,
+        }
+    }
+
+    pub fn start(&mut self) {
+        if self.started {
+            return;
+        }
+        self.started = true;
+        let size = self.size.min(self.state.remaining.load(Ordering::Relaxed)).max(1);
+        for _ in 0..size {
+            let state = self.state.clone();
+            thread::spawn(move || Self::run(state));
+        }
+    }
+
+    // Tasks that are waiting on a channel yield right away. If a thread went through the whole queue
+    // without seeing any channel activity we sleep for a bit instead of spinning.
+    fn run(state: Arc<PoolState>) {
+        let mut idle_polls = 0;
+        let mut backoff = Duration::from_micros(10);
+        loop {
+            if state.stopped.load(Ordering::Relaxed) {
+                return;
+            }
+            let (mut task, queued) = {
+                let mut queue = state.queue.lock().unwrap();
+                match queue.pop_front() {
+                    Some(task) => {
+                        let len = queue.len();
+                        (task, len)
+                    },
+                    None => {
+                        if state.remaining.load(Ordering::Acquire) == 0 {
+                            return;
+                        }
+                        // All remaining tasks are being run by other threads right now
+                        let _ = state.wakeup.wait_timeout(queue, Duration::from_millis(1)).unwrap();
+                        continue;
+                    }
+                }
+            };
+            let before = channel_ops();
+            match task.as_mut().resume(()) {
+                CoroutineState::Yielded(()) => {
+                    state.queue.lock().unwrap().push_back(task);
+                    state.wakeup.notify_one();
+                }
+                CoroutineState::Complete(()) => {
+                    drop(task);
+                    if state.remaining.fetch_sub(1, Ordering::AcqRel) == 1 {
+                        state.wakeup.notify_all();
+                    }
+                }
+            }
+            if channel_ops() == before {
+                idle_polls += 1;
+            } else {
+                idle_polls = 0;
+                backoff = Duration::from_micros(10);
+            }
+            if idle_polls > queued {
+                thread::sleep(backoff);
+                backoff = (backoff * 2).min(Duration::from_millis(1));
+            }
+        }
+    }
+
+    /// Doesn't wait for the threads to exit, so this can be called from async code
+    pub fn stop(&mut self) {
+        self.state.stopped.store(true, Ordering::Relaxed);
+        self.state.wakeup.notify_all();
+        // Dropping the tasks closes their channels. Tasks that are running right now are dropped once their thread exits
+        self.state.queue.lock().unwrap().clear();
+    }
+}
+
+impl Drop for ThreadPool {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+    use crate::algs::Algorithm;
+    use crate::solver::solution::Solution;
+    use crate::defs::StepKind;
+    use crate::solver_new::{build_steps, TryRecvError};
+    use crate::solver_new::eo::EOStep;
+    use crate::solver_new::executor::{ExecutionMode, WORK_SLICE_NODES};
+    use crate::solver_new::stats::SolverStats;
+    use crate::solver_new::thread_util::WorkerContext;
+    use crate::steps::step::StepConfig;
+
+    #[test]
+    fn test_single_threaded_matches_pool() {
+        let cube = Algorithm::from_str("R' U' F L' B L' B2 U B2 D U2 L2 F2 L2 R2 D' L2 B' F' R' F L' F' U' R' U' F").unwrap().into();
+        let solve = |execution: ExecutionMode| EOStep::builder()
+            .max_length(5)
+            .build()
+            .into_worker_with_context(cube, WorkerContext::default().with_execution(execution))
+            .map(|s: Solution| Into::<Algorithm>::into(s))
+            .collect::<Vec<Algorithm>>();
+        let single = solve(ExecutionMode::SingleThreaded);
+        assert!(!single.is_empty());
+        assert_eq!(single, solve(ExecutionMode::SingleThreaded));
+        // Which of two equivalent solutions makes it past the duplicate filter depends on timing,
+        // but solutions have to come out in the same order of length either way
+        let lengths = |solutions: Vec<Algorithm>| solutions.iter().map(|s| s.len()).collect::<Vec<usize>>();
+        assert_eq!(lengths(single), lengths(solve(ExecutionMode::Threads(2))));
+    }
+
+    #[test]
+    fn test_deterministic_matches_single_threaded() {
+        let cube = Algorithm::from_str("R' U' F L' B L' B2 U B2 D U2 L2 F2 L2 R2 D' L2 B' F' R' F L' F' U' R' U' F").unwrap().into();
+        let solve = |execution: ExecutionMode| EOStep::builder()
+            .max_length(5)
+            .build()
+            .into_worker_with_context(cube, WorkerContext::default().with_execution(execution).with_deterministic(true))
+            .map(|s: Solution| Into::<Algorithm>::into(s))
+            .collect::<Vec<Algorithm>>();
+        let single = solve(ExecutionMode::SingleThreaded);
+        assert!(!single.is_empty());
+        for _ in 0..5 {
+            assert_eq!(single, solve(ExecutionMode::Threads(4)));
+        }
+    }
+
+    #[test]
+    fn test_single_threaded_yields_during_search() {
+        let cube = Algorithm::from_str("R' U' F L' B L' B2 U B2 D U2 L2 F2 L2 R2 D' L2 B' F' R' F L' F' U' R' U' F").unwrap().into();
+        let stats = SolverStats::new();
+        let ctx = WorkerContext::default()
+            .with_execution(ExecutionMode::SingleThreaded)
+            .with_stats(stats.clone());
+        let mut worker = build_steps(vec![StepConfig::new(StepKind::EO), StepConfig::new(StepKind::DR)]).unwrap()
+            .into_worker_with_context(cube, ctx);
+        // Every call runs one round, in which each step task (EO and one DR task per axis) may only visit one work slice worth of nodes
+        let mut nodes = 0;
+        let mut max_round = 0;
+        loop {
+            let result = worker.try_next();
+            let total = stats.snapshot().total_nodes();
+            max_round = max_round.max(total - nodes);
+            nodes = total;
+            match result {
+                Ok(_) => break,
+                Err(TryRecvError::Empty) => continue,
+                Err(TryRecvError::Disconnected) => panic!("No solution found"),
+            }
+        }
+        assert!(nodes > 4 * WORK_SLICE_NODES as u64);
+        assert!(max_round <= 4 * WORK_SLICE_NODES as u64, "{max_round}");
+    }
+}
@@ -6,7 +6,9 @@ use log::debug;
 use crate::cube::*;
 use crate::defs::StepVariant;
 use crate::solver::lookup_table;
-use crate::solver::lookup_table::{DepthEstimate, InMemoryIndexTable, MemoryMappedIndexTable};
+use crate::solver::lookup_table::{DepthEstimate, InMemoryIndexTable};
+#[cfg(feature = "fs")]
+use crate::solver::lookup_table::MemoryMappedIndexTable;
 use crate::solver::solution::Solution;
 use crate::solver_new::*;
 use crate::solver_new::group::StepGroup;
@@ -171,20 +173,23 @@ mod test {
                     variant: StepVariant::EO(CubeAxis::LR),
                     alg: Algorithm::from_str("D' R (U L)").unwrap(),
                     comment: "".to_string(),
+                    slice_insertions: vec![],
                 },
                 SolutionStep {
                     variant: StepVariant::DR { eo_axis: CubeAxis::LR, dr_axis: CubeAxis::FB },
                     alg: Algorithm::from_str("(B' L2 D2 U2 F2 B' D)").unwrap(),
                     comment: "".to_string(),
+                    slice_insertions: vec![],
                 },
                 SolutionStep {
                     variant: StepVariant::HTR(CubeAxis::FB),
                     alg: Algorithm::from_str("(L2 B)").unwrap(),
                     comment: "".to_string(),
+                    slice_insertions: vec![],
                 },
             ],
             ends_on_normal: false,
-            vr_solution: None,
+            vr: false,
         };
         assert_eq!(0, DRFinishStep::get_possible_cancellation_count(&solution, CubeAxis::FB));
         assert_eq!(3, DRFinishStep::get_possible_cancellation_count(&solution, CubeAxis::LR));
@@ -199,20 +204,23 @@ mod test {
                     variant: StepVariant::EO(CubeAxis::LR),
                     alg: Algorithm::from_str("D' L F2 R (U L)").unwrap(),
                     comment: "".to_string(),
+                    slice_insertions: vec![],
                 },
                 SolutionStep {
                     variant: StepVariant::DR { eo_axis: CubeAxis::LR, dr_axis: CubeAxis::FB },
                     alg: Algorithm::from_str("(B' L2 D2 U2 F2 B' D)").unwrap(),
                     comment: "".to_string(),
+                    slice_insertions: vec![],
                 },
                 SolutionStep {
                     variant: StepVariant::HTR(CubeAxis::FB),
                     alg: Algorithm::from_str("(L2 B)").unwrap(),
                     comment: "".to_string(),
+                    slice_insertions: vec![],
                 },
             ],
             ends_on_normal: false,
-            vr_solution: None,
+            vr: false,
         };
         assert_eq!(0, DRFinishStep::get_possible_cancellation_count(&solution, CubeAxis::FB));
         assert_eq!(7, DRFinishStep::get_possible_cancellation_count(&solution, CubeAxis::LR));
@@ -227,20 +235,23 @@ mod test {
                     variant: StepVariant::EO(CubeAxis::LR),
                     alg: Algorithm::from_str("(D' R U L)").unwrap(),
                     comment: "".to_string(),
+                    slice_insertions: vec![],
                 },
                 SolutionStep {
                     variant: StepVariant::DR { eo_axis: CubeAxis::LR, dr_axis: CubeAxis::FB },
                     alg: Algorithm::from_str("F B").unwrap(),
                     comment: "".to_string(),
+                    slice_insertions: vec![],
                 },
                 SolutionStep {
                     variant: StepVariant::HTR(CubeAxis::FB),
                     alg: Algorithm::from_str("(L2 B)").unwrap(),
                     comment: "".to_string(),
+                    slice_insertions: vec![],
                 },
             ],
             ends_on_normal: false,
-            vr_solution: None,
+            vr: false,
         };
         assert_eq!(4, DRFinishStep::get_possible_cancellation_count(&solution, CubeAxis::FB));
         assert_eq!(0, DRFinishStep::get_possible_cancellation_count(&solution, CubeAxis::LR));
@@ -370,11 +381,17 @@ fn gen_htr_ls_finish() -> HTRLeaveSliceFinishPruningTable {
 }
 
 fn gen_dr_finish() -> DRFinishPruningTable {
-    Box::new(MemoryMappedIndexTable::load_and_save("drfin", ||lookup_table::generate_large_table(&HTR_DR_UD_MOVESET)).0)
+    #[cfg(feature = "fs")]
+    return Box::new(MemoryMappedIndexTable::load_and_save("drfin", ||lookup_table::generate_large_table(&HTR_DR_UD_MOVESET)).0);
+    #[cfg(not(feature = "fs"))]
+    Box::new(InMemoryIndexTable::load_and_save("drfin", ||lookup_table::generate_large_table(&HTR_DR_UD_MOVESET)).0)
 }
 
 fn gen_dr_leave_slice_finish() -> DRLeaveSliceFinishPruningTable {
-    Box::new(MemoryMappedIndexTable::load_and_save("drfinls", ||lookup_table::generate_large_table(&HTR_DR_UD_MOVESET)).0)
+    #[cfg(feature = "fs")]
+    return Box::new(MemoryMappedIndexTable::load_and_save("drfinls", ||lookup_table::generate_large_table(&HTR_DR_UD_MOVESET)).0);
+    #[cfg(not(feature = "fs"))]
+    Box::new(InMemoryIndexTable::load_and_save("drfinls", ||lookup_table::generate_large_table(&HTR_DR_UD_MOVESET)).0)
 }
 
 struct DRAxisPreStepCheck(CubeAxis);
@@ -0,0 +1,257 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::{LazyLock, Mutex};
+
+use itertools::Itertools;
+use log::debug;
+
+use crate::algs::Algorithm;
+use crate::cube::{Cube333, Direction, Transformation333, Turn333};
+use crate::cube::turn::TurnableMut;
+use crate::defs::{NissSwitchType, StepKind, StepVariant};
+use crate::solver::solution::Solution;
+use crate::solver_new::group::StepGroup;
+use crate::solver_new::step::{DFSParameters, MoveSet};
+use crate::solver_new::subgroup::{PieceMask, PieceTracking, Subgroup};
+use crate::solver_new::Step;
+use crate::steps::step::{PostStepCheck, PreStepCheck, StepConfig};
+
+// Upper bound for the number of states of a single pruning table
+const MAX_TABLE_STATES: usize = 2_000_000;
+
+type TableCache = HashMap<(PieceMask, Vec<Turn333>), Subgroup>;
+
+/// Solves the cube using only the given generators, e.g. `<R, U>` or `<U, D, F2, B2, L2, R2>`.
+///
+/// The heuristic is the maximum of several pruning tables, each of them covering a few of the pieces the generators can move.
+/// Pieces that none of the generators move have to be solved already.
+/// To get into a subgroup with restricted moves instead of solving the cube, see [`crate::solver_new::subgroup::SubgroupBuilder::moves`].
+pub struct GeneratorStep {
+    generators: Vec<Turn333>,
+    tables: Vec<Subgroup>,
+    moved_edges: [bool; 12],
+    moved_corners: [bool; 8],
+    options: DFSParameters,
+    move_set: MoveSet,
+    pre_step_trans: Vec<Transformation333>,
+}
+
+/// All moves that can be made with the generators. A quarter turn generator also allows the half turn and the inverse
+pub fn expand_generators(generators: &[Turn333]) -> Vec<Turn333> {
+    generators.iter()
+        .flat_map(|g| match g.dir {
+            Direction::Half => vec![*g],
+            _ => vec![Turn333::new(g.face, Direction::Clockwise), Turn333::new(g.face, Direction::CounterClockwise), Turn333::new(g.face, Direction::Half)],
+        })
+        .unique()
+        .collect()
+}
+
+impl GeneratorStep {
+    pub fn from_generators(generators: &[Turn333], options: DFSParameters) -> Result<StepGroup, String> {
+        if generators.is_empty() {
+            return Err("At least one generator is required".to_string());
+        }
+        let moves = expand_generators(generators);
+        let mut moved_edges = [false; 12];
+        let mut moved_corners = [false; 8];
+        for m in moves.iter() {
+            let mut cube = Cube333::default();
+            cube.turn(*m);
+            for (pos, edge) in cube.edges.get_edges().iter().enumerate() {
+                moved_edges[pos] |= edge.id as usize != pos;
+            }
+            for (pos, corner) in cube.corners.get_corners().iter().enumerate() {
+                moved_corners[pos] |= corner.id as usize != pos;
+            }
+        }
+
+        let mut tables = vec![];
+        let edges = (0..12).filter(|e| moved_edges[*e]).collect_vec();
+        for chunk in edges.chunks(chunk_size(edges.len(), 2)) {
+            let mask = chunk.iter().enumerate()
+                .fold(PieceMask::new(), |mut mask, (label, edge)| {
+                    mask.edges[*edge] = PieceTracking::piece(label as u8 + 1);
+                    mask
+                });
+            tables.push(Self::table(mask, &moves)?);
+        }
+        let corners = (0..8).filter(|c| moved_corners[*c]).collect_vec();
+        for chunk in corners.chunks(chunk_size(corners.len(), 3)) {
+            let mask = chunk.iter().enumerate()
+                .fold(PieceMask::new(), |mut mask, (label, corner)| {
+                    mask.corners[*corner] = PieceTracking::piece(label as u8 + 1);
+                    mask
+                });
+            tables.push(Self::table(mask, &moves)?);
+        }
+        debug!("Generator step <{}> uses {} pruning tables", generators.iter().join(", "), tables.len());
+
+        Ok(StepGroup::single(Box::new(GeneratorStep {
+            generators: generators.to_vec(),
+            tables,
+            moved_edges,
+            moved_corners,
+            options,
+            move_set: MoveSet::new_interned(&moves, &[]),
+            pre_step_trans: vec![],
+        })))
+    }
+
+    // Tables only depend on the mask and the moves, so they are shared by all steps with the same generators
+    fn table(mask: PieceMask, moves: &[Turn333]) -> Result<Subgroup, String> {
+        static TABLES: LazyLock<Mutex<TableCache>> = LazyLock::new(Default::default);
+        let key = (mask, moves.to_vec());
+        if let Some(table) = TABLES.lock().unwrap().get(&key) {
+            return Ok(table.clone());
+        }
+        let table = Subgroup::builder("gen", mask)
+            .moves(moves)
+            .max_states(MAX_TABLE_STATES)
+            .generators(&[])?;
+        TABLES.lock().unwrap().insert(key, table.clone());
+        Ok(table)
+    }
+
+    /// Returns why the cube can't be solved with the generators, if it can't
+    pub fn check(&self, cube: &Cube333) -> Result<(), String> {
+        let edges_solved = cube.edges.get_edges().iter().enumerate()
+            .all(|(pos, edge)| self.moved_edges[pos] || (edge.id as usize == pos && edge.oriented_fb && edge.oriented_ud));
+        let corners_solved = cube.corners.get_corners().iter().enumerate()
+            .all(|(pos, corner)| self.moved_corners[pos] || (corner.id as usize == pos && corner.orientation == 0));
+        if !edges_solved || !corners_solved {
+            return Err(format!("Pieces that <{}> can't move are not solved", self.generators.iter().join(", ")));
+        }
+        if self.tables.iter().any(|t| t.distance(cube).is_none()) {
+            return Err(format!("The cube state is not in <{}>", self.generators.iter().join(", ")));
+        }
+        Ok(())
+    }
+}
+
+// The largest number of pieces per table that keeps the table below MAX_TABLE_STATES,
+// assuming every piece can end up anywhere in any orientation
+fn chunk_size(pieces: usize, orientations: usize) -> usize {
+    let mut size = 1;
+    while size < pieces {
+        let states = (pieces - size..=pieces).product::<usize>() * orientations.pow(size as u32 + 1);
+        if states > MAX_TABLE_STATES {
+            break;
+        }
+        size += 1;
+    }
+    size
+}
+
+impl PreStepCheck for GeneratorStep {
+    fn is_cube_ready(&self, cube: &Cube333, _: Option<&Solution>) -> bool {
+        self.check(cube).is_ok()
+    }
+}
+
+impl PostStepCheck for GeneratorStep {
+    fn is_solution_admissible(&self, _: &Cube333, _: &Algorithm) -> bool {
+        true
+    }
+}
+
+impl Step for GeneratorStep {
+    fn get_dfs_parameters(&self) -> DFSParameters {
+        self.options
+    }
+
+    fn get_moveset(&self, _: &Cube333, _: usize) -> &'_ MoveSet {
+        &self.move_set
+    }
+
+    fn heuristic(&self, state: &Cube333, can_niss_switch: bool, _: usize) -> usize {
+        let mut heuristic = 0;
+        for table in self.tables.iter() {
+            match table.distance(state) {
+                Some(distance) => heuristic = heuristic.max(distance),
+                None => return usize::MAX,
+            }
+        }
+        if can_niss_switch {
+            heuristic.min(1)
+        } else {
+            heuristic
+        }
+    }
+
+    fn pre_step_trans(&self) -> &'_ Vec<Transformation333> {
+        &self.pre_step_trans
+    }
+
+    fn get_variant(&self) -> StepVariant {
+        StepVariant::GEN
+    }
+
+    fn check_cube(&self, cube: &Cube333) -> Result<(), String> {
+        self.check(cube)
+    }
+}
+
+impl GeneratorStep {
+    /// Builds the step from a config like `GEN[moves=R,U]`
+    pub fn from_config(config: StepConfig) -> Result<StepGroup, String> {
+        if config.kind != StepKind::GEN {
+            return Err(format!("Expected a gen step, got {}", config.kind));
+        }
+        let moves = config.params.get("moves")
+            .ok_or("The gen step requires a list of moves, e.g. gen[moves=R,U]".to_string())?;
+        let generators = Algorithm::from_str(moves.replace(",", " ").as_str())
+            .map_err(|_| format!("Unable to parse moves '{moves}'"))?
+            .normal_moves;
+        if let Some(key) = config.params.keys().find(|k| *k != "moves") {
+            return Err(format!("Unknown parameter '{key}' for the gen step"));
+        }
+        let options = DFSParameters {
+            niss_type: config.niss.unwrap_or(NissSwitchType::Never),
+            min_moves: config.min.unwrap_or(0) as usize,
+            max_moves: config.max.unwrap_or(20) as usize,
+            absolute_max_moves: config.absolute_max.map(|x| x as usize),
+            ignore_previous_step_restrictions: false,
+        };
+        Self::from_generators(&generators, options)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+    use crate::algs::Algorithm;
+    use crate::cube::{Cube333, CubeFace};
+    use crate::cube::turn::ApplyAlgorithm;
+    use crate::defs::StepKind;
+    use crate::solver::backend::{SolveRequest, Solver, SolverError};
+    use crate::solver::solution::Solution;
+    use crate::solver_new::backend::MultiPathChannelSolver;
+    use crate::solver_new::generator::GeneratorStep;
+    use crate::steps::step::StepConfig;
+
+    #[test]
+    fn test_two_gen() {
+        let mut config = StepConfig::new(StepKind::GEN);
+        config.params.insert("moves".to_string(), "R,U".to_string());
+        let scramble = Algorithm::from_str("R U R' U R U2 R' U' R2 U").unwrap();
+        let mut cube = Cube333::default();
+        cube.apply_alg(&scramble);
+        let solution: Solution = GeneratorStep::from_config(config.clone()).unwrap()
+            .into_worker(cube)
+            .next()
+            .unwrap();
+        let solution: Algorithm = solution.into();
+        assert!(solution.len() <= scramble.len());
+        assert!(solution.normal_moves.iter().all(|m| m.face == CubeFace::Right || m.face == CubeFace::Up));
+        cube.apply_alg(&solution);
+        assert_eq!(Cube333::default(), cube);
+
+        // F can't be undone with <R, U>
+        let mut cube = Cube333::default();
+        cube.apply_alg(&Algorithm::from_str("R U F").unwrap());
+        assert!(GeneratorStep::from_config(config.clone()).unwrap().into_worker(cube).next().is_none());
+        let err = MultiPathChannelSolver::new().solve(SolveRequest::new(cube, vec![config])).err();
+        assert!(matches!(err, Some(SolverError::UnsolvableCube(_))));
+    }
+}
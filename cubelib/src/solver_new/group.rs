@@ -1,14 +1,18 @@
-use std::{mem, thread};
 use std::cmp::min;
 use std::collections::HashSet;
-use std::thread::JoinHandle;
+use std::sync::Arc;
 
 use crossbeam::channel::{Select, SendError};
 use crate::cube::Cube333;
 use crate::solver::solution::Solution;
 use crate::solver_new::*;
+use crate::solver_new::executor;
+use crate::solver_new::executor::{ExecutionMode, ready_or_yield, recv_or_yield, send_or_yield, Task};
+use crate::solver_new::stats::SolverStats;
 use crate::solver_new::thread_util::*;
 use crate::solver_new::util_steps::FilterFirstN;
+#[cfg(feature = "futures")]
+use crate::solver_new::stream::SolverStream;
 
 const BUFFER_SIZE: usize = 10;
 
@@ -26,6 +30,25 @@ pub enum StepPredicateResult {
 
 pub trait StepPredicate: Send {
     fn check_solution(&self, solution: &Solution) -> StepPredicateResult;
+
+    fn name(&self) -> &'static str {
+        let name = std::any::type_name::<Self>();
+        name.rsplit("::").next().unwrap_or(name)
+    }
+}
+
+pub(crate) fn check_predicates(predicates: &[Box<dyn StepPredicate>], solution: &Solution, stats: Option<&SolverStats>) -> StepPredicateResult {
+    for p in predicates {
+        let result = p.check_solution(solution);
+        if let Some(stats) = stats {
+            stats.record_predicate(p.name(), &result);
+        }
+        match result {
+            StepPredicateResult::Accepted => {}
+            result => return result,
+        }
+    }
+    StepPredicateResult::Accepted
 }
 
 pub struct StepGroup {
@@ -64,6 +87,9 @@ impl StepGroup {
         }
     }
 
+    /// Runs the steps on the same input and merges their solutions by length. Solutions of the same length arrive
+    /// in whatever order the steps find them, unless [`WorkerContext::deterministic`] is set.
+    /// Then they are ordered by the position of their step in `steps`, and by the order the step found them in.
     pub fn parallel(steps: Vec<StepGroup>) -> StepGroup {
         Self::parallel_with_predicates(steps, vec![])
     }
@@ -94,95 +120,138 @@ impl StepGroup {
         let b: Box<dyn ToWorker + Send> = Box::new(self);
         SolverWorker::new(b, cube)
     }
+
+    /// Like [`StepGroup::into_worker`], but all workers report their progress to `stats`
+    pub fn into_worker_with_stats(self, cube: Cube333, stats: Arc<SolverStats>) -> SolverWorker {
+        self.into_worker_with_context(cube, WorkerContext::default().with_stats(stats))
+    }
+
+    pub fn into_worker_with_context(self, cube: Cube333, ctx: WorkerContext) -> SolverWorker {
+        let b: Box<dyn ToWorker + Send> = Box::new(self);
+        SolverWorker::new_with_context(b, cube, vec![], &ctx)
+    }
+
+    /// Like [`StepGroup::into_worker`], but returns the solutions as a [`futures_core::Stream`]
+    #[cfg(feature = "futures")]
+    pub fn into_stream(self, cube: Cube333) -> SolverStream {
+        self.into_stream_with_context(cube, WorkerContext::default())
+    }
+
+    #[cfg(feature = "futures")]
+    pub fn into_stream_with_context(self, cube: Cube333, ctx: WorkerContext) -> SolverStream {
+        let b: Box<dyn ToWorker + Send> = Box::new(self);
+        SolverStream::new_with_context(b, cube, &ctx)
+    }
 }
 
 impl ToWorker for StepGroup {
-    fn to_worker_box(mut self: Box<Self>, cube_state: Cube333, rc: Receiver<Solution>, tx: Sender<Solution>, mut additional_predicates: Vec<Box<dyn StepPredicate>>) -> Box<dyn Worker<()> + Send>
+    fn to_worker_box(mut self: Box<Self>, cube_state: Cube333, rc: Receiver<Solution>, tx: Sender<Solution>, mut additional_predicates: Vec<Box<dyn StepPredicate>>, ctx: &WorkerContext) -> Vec<Task>
     where
         Self: Send + 'static
     {
         self.predicates.append(&mut additional_predicates);
         match self.step_type {
-            StepType::Sequential(s) => Self::create_sequential_worker(s, cube_state, rc, tx, self.predicates),
-            StepType::Parallel(s) => Self::create_parallel_worker(s, cube_state, rc, tx, self.predicates),
-            StepType::Single(s) => s.to_worker_box(cube_state, rc, tx, self.predicates)
+            StepType::Sequential(s) => Self::create_sequential_worker(s, cube_state, rc, tx, self.predicates, ctx),
+            StepType::Parallel(s) => Self::create_parallel_worker(s, cube_state, rc, tx, self.predicates, ctx),
+            StepType::Single(s) => s.to_worker_box(cube_state, rc, tx, self.predicates, ctx)
+        }
+    }
+
+    fn min_moves(&self) -> usize {
+        match &self.step_type {
+            StepType::Sequential(steps) => steps.iter().map(|s| s.min_moves()).sum(),
+            StepType::Parallel(steps) => steps.iter().map(|s| s.min_moves()).min().unwrap_or(0),
+            StepType::Single(s) => s.min_moves(),
+        }
+    }
+
+    fn max_cancellation(&self) -> usize {
+        match &self.step_type {
+            StepType::Sequential(steps) => steps.iter().map(|s| s.max_cancellation()).sum(),
+            StepType::Parallel(steps) => steps.iter().map(|s| s.max_cancellation()).max().unwrap_or(0),
+            StepType::Single(s) => s.max_cancellation(),
+        }
+    }
+
+    fn check_cube(&self, cube: &Cube333) -> Result<(), String> {
+        match &self.step_type {
+            StepType::Sequential(steps) => steps.first().map_or(Ok(()), |s| s.check_cube(cube)),
+            // Only fails if none of the alternatives can start
+            StepType::Parallel(steps) => {
+                let mut result = Ok(());
+                for step in steps {
+                    result = step.check_cube(cube);
+                    if result.is_ok() {
+                        break;
+                    }
+                }
+                result
+            },
+            StepType::Single(s) => s.check_cube(cube),
         }
     }
 }
 
 impl StepGroup {
 
-    fn create_sequential_worker(mut steps: Vec<StepGroup>, cube_state: Cube333, mut rc: Receiver<Solution>, tx_last: Sender<Solution>, additional_predicates: Vec<Box<dyn StepPredicate>>) -> Box<dyn Worker<()> + Send>
+    fn create_sequential_worker(mut steps: Vec<StepGroup>, cube_state: Cube333, mut rc: Receiver<Solution>, tx_last: Sender<Solution>, additional_predicates: Vec<Box<dyn StepPredicate>>, ctx: &WorkerContext) -> Vec<Task>
     where
         Self: Sized + Send + 'static
     {
         assert!(!steps.is_empty());
         if steps.len() == 1 {
-            return steps.pop().unwrap().to_worker(cube_state, rc, tx_last, additional_predicates);
+            return steps.pop().unwrap().to_worker(cube_state, rc, tx_last, additional_predicates, ctx);
         }
         let (mut tx, mut rc_next) = bounded_channel(BUFFER_SIZE);
-        let mut workers = vec![];
+        let mut tasks = vec![];
+        // Moves the later steps need at least, so each step knows when it can't beat the best solution anymore
+        let mut remaining: Vec<(usize, usize)> = steps.iter().rev()
+            .scan((ctx.remaining_min_moves, ctx.remaining_cancellation), |remaining, step| {
+                let before = *remaining;
+                remaining.0 += step.min_moves();
+                remaining.1 += step.max_cancellation();
+                Some(before)
+            })
+            .collect();
         steps.reverse();
         for _ in 0..(steps.len() - 1) {
-            workers.push(steps.pop().unwrap().to_worker(cube_state.clone(), rc, tx, vec![]));
+            let (remaining_min_moves, remaining_cancellation) = remaining.pop().unwrap();
+            let step_ctx = WorkerContext { remaining_min_moves, remaining_cancellation, ..ctx.clone() };
+            tasks.append(&mut steps.pop().unwrap().to_worker(cube_state.clone(), rc, tx, vec![], &step_ctx));
             rc = rc_next;
             (tx, rc_next) = bounded_channel(BUFFER_SIZE);
         }
-        workers.push(steps.pop().unwrap().to_worker(cube_state.clone(), rc, tx_last, additional_predicates));
-        Box::new(SequentialWorker {
-            workers,
-        })
+        let (remaining_min_moves, remaining_cancellation) = remaining.pop().unwrap();
+        let step_ctx = WorkerContext { remaining_min_moves, remaining_cancellation, ..ctx.clone() };
+        tasks.append(&mut steps.pop().unwrap().to_worker(cube_state.clone(), rc, tx_last, additional_predicates, &step_ctx));
+        tasks
     }
 
-    fn create_parallel_worker(mut steps: Vec<StepGroup>, cube_state: Cube333, rc: Receiver<Solution>, tx: Sender<Solution>, additional_predicates: Vec<Box<dyn StepPredicate>>) -> Box<dyn Worker<()> + Send>
+    fn create_parallel_worker(mut steps: Vec<StepGroup>, cube_state: Cube333, rc: Receiver<Solution>, tx: Sender<Solution>, additional_predicates: Vec<Box<dyn StepPredicate>>, ctx: &WorkerContext) -> Vec<Task>
     where
         Self: Sized + Send + 'static
     {
         assert!(!steps.is_empty());
         if steps.len() == 1 {
-            return steps.pop().unwrap().to_worker(cube_state, rc, tx, additional_predicates);
+            return steps.pop().unwrap().to_worker(cube_state, rc, tx, additional_predicates, ctx);
         }
-        let mut workers = vec![];
+        let mut tasks = vec![];
         let mut inputs = vec![];
         let mut outputs = vec![];
         for step in steps.into_iter() {
             let (tx0, rc0) = bounded_channel(BUFFER_SIZE);
             let (tx1, rc1) = bounded_channel(BUFFER_SIZE);
-            workers.push(step.to_worker(cube_state.clone(), rc0, tx1, vec![]));
+            tasks.append(&mut step.to_worker(cube_state.clone(), rc0, tx1, vec![], ctx));
             inputs.push(rc1);
             outputs.push(tx0);
         }
-        Box::new(ParallelWorker {
-            broadcaster: ThreadState::PreStart(Box::new(Broadcaster::new(rc, outputs))),
-            workers,
-            sampler: ThreadState::PreStart(Box::new(InOrderSampler::new(tx, inputs, additional_predicates))),
-        })
-    }
-}
-
-struct SequentialWorker {
-    workers: Vec<Box<dyn Worker<()> + Send>>,
-}
-
-impl Worker<()> for SequentialWorker {
-    fn start(&mut self) {
-        self.workers.iter_mut()
-            .for_each(|w| w.start());
-    }
-
-    fn stop(&mut self) -> Option<JoinHandle<()>> {
-        let workers = mem::take(&mut self.workers);
-        Some(thread::spawn(move||{
-            let mut to_join = vec![];
-            for mut w in workers {
-                if let Some(handle) = w.stop() {
-                    to_join.push(handle);
-                }
-            }
-            for handle in to_join {
-                handle.join().unwrap();
-            }
-        }))
+        tasks.push(Broadcaster::new(rc, outputs, ctx.execution).into_task());
+        tasks.push(InOrderSampler::new(tx, inputs, additional_predicates)
+            .with_stats(ctx.stats.clone())
+            .with_execution(ctx.execution)
+            .with_deterministic(ctx.deterministic)
+            .into_task());
+        tasks
     }
 }
 
@@ -190,80 +259,84 @@ struct Broadcaster {
     sinks: Vec<Sender<Solution>>,
     positions: Vec<usize>,
     source: Receiver<Solution>,
-    buffer: Vec<Solution>
+    buffer: Vec<Solution>,
+    execution: ExecutionMode,
 }
 
 impl Broadcaster {
-    pub fn new(source: Receiver<Solution>, sinks: Vec<Sender<Solution>>) -> Self {
+    pub fn new(source: Receiver<Solution>, sinks: Vec<Sender<Solution>>, execution: ExecutionMode) -> Self {
         Self {
             positions: vec![0; sinks.len()],
             sinks,
             source,
             buffer: vec![],
+            execution,
         }
     }
 }
 
 // If only we had a good sync spmc broadcast channel :(
-// I don't want to spend the time to implement myself and it doesn't have to be super fast so we'll just add another thread
-impl Run<()> for Broadcaster {
-    fn run(&mut self) {
-        let mut source_dead = false;
-        while !self.sinks.is_empty() {
-            let mut select = Select::new();
-            let mut lowest = self.buffer.len();
-            let mut active_sinks = vec![];
-            for id in 0..self.sinks.len() {
-                lowest = min(lowest, self.positions[id]);
-                if self.positions[id] < self.buffer.len() {
-                    select.send(&self.sinks[id]);
-                    active_sinks.push(id);
+// I don't want to spend the time to implement myself and it doesn't have to be super fast so we'll just add another task
+impl Broadcaster {
+    pub fn into_task(mut self) -> Task {
+        Box::pin(#[coroutine] static move || {
+            let mut source_dead = false;
+            while !self.sinks.is_empty() {
+                let mut select = self.execution.select();
+                let mut lowest = self.buffer.len();
+                let mut active_sinks = vec![];
+                for id in 0..self.sinks.len() {
+                    lowest = min(lowest, self.positions[id]);
+                    if self.positions[id] < self.buffer.len() {
+                        select.send(&self.sinks[id]);
+                        active_sinks.push(id);
+                    }
                 }
-            }
-            // Has one sink reached the end of the buffer
-            if active_sinks.len() < self.sinks.len() && !source_dead {
-                select.recv(&self.source);
-            }
-            if lowest > 0 {
-                self.buffer.drain(0..lowest);
-                for buffer_positios in self.positions.iter_mut() {
-                    *buffer_positios -= lowest;
+                // Has one sink reached the end of the buffer
+                if active_sinks.len() < self.sinks.len() && !source_dead {
+                    select.recv(&self.source);
                 }
-            }
-            let index = select.ready();
-            if index == active_sinks.len() {
-                match self.source.recv() {
-                    Ok(v) => {
-                        self.buffer.push(v);
-                    }
-                    Err(_) => {
-                        source_dead = true;
+                if lowest > 0 {
+                    self.buffer.drain(0..lowest);
+                    for buffer_positios in self.positions.iter_mut() {
+                        *buffer_positios -= lowest;
                     }
                 }
-            } else {
-                let index = active_sinks[index];
-                let sink = &self.sinks[index];
-                let item = self.buffer[self.positions[index]].clone();
-                let result = sink.send(item);
-                match result {
-                    Ok(_) => {
-                        self.positions[index] += 1;
+                let index = ready_or_yield!(select);
+                drop(select);
+                if index == active_sinks.len() {
+                    match recv_or_yield!(&self.source) {
+                        Ok(v) => {
+                            self.buffer.push(v);
+                        }
+                        Err(_) => {
+                            source_dead = true;
+                        }
                     }
-                    Err(_) => {
-                        self.sinks.remove(index);
-                        self.positions.remove(index);
+                } else {
+                    let index = active_sinks[index];
+                    let item = self.buffer[self.positions[index]].clone();
+                    let result = send_or_yield!(&self.sinks[index], item);
+                    match result {
+                        Ok(_) => {
+                            self.positions[index] += 1;
+                        }
+                        Err(_) => {
+                            self.sinks.remove(index);
+                            self.positions.remove(index);
+                        }
                     }
                 }
-            }
-            if source_dead {
-                for idx in (0..self.sinks.len()).rev() {
-                    if self.positions[idx] >= self.buffer.len() {
-                        self.positions.remove(idx);
-                        self.sinks.remove(idx);
+                if source_dead {
+                    for idx in (0..self.sinks.len()).rev() {
+                        if self.positions[idx] >= self.buffer.len() {
+                            self.positions.remove(idx);
+                            self.sinks.remove(idx);
+                        }
                     }
                 }
             }
-        }
+        })
     }
 }
 
@@ -279,33 +352,33 @@ impl FifoSampler {
             sink,
         }
     }
-}
 
-impl Run<()> for FifoSampler {
-    fn run(&mut self) -> () {
-        let mut sel = Select::new();
+    pub fn into_task(self) -> Task {
+        Box::pin(#[coroutine] static move || {
+            let mut sel = Select::new();
 
-        for source in &self.sources {
-            sel.recv(source);
-        }
+            for source in &self.sources {
+                sel.recv(source);
+            }
 
-        let mut connected = self.sources.len();
-        while connected > 0 {
-            let index = sel.ready();
+            let mut connected = self.sources.len();
+            while connected > 0 {
+                let index = ready_or_yield!(sel);
 
-            match self.sources[index].try_recv() {
-                Ok(res) => {
-                    if let Err(_) = self.sink.send(res) {
-                        return;
+                match executor::try_recv(&self.sources[index]) {
+                    Ok(res) => {
+                        if send_or_yield!(&self.sink, res).is_err() {
+                            return;
+                        }
                     }
+                    Err(TryRecvError::Disconnected) => {
+                        sel.remove(index);
+                        connected -= 1;
+                    }
+                    _ => {}
                 }
-                Err(TryRecvError::Disconnected) => {
-                    sel.remove(index);
-                    connected -= 1;
-                }
-                _ => {}
             }
-        }
+        })
     }
 }
 
@@ -313,6 +386,9 @@ pub struct InOrderSampler {
     sources: Vec<Receiver<Solution>>,
     sink: Sender<Solution>,
     predicates: Vec<Box<dyn StepPredicate>>,
+    stats: Option<Arc<SolverStats>>,
+    execution: ExecutionMode,
+    deterministic: bool,
 }
 
 impl InOrderSampler {
@@ -321,117 +397,141 @@ impl InOrderSampler {
             sources,
             sink,
             predicates,
+            stats: None,
+            execution: ExecutionMode::default(),
+            deterministic: false,
         }
     }
+
+    pub fn with_stats(mut self, stats: Option<Arc<SolverStats>>) -> Self {
+        self.stats = stats;
+        self
+    }
+
+    pub fn with_execution(mut self, execution: ExecutionMode) -> Self {
+        self.execution = execution;
+        self
+    }
+
+    pub fn with_deterministic(mut self, deterministic: bool) -> Self {
+        self.deterministic = deterministic;
+        self
+    }
 }
 
 impl InOrderSampler {
-    fn send(&self, item: Solution) -> Result<(), SendError<Solution>> {
-        for p in self.predicates.iter() {
-            match p.check_solution(&item) {
-                StepPredicateResult::Accepted => {}
-                StepPredicateResult::Rejected => {
-                    return Ok(());
-                }
-                StepPredicateResult::Closed => {
-                    return Err(SendError(item));
-                }
-            }
+    // Returns the solution if it passed all predicates and should be sent
+    fn filter(&self, item: Solution) -> Result<Option<Solution>, SendError<Solution>> {
+        match check_predicates(&self.predicates, &item, self.stats.as_deref()) {
+            StepPredicateResult::Accepted => Ok(Some(item)),
+            StepPredicateResult::Rejected => Ok(None),
+            StepPredicateResult::Closed => Err(SendError(item)),
         }
-        self.sink.send(item)
     }
-}
 
-impl Run<()> for InOrderSampler {
-    fn run(&mut self) -> () {
-        let mut cache: Vec<Option<Solution>> = vec![None; self.sources.len()];
-        let mut sel = Select::new();
-        let mut target_length = 0;
-        let mut active = self.sources.len();
-        for s in &self.sources {
-            sel.recv(s);
+    pub fn into_task(self) -> Task {
+        if self.deterministic {
+            return self.into_deterministic_task();
         }
-        let mut dead = HashSet::new();
-        while dead.len() < self.sources.len() {
-            if active == 0 {
-                target_length = cache.iter().filter_map(|x| x.as_ref().map(|x| x.len())).min().unwrap_or(target_length + 1);
-                for idx in 0..cache.len() {
-                    if cache[idx].as_ref().filter(|x|x.len() <= target_length).is_some() {
-                        if let Err(_) = self.send(cache[idx].take().unwrap()) {
-                            return;
+        Box::pin(#[coroutine] static move || {
+            // Filters and sends a solution, ends the task if the sink is closed
+            macro_rules! send {
+                ($item:expr) => {
+                    match self.filter($item) {
+                        Ok(Some(item)) => {
+                            if send_or_yield!(&self.sink, item).is_err() {
+                                return;
+                            }
                         }
+                        Ok(None) => {}
+                        Err(_) => return,
                     }
-                }
-                active = self.sources.len();
-                sel = Select::new();
-                for s in &self.sources {
-                    sel.recv(s);
-                }
+                };
             }
-            let index = sel.ready();
-            if cache[index].is_some() {
-                sel.remove(index);
-                active -= 1;
-                continue;
+
+            let mut cache: Vec<Option<Solution>> = vec![None; self.sources.len()];
+            let mut sel = self.execution.select();
+            let mut target_length = 0;
+            let mut active = self.sources.len();
+            for s in &self.sources {
+                sel.recv(s);
             }
-            match self.sources[index].try_recv() {
-                Ok(res) => {
-                    if res.len() > target_length {
-                        sel.remove(index);
-                        cache[index] = Some(res);
-                        active -= 1;
-                        continue
+            let mut dead = HashSet::new();
+            while dead.len() < self.sources.len() {
+                if active == 0 {
+                    target_length = cache.iter().filter_map(|x| x.as_ref().map(|x| x.len())).min().unwrap_or(target_length + 1);
+                    for idx in 0..cache.len() {
+                        if cache[idx].as_ref().filter(|x|x.len() <= target_length).is_some() {
+                            send!(cache[idx].take().unwrap());
+                        }
                     }
-                    if let Err(_) = self.send(res) {
-                        return;
+                    active = self.sources.len();
+                    sel = self.execution.select();
+                    for s in &self.sources {
+                        sel.recv(s);
                     }
                 }
-                Err(TryRecvError::Disconnected) => {
+                let index = ready_or_yield!(sel);
+                if cache[index].is_some() {
                     sel.remove(index);
-                    dead.insert(index);
                     active -= 1;
+                    continue;
+                }
+                match executor::try_recv(&self.sources[index]) {
+                    Ok(res) => {
+                        if res.len() > target_length {
+                            sel.remove(index);
+                            cache[index] = Some(res);
+                            active -= 1;
+                            continue
+                        }
+                        send!(res);
+                    }
+                    Err(TryRecvError::Disconnected) => {
+                        sel.remove(index);
+                        dead.insert(index);
+                        active -= 1;
+                    }
+                    _ => {}
                 }
-                _ => {}
             }
-        }
-    }
-}
-
-struct ParallelWorker {
-    workers: Vec<Box<dyn Worker<()> + Send>>,
-    broadcaster: ThreadState<()>,
-    sampler: ThreadState<()>,
-}
-
-impl Worker<()> for ParallelWorker {
-    fn start(&mut self) {
-        self.broadcaster.start();
-        self.workers.iter_mut()
-            .for_each(|w| w.start());
-        self.sampler.start();
+        })
     }
 
-    fn stop(&mut self) -> Option<JoinHandle<()>> {
-        let workers = mem::take(&mut self.workers);
-        let mut broadcaster = mem::take(&mut self.broadcaster);
-        let mut sampler = mem::take(&mut self.sampler);
-
-        Some(thread::spawn(move||{
-            let mut to_join = vec![];
-            if let Some(handle) = broadcaster.stop() {
-                to_join.push(handle);
-            }
-            if let Some(handle) = sampler.stop() {
-                to_join.push(handle);
-            }
-            for mut w in workers {
-                if let Some(handle) = w.stop() {
-                    to_join.push(handle);
+    // Every source sends its solutions ordered by length, so we only have to merge them. We wait for the next solution of every source
+    // before sending anything, which is slower, but the order no longer depends on which source happens to be faster.
+    fn into_deterministic_task(self) -> Task {
+        Box::pin(#[coroutine] static move || {
+            let mut heads: Vec<Option<Solution>> = vec![None; self.sources.len()];
+            let mut dead = vec![false; self.sources.len()];
+            loop {
+                for idx in 0..self.sources.len() {
+                    if heads[idx].is_none() && !dead[idx] {
+                        match recv_or_yield!(&self.sources[idx]) {
+                            Ok(res) => heads[idx] = Some(res),
+                            Err(_) => dead[idx] = true,
+                        }
+                    }
+                }
+                // min_by_key returns the first of several equal elements, so ties go to the earlier source
+                let next = heads.iter()
+                    .enumerate()
+                    .filter_map(|(idx, head)| head.as_ref().map(|x| (idx, x.len())))
+                    .min_by_key(|(_, len)| *len)
+                    .map(|(idx, _)| idx);
+                let Some(idx) = next else {
+                    return;
+                };
+                match self.filter(heads[idx].take().unwrap()) {
+                    Ok(Some(item)) => {
+                        if send_or_yield!(&self.sink, item).is_err() {
+                            return;
+                        }
+                    }
+                    Ok(None) => {}
+                    Err(_) => return,
                 }
             }
-            for handle in to_join {
-                handle.join().unwrap();
-            }
-        }))
+        })
     }
 }
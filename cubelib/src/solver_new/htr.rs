@@ -6,7 +6,9 @@ use log::debug;
 use crate::cube::*;
 use crate::defs::StepVariant;
 use crate::solver::lookup_table;
-use crate::solver::lookup_table::{DepthEstimate, NissDepthEstimate, SaveToDisk, InMemoryIndexTable, InMemoryNissIndexTable};
+#[cfg(feature = "fs")]
+use crate::solver::lookup_table::SaveToDisk;
+use crate::solver::lookup_table::{DepthEstimate, NissDepthEstimate, InMemoryIndexTable, InMemoryNissIndexTable};
 use crate::solver_new::*;
 use crate::solver_new::group::StepGroup;
 use crate::solver_new::step::*;
@@ -72,8 +74,14 @@ fn gen_htr_with_subsets() -> (HTRPruningTable, HTRSubsetTable) {
                                                &|table, coord|table.get(coord),
                                                &|table, coord, val|table.set(coord, val)));
     let (htr_subset_table, generated) = InMemoryIndexTable::load_and_save("htr-subset", ||crate::steps::htr::subsets::gen_subset_tables(&mut htr_table));
+    // Generating the subsets also fills in the NISS bits of the HTR table
     if generated {
-        _ = htr_table.save_to_disk("333", "htr");
+        #[cfg(feature = "fs")]
+        {
+            _ = htr_table.save_to_disk("333", "htr");
+        }
+        #[cfg(not(feature = "fs"))]
+        lookup_table::store_generated("htr", htr_table.to_bytes());
     }
     (Box::new(htr_table), htr_subset_table)
 }
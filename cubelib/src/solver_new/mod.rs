@@ -17,15 +17,23 @@ use crate::solver_new::finish::{DRFinishBuilder, FRFinishBuilder, HTRFinishBuild
 #[cfg(feature = "multi-path-channel-solver")]
 use crate::solver_new::fr::FRBuilder;
 #[cfg(feature = "multi-path-channel-solver")]
-use crate::solver_new::group::StepGroup;
+use crate::solver_new::group::{StepGroup, StepPredicate};
 #[cfg(feature = "multi-path-channel-solver")]
 use crate::solver_new::step::{DFSParameters, MoveSet};
 #[cfg(feature = "multi-path-channel-solver")]
-use crate::solver_new::thread_util::{ToWorker, Worker};
+use crate::solver_new::thread_util::ToWorker;
 #[cfg(feature = "multi-path-channel-solver")]
 use crate::steps::step::{PostStepCheck, PreStepCheck, StepConfig};
 #[cfg(feature = "multi-path-channel-solver")]
-use crate::solver_new::util_steps::FilterExcluded;
+use crate::solver_new::util_steps::{FilterExcluded, FilterFirstN, FilterFirstNStepVariant};
+#[cfg(feature = "multi-path-channel-solver")]
+use crate::solver_new::ar::ARBuilder;
+#[cfg(feature = "multi-path-channel-solver")]
+use crate::solver_new::trigger::{DEFAULT_TRIGGER_LENGTH, MAX_AUTO_TRIGGER_LENGTH, TriggerLibrary};
+#[cfg(feature = "multi-path-channel-solver")]
+use crate::solver_new::vr::VRStep;
+#[cfg(feature = "multi-path-channel-solver")]
+use crate::solver_new::generator::GeneratorStep;
 
 #[cfg(feature = "multi-path-channel-solver")]
 pub mod step;
@@ -38,6 +46,10 @@ pub mod group;
 #[cfg(feature = "multi-path-channel-solver")]
 pub mod thread_util;
 #[cfg(feature = "multi-path-channel-solver")]
+pub mod executor;
+#[cfg(feature = "multi-path-channel-solver")]
+pub mod backend;
+#[cfg(feature = "multi-path-channel-solver")]
 pub mod util_steps;
 #[cfg(feature = "multi-path-channel-solver")]
 pub mod htr;
@@ -49,6 +61,16 @@ pub mod fr;
 pub mod finish;
 #[cfg(feature = "multi-path-channel-solver")]
 pub mod ar;
+#[cfg(feature = "multi-path-channel-solver")]
+pub mod stats;
+#[cfg(feature = "multi-path-channel-solver")]
+pub mod subgroup;
+#[cfg(feature = "multi-path-channel-solver")]
+pub mod generator;
+#[cfg(feature = "multi-path-channel-solver")]
+pub mod trigger;
+#[cfg(feature = "futures")]
+pub mod stream;
 pub mod vr;
 
 #[cfg(feature = "multi-path-channel-solver")]
@@ -75,84 +97,207 @@ pub trait Step: PreStepCheck + PostStepCheck {
     fn heuristic(&self, state: &Cube333, can_niss_switch: bool, depth_left: usize) -> usize;
     fn pre_step_trans(&self) -> &'_ Vec<Transformation333>;
     fn get_variant(&self) -> StepVariant;
+
+    /// Returns why the step can never start from this cube, if it can't. Most steps can start anywhere,
+    /// so this is only worth implementing when the reason is cheap to find and worth telling the user about.
+    fn check_cube(&self, _cube: &Cube333) -> Result<(), String> {
+        Ok(())
+    }
 }
 
 #[cfg(feature = "multi-path-channel-solver")]
-pub fn build_steps(mut steps: Vec<StepConfig>) -> Result<StepGroup, String> {
+pub fn build_steps(steps: Vec<StepConfig>) -> Result<StepGroup, String> {
     let mut step_groups = vec![];
-    let mut previous = None;
-    steps.reverse();
-    while !steps.is_empty() {
-        let mut step = steps.pop().unwrap();
-        let mut next_prev = Some(step.kind.clone());
-        let excluded = step.excluded.clone();
-        let mut step_group = match (previous, step.kind.clone()) {
-            (None, StepKind::EO) => EOBuilder::try_from(step).map_err(|_|"Failed to parse EO step")?.build(),
-            (Some(StepKind::EO), StepKind::RZP) => {
-                let mut dr = steps.pop().ok_or("Expected DR to follow RZP".to_string())?;
-                next_prev = Some(StepKind::DR);
-                let rzp_builder = RZPBuilder::try_from(step).map_err(|_|"Failed to parse RZP step")?;
-                let triggers = dr.params.remove("triggers").ok_or("Found RZP, but DR step has no triggers".to_string())?;
-                let dr_excluded = dr.excluded.clone();
-                let mut dr_step = DRBuilder::try_from(dr).map_err(|_|"Failed to parse DR step")?
-                    .triggers(triggers.split(",")
-                        .map(Algorithm::from_str)
-                        .collect::<Result<_, _>>()
-                        .map_err(|_|"Unable to parse algorithm")?)
-                    .rzp(rzp_builder)
-                    .build();
-                if !dr_excluded.is_empty() {
-                    dr_step.with_predicates(vec![FilterExcluded::new(dr_excluded)]);
-                }
-                dr_step
-            },
-            (Some(StepKind::EO), StepKind::DR) => {
-                match step.params.remove("triggers") {
-                    None => DRBuilder::try_from(step).map_err(|_|"Failed to parse DR step")?.build(),
-                    Some(triggers) => {
-                        let rzp = RZPStep::builder()
-                            .max_length(step.max.unwrap_or(3).min(3) as usize)
-                            .max_absolute_length(step.absolute_max.unwrap_or(6).min(6) as usize);
-                        DRBuilder::try_from(step).map_err(|_|"Failed to parse DR step")?
-                            .triggers(triggers.split(",")
-                                .map(Algorithm::from_str)
-                                .collect::<Result<_, _>>()
-                                .map_err(|_|"Unable to parse algorithm")?)
-                            .rzp(rzp)
-                            .build()
+    let mut previous: Option<StepConfig> = None;
+    for step in steps {
+        let (step_group, config) = build_step(previous.as_ref(), step)?;
+        step_groups.extend(step_group);
+        previous = Some(config);
+    }
+    if previous.is_some_and(|x| x.kind == StepKind::RZP) {
+        return Err("Expected DR to follow RZP".to_string());
+    }
+    Ok(StepGroup::sequential(step_groups))
+}
+
+/// Steps with parallel alternatives, e.g. `EO > (DR[triggers=R] | AR > DR) > HTR`.
+/// This is the serializable form of the nested [`StepGroup`]s that [`build_step_tree`] creates, for callers that can't
+/// send step groups across, like the web worker.
+#[cfg(feature = "multi-path-channel-solver")]
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde_support", derive(serde::Serialize, serde::Deserialize))]
+pub enum StepConfigTree {
+    Step(StepConfig),
+    Sequence(Vec<StepConfigTree>),
+    Parallel(Vec<StepConfigTree>),
+}
+
+#[cfg(feature = "multi-path-channel-solver")]
+impl From<Vec<StepConfig>> for StepConfigTree {
+    fn from(value: Vec<StepConfig>) -> Self {
+        StepConfigTree::Sequence(value.into_iter().map(StepConfigTree::Step).collect())
+    }
+}
+
+#[cfg(feature = "multi-path-channel-solver")]
+pub fn build_step_tree(tree: StepConfigTree) -> Result<StepGroup, String> {
+    let (group, last) = build_step_tree_from(None, tree)?;
+    if last.as_ref().is_some_and(|x| x.kind == StepKind::RZP) {
+        return Err("Expected DR to follow RZP".to_string());
+    }
+    group.ok_or("No steps".to_string())
+}
+
+#[cfg(feature = "multi-path-channel-solver")]
+fn build_step_tree_from(mut previous: Option<StepConfig>, tree: StepConfigTree) -> Result<(Option<StepGroup>, Option<StepConfig>), String> {
+    Ok(match tree {
+        StepConfigTree::Step(step) => {
+            let (group, config) = build_step(previous.as_ref(), step)?;
+            (group, Some(config))
+        },
+        StepConfigTree::Sequence(steps) => {
+            let mut groups = vec![];
+            for step in steps {
+                let (group, config) = build_step_tree_from(previous, step)?;
+                groups.extend(group);
+                previous = config;
+            }
+            ((!groups.is_empty()).then(|| StepGroup::sequential(groups)), previous)
+        },
+        StepConfigTree::Parallel(branches) => {
+            let mut groups = vec![];
+            let mut target: Option<StepConfig> = None;
+            for branch in branches {
+                let (group, config) = build_step_tree_from(previous.clone(), branch)?;
+                let kind = config.as_ref().map(|x| x.kind.clone());
+                if let Some(target) = target.as_ref() {
+                    if Some(&target.kind) != kind.as_ref() {
+                        return Err(format!("Parallel steps must end with the same step, got {} and {}", target.kind, kind.map_or("nothing".to_string(), |k| k.to_string())));
                     }
                 }
-            },
-            (Some(StepKind::DR), StepKind::HTR) => HTRBuilder::try_from(step).map_err(|_|"Failed to parse HTR step")?.build(),
-            (Some(StepKind::HTR), StepKind::FR) | (Some(StepKind::HTR), StepKind::FRLS)  => FRBuilder::try_from(step).map_err(|_|"Failed to parse FR step")?.build(),
-            (Some(StepKind::FR), StepKind::FIN) => FRFinishBuilder::try_from(step).map_err(|_|"Failed to parse FIN step")?.build(),
-            (Some(StepKind::FRLS), StepKind::FINLS) => FRFinishBuilder::try_from(step).map_err(|_|"Failed to parse FIN step")?.build(),
-            (Some(StepKind::HTR), StepKind::FIN) | (Some(StepKind::HTR), StepKind::FINLS) => {
-                let htr_breaking = step.params.remove("htr-breaking").map(|x|bool::from_str(x.to_lowercase().as_str()).unwrap_or(false)).unwrap_or(false);
-                if htr_breaking {
-                    DRFinishBuilder::try_from(step)
-                        .map_err(|_|"Failed to parse FIN step")?
-                        .from_htr()
+                target = config;
+                groups.extend(group);
+            }
+            ((!groups.is_empty()).then(|| StepGroup::parallel(groups)), target)
+        },
+    })
+}
+
+/// Builds the step group for a single step, given the config of the step before it.
+/// RZP is solved together with the DR that follows it, so there is no group for it. The returned config has to be
+/// passed as `previous` when building the next step.
+#[cfg(feature = "multi-path-channel-solver")]
+pub fn build_step(previous: Option<&StepConfig>, mut step: StepConfig) -> Result<(Option<StepGroup>, StepConfig), String> {
+    let config = step.clone();
+    let limit = step.params.remove("step-limit");
+    let max_use = step.params.remove("max-use");
+    let excluded = step.excluded.clone();
+    let kind = step.kind.clone();
+
+    let step_group = match (previous.map(|x| x.kind.clone()), kind.clone()) {
+        (None, StepKind::EO) => Some(EOBuilder::try_from(step).map_err(|_|"Failed to parse EO step")?.build()),
+        (Some(StepKind::EO), StepKind::RZP) => None,
+        (Some(StepKind::RZP), StepKind::DR) => {
+            let triggers = step.params.remove("triggers").ok_or("Found RZP, but DR step has no triggers".to_string())?;
+            let rzp_builder = RZPBuilder::try_from(previous.unwrap().clone()).map_err(|_|"Failed to parse RZP step")?;
+            Some(DRBuilder::try_from(step).map_err(|_|"Failed to parse DR step")?
+                .triggers(parse_triggers(&triggers)?)
+                .rzp(rzp_builder)
+                .build())
+        },
+        (Some(StepKind::EO), StepKind::AR) => Some(ARBuilder::try_from(step).map_err(|_|"Failed to parse AR step")?.build()),
+        (Some(StepKind::AR), StepKind::DR) => {
+            Some(DRBuilder::try_from(step).map_err(|_|"Failed to parse DR step")?
+                .from_ar()
+                .build())
+        },
+        (Some(StepKind::EO), StepKind::DR) => {
+            Some(match step.params.remove("triggers") {
+                None => DRBuilder::try_from(step).map_err(|_|"Failed to parse DR step")?.build(),
+                Some(triggers) => {
+                    let rzp = RZPStep::builder()
+                        .max_length(step.max.unwrap_or(3).min(3) as usize)
+                        .max_absolute_length(step.absolute_max.unwrap_or(6).min(6) as usize);
+                    DRBuilder::try_from(step).map_err(|_|"Failed to parse DR step")?
+                        .triggers(parse_triggers(&triggers)?)
+                        .rzp(rzp)
                         .build()
-                } else {
-                    HTRFinishBuilder::try_from(step).map_err(|_|"Failed to parse FIN step")?.build()
                 }
-            },
-            (Some(StepKind::DR), StepKind::FIN) | (Some(StepKind::DR), StepKind::FINLS) => {
-                step.params.remove("htr-breaking");
-                DRFinishBuilder::try_from(step).map_err(|_|"Failed to parse FIN step")?.build()
-            },
-            // (Some(StepKind::FINLS), StepKind::FIN) => {
-            //     crate::solver_new::vr::VRStep::new(2, true)
-            // },
-            (None, x) => return Err(format!("{x:?} is not supported as a first step", )),
-            (Some(a), b) => return Err(format!("Step order {a:?} > {b:?} is not supported")),
+            })
+        },
+        (Some(StepKind::DR), StepKind::HTR) => Some(HTRBuilder::try_from(step).map_err(|_|"Failed to parse HTR step")?.build()),
+        (Some(StepKind::HTR), StepKind::FR) | (Some(StepKind::HTR), StepKind::FRLS) => Some(FRBuilder::try_from(step).map_err(|_|"Failed to parse FR step")?.build()),
+        (Some(StepKind::DR), StepKind::FIN) | (Some(StepKind::DR), StepKind::FINLS) => {
+            step.params.remove("htr-breaking");
+            Some(DRFinishBuilder::try_from(step).map_err(|_|"Failed to parse FIN step")?.build())
+        },
+        (Some(StepKind::FR), StepKind::FIN) => Some(FRFinishBuilder::try_from(step).map_err(|_|"Failed to parse FIN step")?.build()),
+        (Some(StepKind::FRLS), StepKind::FINLS) => Some(FRFinishBuilder::try_from(step).map_err(|_|"Failed to parse FIN step")?.build()),
+        (Some(StepKind::HTR), StepKind::FIN) | (Some(StepKind::HTR), StepKind::FINLS) => {
+            let htr_breaking = step.params.remove("htr-breaking").map(|x|bool::from_str(x.to_lowercase().as_str()).unwrap_or(false)).unwrap_or(false);
+            if htr_breaking {
+                Some(DRFinishBuilder::try_from(step).map_err(|_|"Failed to parse FIN step")?
+                    .from_htr()
+                    .build())
+            } else {
+                Some(HTRFinishBuilder::try_from(step).map_err(|_|"Failed to parse FIN step")?.build())
+            }
+        },
+        (Some(StepKind::FINLS), StepKind::VR) => {
+            Some(VRStep::new(step.params.get("vr-in").and_then(|x|usize::from_str(x).ok()).unwrap_or(2), false))
+        },
+        // A bit cursed, as this just replaces the previously found VR by looking for another one
+        (Some(StepKind::VR), StepKind::FIN) => Some(VRStep::new(previous.unwrap().params.get("vr-in").and_then(|x|usize::from_str(x).ok()).unwrap_or(2), true)),
+        (Some(StepKind::FINLS), StepKind::FIN) => Some(VRStep::new(2, true)),
+        (_, StepKind::GEN) => Some(GeneratorStep::from_config(step)?),
+        (None, x) => return Err(format!("{x:?} is not supported as a first step", )),
+        (Some(a), b) => return Err(format!("Step order {a:?} > {b:?} is not supported")),
+    };
+
+    let step_group = match step_group {
+        Some(mut step_group) => {
+            if let Some(max_use) = max_use {
+                let filters: Result<Vec<Box<dyn StepPredicate>>, String> = max_use.split(",")
+                    .flat_map(|x|{
+                        x.split_once(":")
+                            .map(|(a, b)|{
+                                let kind = StepKind::from_str(a).unwrap();
+                                let n = usize::from_str(b).map_err(|_|"Failed to parse max use limit".to_string());
+                                n.map(|n|FilterFirstNStepVariant::new(kind, n))
+                            })
+                    })
+                    .collect();
+                step_group.with_predicates(filters?);
+            }
+            if let Some(limit) = limit {
+                step_group.with_predicates(vec![FilterFirstN::new(usize::from_str(limit.as_str()).map_err(|_|"Failed to parse step limit")?)]);
+            }
+            if !excluded.is_empty() {
+                step_group.with_predicates(vec![FilterExcluded::new(excluded)]);
+            }
+            Some(step_group)
+        }
+        None => None,
+    };
+    Ok((step_group, config))
+}
+
+// `auto` or `auto:<length>` uses all triggers up to that length, see [`TriggerLibrary`]
+#[cfg(feature = "multi-path-channel-solver")]
+fn parse_triggers(triggers: &str) -> Result<Vec<Algorithm>, String> {
+    if let Some(length) = triggers.strip_prefix("auto") {
+        let length = match length.strip_prefix(":") {
+            Some(length) => usize::from_str(length).map_err(|_| format!("Invalid trigger length '{length}'"))?,
+            None if length.is_empty() => DEFAULT_TRIGGER_LENGTH,
+            None => return Err(format!("Unable to parse triggers '{triggers}'")),
         };
-        if !excluded.is_empty() {
-            step_group.with_predicates(vec![FilterExcluded::new(excluded)]);
+        if length > MAX_AUTO_TRIGGER_LENGTH {
+            return Err(format!("Trigger length {length} is too long. At most {MAX_AUTO_TRIGGER_LENGTH} is supported"));
         }
-        step_groups.push(step_group);
-        previous = next_prev;
+        return Ok(TriggerLibrary::cached(length).triggers());
     }
-    Ok(StepGroup::sequential(step_groups))
-}
\ No newline at end of file
+    triggers.split(",")
+        .map(Algorithm::from_str)
+        .collect::<Result<_, _>>()
+        .map_err(|_|"Unable to parse algorithm".to_string())
+}
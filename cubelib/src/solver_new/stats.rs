@@ -0,0 +1,193 @@
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+use crate::defs::StepVariant;
+use crate::solver_new::{Receiver, Sender};
+use crate::solver_new::group::StepPredicateResult;
+
+#[derive(Clone, Debug)]
+pub enum ProgressEvent {
+    /// The step started looking for solutions of this total length
+    Depth { variant: StepVariant, length: usize },
+    /// The step produced a solution that was accepted by all of its predicates
+    Solution { variant: StepVariant, length: usize },
+}
+
+#[derive(Clone, Debug)]
+pub struct StepStats {
+    pub variant: StepVariant,
+    pub length: usize,
+    pub nodes: u64,
+    pub pruned: u64,
+    pub solutions: u64,
+}
+
+#[derive(Clone, Debug)]
+pub struct PredicateStats {
+    pub name: &'static str,
+    pub accepted: u64,
+    pub rejected: u64,
+    pub closed: u64,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SolverStatsSnapshot {
+    pub steps: Vec<StepStats>,
+    pub predicates: Vec<PredicateStats>,
+}
+
+impl SolverStatsSnapshot {
+    pub fn total_nodes(&self) -> u64 {
+        self.steps.iter().map(|s| s.nodes).sum()
+    }
+}
+
+#[derive(Default)]
+struct StepCounters {
+    length: AtomicUsize,
+    nodes: AtomicU64,
+    pruned: AtomicU64,
+    solutions: AtomicU64,
+}
+
+/// Collects statistics from all workers of a solver. Counters can be read at any time with [`SolverStats::snapshot`],
+/// while [`SolverStats::subscribe`] delivers progress events as they happen.
+#[derive(Default)]
+pub struct SolverStats {
+    steps: Mutex<Vec<(StepVariant, Arc<StepCounters>)>>,
+    predicates: Mutex<HashMap<&'static str, PredicateStats>>,
+    subscribers: Mutex<Vec<Sender<ProgressEvent>>>,
+}
+
+impl SolverStats {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    /// The returned channel is unbounded, so subscribers are expected to keep draining it while the solver runs.
+    pub fn subscribe(&self) -> Receiver<ProgressEvent> {
+        let (tx, rc) = crossbeam::channel::unbounded();
+        self.subscribers.lock().unwrap().push(tx);
+        rc
+    }
+
+    pub fn snapshot(&self) -> SolverStatsSnapshot {
+        let steps = self.steps.lock().unwrap().iter()
+            .map(|(variant, counters)| StepStats {
+                variant: *variant,
+                length: counters.length.load(Ordering::Relaxed),
+                nodes: counters.nodes.load(Ordering::Relaxed),
+                pruned: counters.pruned.load(Ordering::Relaxed),
+                solutions: counters.solutions.load(Ordering::Relaxed),
+            })
+            .collect();
+        let mut predicates: Vec<PredicateStats> = self.predicates.lock().unwrap().values().cloned().collect();
+        predicates.sort_by_key(|p| p.name);
+        SolverStatsSnapshot { steps, predicates }
+    }
+
+    pub(crate) fn register_step(self: &Arc<Self>, variant: StepVariant) -> StepStatsRecorder {
+        let mut steps = self.steps.lock().unwrap();
+        // Steps with the same variant can show up multiple times in a solver, we just merge their numbers
+        let counters = match steps.iter().find(|(v, _)| *v == variant) {
+            Some((_, counters)) => counters.clone(),
+            None => {
+                let counters = Arc::new(StepCounters::default());
+                steps.push((variant, counters.clone()));
+                counters
+            }
+        };
+        StepStatsRecorder {
+            solver: self.clone(),
+            counters,
+            variant,
+            nodes: Cell::new(0),
+            pruned: Cell::new(0),
+        }
+    }
+
+    pub(crate) fn record_predicate(&self, name: &'static str, result: &StepPredicateResult) {
+        let mut predicates = self.predicates.lock().unwrap();
+        let entry = predicates.entry(name).or_insert(PredicateStats { name, accepted: 0, rejected: 0, closed: 0 });
+        match result {
+            StepPredicateResult::Accepted => entry.accepted += 1,
+            StepPredicateResult::Rejected => entry.rejected += 1,
+            StepPredicateResult::Closed => entry.closed += 1,
+        }
+    }
+
+    fn emit(&self, event: ProgressEvent) {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        if !subscribers.is_empty() {
+            subscribers.retain(|s| s.send(event.clone()).is_ok());
+        }
+    }
+}
+
+// Node counts are kept locally and only published in batches, so the DFS doesn't touch shared memory for every node
+pub(crate) struct StepStatsRecorder {
+    solver: Arc<SolverStats>,
+    counters: Arc<StepCounters>,
+    variant: StepVariant,
+    nodes: Cell<u64>,
+    pruned: Cell<u64>,
+}
+
+impl StepStatsRecorder {
+    pub fn solver(&self) -> &SolverStats {
+        &self.solver
+    }
+
+    pub fn set_length(&self, length: usize) {
+        self.counters.length.store(length, Ordering::Relaxed);
+        self.solver.emit(ProgressEvent::Depth { variant: self.variant, length });
+    }
+
+    #[inline]
+    pub fn node(&self, pruned: bool) {
+        self.nodes.set(self.nodes.get() + 1);
+        if pruned {
+            self.pruned.set(self.pruned.get() + 1);
+        }
+    }
+
+    pub fn flush(&self) {
+        self.counters.nodes.fetch_add(self.nodes.replace(0), Ordering::Relaxed);
+        self.counters.pruned.fetch_add(self.pruned.replace(0), Ordering::Relaxed);
+    }
+
+    pub fn solution(&self, length: usize) {
+        self.counters.solutions.fetch_add(1, Ordering::Relaxed);
+        self.solver.emit(ProgressEvent::Solution { variant: self.variant, length });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+    use crate::algs::Algorithm;
+    use crate::solver_new::eo::EOStep;
+    use crate::solver_new::stats::{ProgressEvent, SolverStats};
+
+    #[test]
+    fn test_eo_stats() {
+        let cube = Algorithm::from_str("R' U' F L' B L' B2 U B2 D U2 L2 F2 L2 R2 D' L2 B' F' R' F L' F' U' R' U' F").unwrap().into();
+        let stats = SolverStats::new();
+        let events = stats.subscribe();
+        let mut worker = EOStep::builder()
+            .max_length(5)
+            .build()
+            .into_worker_with_stats(cube, stats.clone());
+        assert!(worker.next().is_some());
+        drop(worker);
+
+        let snapshot = stats.snapshot();
+        assert!(!snapshot.steps.is_empty());
+        assert!(snapshot.total_nodes() > 0);
+        assert!(snapshot.steps.iter().map(|s| s.solutions).sum::<u64>() > 0);
+        assert!(snapshot.predicates.iter().any(|p| p.name == "FilterDup" && p.accepted > 0));
+        assert!(events.try_iter().any(|e| matches!(e, ProgressEvent::Solution { .. })));
+    }
+}
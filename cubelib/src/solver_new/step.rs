@@ -1,10 +1,12 @@
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::cmp::min;
+use std::collections::HashSet;
 use std::fmt::Debug;
+use std::iter;
 use std::marker::PhantomData;
-use std::sync::Arc;
+use std::ops::Coroutine;
+use std::sync::{Arc, LazyLock, Mutex};
 
-use std::thread::JoinHandle;
 use log::trace;
 use sorted_insert::{SortedInsertBinaryBy};
 use crate::algs::Algorithm;
@@ -16,7 +18,9 @@ use crate::solver::lookup_table::{DepthEstimate, NissDepthEstimate};
 use crate::solver::solution::{Solution, SolutionStep};
 use crate::solver_new::*;
 use crate::solver_new::finish::DRFinishStep;
-use crate::solver_new::group::{StepPredicate, StepPredicateResult};
+use crate::solver_new::executor::{recv_or_yield, send_or_yield, yield_from, SelfContained, Task};
+use crate::solver_new::group::{check_predicates, StepPredicate, StepPredicateResult};
+use crate::solver_new::stats::StepStatsRecorder;
 use crate::solver_new::thread_util::*;
 use crate::steps::coord::Coord;
 use crate::steps::step::{PostStepCheck, PreStepCheck};
@@ -138,23 +142,6 @@ impl <'a, 'b, const C_SIZE: usize, C: Coord<C_SIZE>, const PC_SIZE: usize, PC: C
     }
 }
 
-pub struct StepWorker {
-    pub(crate) join_handle: Option<JoinHandle<()>>,
-    pub(crate) cancel_token: Arc<CancelToken>,
-    pub(crate) step_runner: ThreadState<()>,
-}
-
-impl Worker<()> for StepWorker {
-    fn start(&mut self) {
-        self.step_runner.start();
-    }
-
-    fn stop(&mut self) -> Option<JoinHandle<()>> {
-        self.cancel_token.cancel();
-        self.join_handle.take()
-    }
-}
-
 struct StepIORunner {
     rc: Option<Receiver<Solution>>,
     tx: Option<Sender<Solution>>,
@@ -167,136 +154,171 @@ struct StepIORunner {
     cancel_token: Arc<CancelToken>,
     cube_state: Cube333,
     predicates: Vec<Box<dyn StepPredicate>>,
-}
-
-impl Run<()> for StepIORunner {
-    fn run(&mut self) -> () {
-        if let Some(rc) = self.rc.take() {
-            trace!("[{}] Started", self.step.get_variant());
-            self.run_internal(rc);
-            trace!("[{}] Terminated", self.step.get_variant());
-        }
-        drop(self.tx.take());
-    }
+    stats: Option<StepStatsRecorder>,
+    work_slice: Option<usize>,
+    visited_nodes: Cell<usize>,
+    bound: Option<Arc<SolutionBound>>,
+    remaining_min_moves: usize,
+    remaining_cancellation: usize,
 }
 
 impl StepIORunner {
-    fn run_internal(&mut self, rc: Receiver<Solution>) {
-        let next = if let Ok(next) = rc.recv() {
-            next
-        } else {
-            return;
+    fn into_task(self) -> Task {
+        // Safety: no value that isn't Send lives across a yield in this coroutine or the ones it yields from.
+        // The RefCell of the output buffer is only borrowed inside submit_solution, which doesn't yield
+        let task = unsafe {
+            SelfContained::new(self, |mut runner| {
+                #[coroutine] static move || {
+                    if let Some(rc) = runner.rc.take() {
+                        trace!("[{}] Started", runner.step.get_variant());
+                        yield_from!(runner.run_internal(rc));
+                        trace!("[{}] Terminated", runner.step.get_variant());
+                    }
+                    drop(runner.tx.take());
+                }
+            })
         };
-        self.input.push(next);
-        self.current_length = self.input[0].len();
-        while !self.cancel_token.is_cancelled() && self.current_length <= self.dfs_parameters.absolute_max_moves.unwrap_or(100) {
-            loop {
-                let output_buffer = self.output_buffer.get_mut();
-                if let Some(sol) = output_buffer.pop_if(|x|x.len() < self.current_length) {
-                    trace!("[{}] Flushed buffered solution of length {}\nTest: {}", self.step.get_variant(), sol.len(), sol);
-                    if let Err(_) = self.process_solution(sol) {
-                        return
+        Box::pin(task)
+    }
+
+    fn run_internal(&mut self, rc: Receiver<Solution>) -> impl Coroutine<Yield = (), Return = ()> + '_ {
+        #[coroutine] static move || {
+            let next = if let Ok(next) = recv_or_yield!(&rc) {
+                next
+            } else {
+                return;
+            };
+            self.input.push(next);
+            self.current_length = self.input[0].len();
+            while !self.cancel_token.is_cancelled() && self.current_length <= self.dfs_parameters.absolute_max_moves.unwrap_or(100) && !self.bound_exceeded() {
+                if let Some(stats) = self.stats.as_ref() {
+                    stats.set_length(self.current_length);
+                }
+                loop {
+                    let output_buffer = self.output_buffer.get_mut();
+                    if let Some(sol) = output_buffer.pop_if(|x|x.len() < self.current_length) {
+                        trace!("[{}] Flushed buffered solution of length {}\nTest: {}", self.step.get_variant(), sol.len(), sol);
+                        if let Err(_) = yield_from!(self.process_solution(sol)) {
+                            return
+                        }
+                    } else {
+                        break
                     }
-                } else {
-                    break
                 }
-            }
-            match self.process_fetched() {
-                Ok(Some(full_fetch_required_length)) => {
-                    while !self.cancel_token.is_cancelled() {
-                        match rc.recv() {
-                            Ok(next) => {
-                                let len = next.len();
-                                self.input.push(next);
-                                if len > full_fetch_required_length {
+                match yield_from!(self.process_fetched()) {
+                    Ok(Some(full_fetch_required_length)) => {
+                        while !self.cancel_token.is_cancelled() {
+                            match recv_or_yield!(&rc) {
+                                Ok(next) => {
+                                    let len = next.len();
+                                    self.input.push(next);
+                                    if len > full_fetch_required_length {
+                                        break
+                                    } else {
+                                        _ = yield_from!(self.process_fetched());
+                                    }
+                                }
+                                Err(_) => {
                                     break
-                                } else {
-                                    _ = self.process_fetched();
                                 }
                             }
-                            Err(_) => {
-                                break
-                            }
                         }
+                    },
+                    Ok(None) => {}
+                    Err(_) => {
+                        return;
                     }
-                },
-                Ok(None) => {}
-                Err(_) => {
-                    return;
-                }
-            };
-            _ = self.process_fetched();
-            self.current_position = 0;
-            self.current_length += 1;
+                };
+                _ = yield_from!(self.process_fetched());
+                self.current_position = 0;
+                self.current_length += 1;
+            }
         }
     }
 
-    fn process_fetched(&mut self) -> Result<Option<usize>, SendError<Solution>> {
-        let mut drain_until = 0;
-        while !self.cancel_token.is_cancelled() && self.current_position < self.input.len() {
-            let len = self.input[self.current_position].len();
-            if len > self.current_length {
-                break;
-            }
-            let depth = self.current_length - len;
-            if depth > self.dfs_parameters.max_moves {
-                drain_until += 1;
-                self.current_position += 1;
-                continue
-            } else if depth < self.dfs_parameters.min_moves {
+    // Nothing we find from here on can be completed into a solution that is as short as the best one
+    fn bound_exceeded(&self) -> bool {
+        let min_length = (self.current_length + self.remaining_min_moves).saturating_sub(self.remaining_cancellation);
+        self.bound.as_ref().is_some_and(|bound| bound.exceeded_by(min_length))
+    }
+
+    fn process_fetched(&mut self) -> impl Coroutine<Yield = (), Return = Result<Option<usize>, SendError<Solution>>> + '_ {
+        #[coroutine] static move || {
+            let mut drain_until = 0;
+            while !self.cancel_token.is_cancelled() && self.current_position < self.input.len() {
+                let len = self.input[self.current_position].len();
+                if len > self.current_length {
+                    break;
+                }
+                let depth = self.current_length - len;
+                if depth > self.dfs_parameters.max_moves {
+                    drain_until += 1;
+                    self.current_position += 1;
+                    continue
+                } else if depth < self.dfs_parameters.min_moves {
+                    self.current_position += 1;
+                    continue
+                }
+                let input = &self.input[self.current_position];
+                let dr_fin_axis = match self.step.get_variant() {
+                    StepVariant::DRFIN(axis) | StepVariant::DRFINLS(axis) => Some(axis),
+                     _ => None,
+                };
+                let cancel_previous_count = if let Some(dr_axis) = dr_fin_axis {
+                    DRFinishStep::get_possible_cancellation_count(input, dr_axis).min(4)
+                } else {
+                    0
+                };
+                let result = if depth == 0 {
+                    let mut result = Ok(());
+                    for additional_depth in 0..=cancel_previous_count {
+                        result = yield_from!(self.find_solutions(self.cube_state.clone(), input, depth + additional_depth, self.dfs_parameters.niss_type));
+                        if result.is_err() {
+                            break;
+                        }
+                    }
+                    result
+                } else {
+                    yield_from!(self.find_solutions(self.cube_state.clone(), input, depth + cancel_previous_count, self.dfs_parameters.niss_type))
+                };
+                if let Some(stats) = self.stats.as_ref() {
+                    stats.flush();
+                }
+                result?;
                 self.current_position += 1;
-                continue
             }
-            let input = &self.input[self.current_position];
-            let dr_fin_axis = match self.step.get_variant() {
-                StepVariant::DRFIN(axis) | StepVariant::DRFINLS(axis) => Some(axis),
-                 _ => None,
-            };
-            let cancel_previous_count = if let Some(dr_axis) = dr_fin_axis {
-                DRFinishStep::get_possible_cancellation_count(input, dr_axis).min(4)
-            } else {
-                0
-            };
-            if depth == 0 {
-                for additional_depth in 0..=cancel_previous_count {
-                    self.find_solutions(self.cube_state.clone(), input, depth + additional_depth, self.dfs_parameters.niss_type)?;
-                }
+
+            if drain_until > 0 {
+                self.input.drain(0..drain_until);
+            }
+            if self.current_position >= self.input.len() + drain_until {
+                Ok(Some(self.current_length))
             } else {
-                self.find_solutions(self.cube_state.clone(), input, depth + cancel_previous_count, self.dfs_parameters.niss_type)?;
+                Ok(None)
             }
-            self.current_position += 1;
-        }
-
-        if drain_until > 0 {
-            self.input.drain(0..drain_until);
-        }
-        if self.current_position >= self.input.len() + drain_until {
-            Ok(Some(self.current_length))
-        } else {
-            Ok(None)
         }
     }
 
-    fn submit_solution(&self, input: &Solution, result: Algorithm) -> Result<(), SendError<Solution>>{
+    // Returns the extended solution if it can be passed on right away, solutions that are too long get buffered instead
+    fn submit_solution(&self, input: &Solution, result: Algorithm) -> Option<Solution> {
         let mut input = input.clone();
         input.add_step(SolutionStep {
             variant: self.step.get_variant(),
             alg: result.clone(),
             comment: "".to_string(),
-            e_insertions: Default::default(),
+            slice_insertions: vec![],
         });
         if self.current_length < input.len() {
             self.output_buffer.borrow_mut().sorted_insert_binary_by(input.clone(), |a, b|b.len().cmp(&a.len()));
             trace!("Received solution of length {} out of order (current length: {})", input.len(), self.current_length);
-            return Ok(())
+            return None
         }
-
-        self.process_solution(input)
+        Some(input)
     }
 
-    fn process_solution(&self, input: Solution) -> Result<(), SendError<Solution>>{
-        for p in self.predicates.iter() {
-            match p.check_solution(&input) {
+    fn process_solution(&self, input: Solution) -> impl Coroutine<Yield = (), Return = Result<(), SendError<Solution>>> + '_ {
+        #[coroutine] static move || {
+            match check_predicates(&self.predicates, &input, self.stats.as_ref().map(|s| s.solver())) {
                 StepPredicateResult::Accepted => {}
                 StepPredicateResult::Rejected => {
                     return Ok(())
@@ -305,134 +327,179 @@ impl StepIORunner {
                     return Err(crossbeam::channel::SendError(input))
                 }
             }
-        }
-        if let Some(tx) = self.tx.as_ref() {
-            tx.send(input)
-        } else {
-            Err(crossbeam::channel::SendError(input))
+            if let Some(stats) = self.stats.as_ref() {
+                stats.solution(input.len());
+            }
+            if let Some(tx) = self.tx.as_ref() {
+                send_or_yield!(tx, input)
+            } else {
+                Err(crossbeam::channel::SendError(input))
+            }
         }
     }
 
     // Finds solutions that exactly match the depth parameter. Does _not_ look for shorter ones
-    pub fn find_solutions(&self, mut cube: Cube333, input: &Solution, depth: usize, niss_type: NissSwitchType) -> Result<(), SendError<Solution>> {
-        let alg: Algorithm = input.clone().into();
-        cube.apply_alg(&alg);
-
-        let (mut previous_normal, mut previous_inverse) = if self.dfs_parameters.ignore_previous_step_restrictions {
-            (None, None)
-        } else {
-            (alg.normal_moves.last().cloned(), alg.inverse_moves.last().cloned())
-        };
-        let start_on_normal = input.ends_on_normal;
+    fn find_solutions<'a>(&'a self, mut cube: Cube333, input: &'a Solution, depth: usize, niss_type: NissSwitchType) -> impl Coroutine<Yield = (), Return = Result<(), SendError<Solution>>> + 'a {
+        #[coroutine] static move || {
+            let alg: Algorithm = input.clone().into();
+            cube.apply_alg(&alg);
 
-        for t in self.step.pre_step_trans().iter().cloned() {
-            cube.transform(t);
-            previous_normal = previous_normal.map(|m|m.transform(t));
-            previous_inverse = previous_inverse.map(|m|m.transform(t));
-        }
-        if !self.step.is_cube_ready(&cube, Some(input)) {
-            return Ok(());
-        }
+            let (mut previous_normal, mut previous_inverse) = if self.dfs_parameters.ignore_previous_step_restrictions {
+                (None, None)
+            } else {
+                (alg.normal_moves.last().cloned(), alg.inverse_moves.last().cloned())
+            };
+            let start_on_normal = input.ends_on_normal;
 
-        //trace!("[{}{}] \t{alg} is solvable in {}, depth is {depth}", self.step.get_name().0, self.step.get_name().1, heuristic);
-        if self.step.heuristic(&cube, niss_type != NissSwitchType::Never, depth) == 0 {
-            //Only return a solution if we are allowed to return zero length solutions
-            if depth == 0 {
-                if self.step.is_solution_admissible(&cube, &alg) {
-                    self.submit_solution(input, Algorithm::new())?;
-                }
+            for t in self.step.pre_step_trans().iter().cloned() {
+                cube.transform(t);
+                previous_normal = previous_normal.map(|m|m.transform(t));
+                previous_inverse = previous_inverse.map(|m|m.transform(t));
             }
-            return Ok(());
-        }
-
-        let cancel_token = self.cancel_token.as_ref();
-        previous_normal = None;
-        previous_inverse = None;
-        let iter: Box<dyn Iterator<Item = Algorithm>> = match niss_type {
-            NissSwitchType::Never if start_on_normal => {
-                Box::new(self.find_solutions_dfs(cube, depth, false, previous_normal, previous_inverse, cancel_token))
-            },
-            NissSwitchType::Never => {
-                let mut cube = cube.clone();
-                cube.invert();
-                Box::new(self.find_solutions_dfs(cube, depth, false, previous_inverse, previous_normal, cancel_token)
-                    .map(|alg| {
-                        Algorithm {
-                            normal_moves: alg.inverse_moves,
-                            inverse_moves: alg.normal_moves
-                        }
-                    }))
-            },
-            NissSwitchType::Before => {
-                let mut cube = cube.clone();
-                let normal = self.find_solutions_dfs(cube.clone(), depth, false, previous_normal, previous_inverse, cancel_token);
-                cube.invert();
-                let inverse = self.find_solutions_dfs(cube, depth, false, previous_inverse, previous_normal, cancel_token)
-                    .map(|alg| {
-                        Algorithm {
-                            normal_moves: alg.inverse_moves,
-                            inverse_moves: alg.normal_moves
-                        }
-                    });
-                Box::new(normal.chain(inverse))
+            if !self.step.is_cube_ready(&cube, Some(input)) {
+                return Ok(());
             }
-            NissSwitchType::Always => {
-                let normal = self.find_solutions_dfs(cube.clone(), depth, true, previous_normal, previous_inverse, cancel_token);
-                let mut cube = cube.clone();
-                cube.invert();
-                let inverse = self.find_solutions_dfs(cube, depth, false, previous_inverse, previous_normal, cancel_token)
-                    .map(|alg| {
-                        Algorithm {
-                            normal_moves: alg.inverse_moves,
-                            inverse_moves: alg.normal_moves
+
+            //trace!("[{}{}] \t{alg} is solvable in {}, depth is {depth}", self.step.get_name().0, self.step.get_name().1, heuristic);
+            if self.step.heuristic(&cube, niss_type != NissSwitchType::Never, depth) == 0 {
+                //Only return a solution if we are allowed to return zero length solutions
+                if depth == 0 {
+                    if self.step.is_solution_admissible(&cube, &alg) {
+                        if let Some(solution) = self.submit_solution(input, Algorithm::new()) {
+                            yield_from!(self.process_solution(solution))?;
                         }
-                    });
-                Box::new(normal.chain(inverse))
+                    }
+                }
+                return Ok(());
             }
-        };
 
-        for alg in iter {
-            let mut alg = alg.reverse();
-            if !self.step.is_solution_admissible(&cube, &alg) {
-                continue;
-            }
-            for t in self.step.pre_step_trans().iter().cloned().rev() {
-                alg.transform(t.invert());
+            let cancel_token = self.cancel_token.as_ref();
+            previous_normal = None;
+            previous_inverse = None;
+            let iter: Box<dyn Iterator<Item = Option<Algorithm>>> = match niss_type {
+                NissSwitchType::Never if start_on_normal => {
+                    Box::new(self.find_solutions_dfs(cube, depth, false, previous_normal, previous_inverse, cancel_token))
+                },
+                NissSwitchType::Never => {
+                    let mut cube = cube.clone();
+                    cube.invert();
+                    Box::new(self.find_solutions_dfs(cube, depth, false, previous_inverse, previous_normal, cancel_token)
+                        .map(|alg| alg.map(|alg| {
+                            Algorithm {
+                                normal_moves: alg.inverse_moves,
+                                inverse_moves: alg.normal_moves
+                            }
+                        })))
+                },
+                NissSwitchType::Before => {
+                    let mut cube = cube.clone();
+                    let normal = self.find_solutions_dfs(cube.clone(), depth, false, previous_normal, previous_inverse, cancel_token);
+                    cube.invert();
+                    let inverse = self.find_solutions_dfs(cube, depth, false, previous_inverse, previous_normal, cancel_token)
+                        .map(|alg| alg.map(|alg| {
+                            Algorithm {
+                                normal_moves: alg.inverse_moves,
+                                inverse_moves: alg.normal_moves
+                            }
+                        }));
+                    Box::new(normal.chain(inverse))
+                }
+                NissSwitchType::Always => {
+                    let normal = self.find_solutions_dfs(cube.clone(), depth, true, previous_normal, previous_inverse, cancel_token);
+                    let mut cube = cube.clone();
+                    cube.invert();
+                    let inverse = self.find_solutions_dfs(cube, depth, false, previous_inverse, previous_normal, cancel_token)
+                        .map(|alg| alg.map(|alg| {
+                            Algorithm {
+                                normal_moves: alg.inverse_moves,
+                                inverse_moves: alg.normal_moves
+                            }
+                        }));
+                    Box::new(normal.chain(inverse))
+                }
+            };
+
+            for alg in iter {
+                let Some(alg) = alg else {
+                    // Keep progress reporting up to date while other tasks get to run
+                    if let Some(stats) = self.stats.as_ref() {
+                        stats.flush();
+                    }
+                    yield;
+                    continue;
+                };
+                let mut alg = alg.reverse();
+                if !self.step.is_solution_admissible(&cube, &alg) {
+                    continue;
+                }
+                for t in self.step.pre_step_trans().iter().cloned().rev() {
+                    alg.transform(t.invert());
+                }
+                if let Some(solution) = self.submit_solution(input, alg) {
+                    yield_from!(self.process_solution(solution))?;
+                }
             }
-            self.submit_solution(input, alg)?;
+            Ok(())
+        }
+    }
+
+    // Yields None in between solutions whenever the current work slice is used up
+    fn find_solutions_dfs<'a>(&'a self, cube: Cube333, depth: usize, niss_available: bool, prev: Option<Turn333>, prev_inv: Option<Turn333>, cancel_token: &'a CancelToken) -> Box<dyn Iterator<Item = Option<Algorithm>> + 'a> {
+        let results = self.find_solutions_dfs_node(cube, depth, niss_available, prev, prev_inv, cancel_token);
+        if self.work_slice_finished() {
+            Box::new(iter::once(None).chain(results))
+        } else {
+            results
+        }
+    }
+
+    fn work_slice_finished(&self) -> bool {
+        let Some(work_slice) = self.work_slice else {
+            return false;
+        };
+        let visited = self.visited_nodes.get() + 1;
+        if visited >= work_slice {
+            self.visited_nodes.set(0);
+            true
+        } else {
+            self.visited_nodes.set(visited);
+            false
         }
-        Ok(())
     }
 
-    fn find_solutions_dfs<'a>(&'a self, mut cube: Cube333, depth: usize, niss_available: bool, prev: Option<Turn333>, prev_inv: Option<Turn333>, cancel_token: &'a CancelToken) -> Box<dyn Iterator<Item = Algorithm> + 'a> {
+    fn find_solutions_dfs_node<'a>(&'a self, mut cube: Cube333, depth: usize, niss_available: bool, prev: Option<Turn333>, prev_inv: Option<Turn333>, cancel_token: &'a CancelToken) -> Box<dyn Iterator<Item = Option<Algorithm>> + 'a> {
         if cancel_token.is_cancelled() {
             return Box::new(vec![].into_iter());
         }
         let lower_bound = self.step.heuristic(&cube, niss_available, depth);
+        let pruned = (lower_bound == 0 && depth > 0) || lower_bound > depth;
+        if let Some(stats) = self.stats.as_ref() {
+            stats.node(pruned);
+        }
         if depth == 0 && lower_bound == 0 {
-            return Box::new(vec![Algorithm::new()].into_iter());
-        } else if lower_bound == 0 || lower_bound > depth {
+            return Box::new(iter::once(Some(Algorithm::new())));
+        } else if pruned {
             return Box::new(vec![].into_iter());
         }
-        let values: Box<dyn Iterator<Item = Algorithm>> = Box::new(self.step.get_moveset(&cube, depth).get_allowed_moves(prev, depth)
+        let values: Box<dyn Iterator<Item = Option<Algorithm>>> = Box::new(self.step.get_moveset(&cube, depth).get_allowed_moves(prev, depth)
             .flat_map(move |(turn, can_invert)|{
                 cube.turn(turn);
                 let normal_results = self.find_solutions_dfs(cube, depth - 1, niss_available, Some(turn), prev_inv, cancel_token)
-                    .map(move |mut alg|{
+                    .map(move |alg| alg.map(|mut alg| {
                         alg.normal_moves.push(turn);
                         alg
-                    });
-                let results: Box<dyn Iterator<Item = Algorithm>> = if niss_available && can_invert && depth > 1 {
+                    }));
+                let results: Box<dyn Iterator<Item = Option<Algorithm>>> = if niss_available && can_invert && depth > 1 {
                     let mut cube = cube.clone();
                     cube.invert();
                     let inverse_results = self.find_solutions_dfs(cube, depth - 1, false, prev_inv, Some(turn), cancel_token)
-                        .map(move |mut alg|{
+                        .map(move |alg| alg.map(|mut alg| {
                             alg.inverse_moves.push(turn);
                             Algorithm {
                                 normal_moves: alg.inverse_moves,
                                 inverse_moves: alg.normal_moves,
                             }
-                        });
+                        }));
                     Box::new(normal_results.chain(inverse_results))
                 } else {
                     Box::new(normal_results)
@@ -445,25 +512,48 @@ impl StepIORunner {
 }
 
 impl <S: Step + Send + 'static> ToWorker for S {
-    fn to_worker_box(self: Box<Self>, cube_state: Cube333, rc: Receiver<Solution>, tx: Sender<Solution>, additional_predicates: Vec<Box<dyn StepPredicate>>) -> Box<dyn Worker<()> + Send> {
-        let cancel_token = Arc::new(CancelToken::default());
-        Box::new(StepWorker {
-            join_handle: None,
-            cancel_token: cancel_token.clone(),
-            step_runner: ThreadState::PreStart(Box::new(StepIORunner {
-                rc: Some(rc),
-                tx: Some(tx),
-                input: vec![],
-                output_buffer: RefCell::new(vec![]),
-                dfs_parameters: self.get_dfs_parameters(),
-                current_length: 0,
-                current_position: 0,
-                step: self,
-                cancel_token: cancel_token.clone(),
-                predicates: additional_predicates,
-                cube_state
-            })),
-        })
+    fn to_worker_box(self: Box<Self>, cube_state: Cube333, rc: Receiver<Solution>, tx: Sender<Solution>, additional_predicates: Vec<Box<dyn StepPredicate>>, ctx: &WorkerContext) -> Vec<Task> {
+        let stats = ctx.stats.as_ref().map(|s| s.register_step(self.get_variant()));
+        // Solutions of this step can be shorter than the length it is searching at, if they cancel with the input
+        let remaining_cancellation = ctx.remaining_cancellation + self.max_cancellation();
+        vec![StepIORunner {
+            rc: Some(rc),
+            tx: Some(tx),
+            input: vec![],
+            output_buffer: RefCell::new(vec![]),
+            dfs_parameters: self.get_dfs_parameters(),
+            current_length: 0,
+            current_position: 0,
+            step: self,
+            cancel_token: ctx.cancel_token.clone(),
+            predicates: additional_predicates,
+            stats,
+            cube_state,
+            work_slice: ctx.execution.work_slice(),
+            visited_nodes: Cell::new(0),
+            bound: ctx.bound.clone(),
+            remaining_min_moves: ctx.remaining_min_moves,
+            remaining_cancellation,
+        }.into_task()]
+    }
+
+    fn min_moves(&self) -> usize {
+        self.get_dfs_parameters().min_moves
+    }
+
+    fn max_cancellation(&self) -> usize {
+        match self.get_dfs_parameters().niss_type {
+            NissSwitchType::Never => MAX_CANCELLATION_PER_SIDE,
+            _ => 2 * MAX_CANCELLATION_PER_SIDE,
+        }
+    }
+
+    fn check_cube(&self, cube: &Cube333) -> Result<(), String> {
+        let mut cube = *cube;
+        for t in self.pre_step_trans() {
+            cube.transform(*t);
+        }
+        Step::check_cube(self, &cube)
     }
 }
 
@@ -476,6 +566,19 @@ pub struct DFSParameters {
     pub ignore_previous_step_restrictions: bool,
 }
 
+fn intern_moves(moves: &[Turn333]) -> &'static [Turn333] {
+    static INTERNED: LazyLock<Mutex<HashSet<&'static [Turn333]>>> = LazyLock::new(Default::default);
+    let mut interned = INTERNED.lock().unwrap();
+    match interned.get(moves) {
+        Some(moves) => moves,
+        None => {
+            let moves: &'static [Turn333] = Vec::leak(moves.to_vec());
+            interned.insert(moves);
+            moves
+        }
+    }
+}
+
 pub struct MoveSet {
     pub st_moves: &'static [Turn333],
     pub aux_moves: &'static [Turn333],
@@ -507,6 +610,12 @@ impl MoveSet {
         }
     }
 
+    /// Move set for moves that are only known at runtime, like the ones of custom steps.
+    /// Every distinct move list is leaked once and reused after that, so building the same step again doesn't leak memory
+    pub fn new_interned(st_moves: &[Turn333], aux_moves: &[Turn333]) -> Self {
+        Self::new(intern_moves(st_moves), intern_moves(aux_moves))
+    }
+
     pub const fn new_default_transitions(ordered: bool, qt_first: bool) -> [[bool; 18]; 18] {
         let mut transitions = [[true; 18]; 18];
         let dirs = [Direction::Clockwise, Direction::CounterClockwise, Direction::Half];
@@ -0,0 +1,176 @@
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+use futures_core::Stream;
+
+use crate::cube::Cube333;
+use crate::solver::solution::Solution;
+use crate::solver_new::{bounded_channel, Receiver, Sender, TryRecvError};
+use crate::solver_new::executor::{recv_or_yield, send_or_yield, Task};
+use crate::solver_new::thread_util::{SolverWorker, ToWorker, WorkerContext};
+
+type WakerSlot = Arc<Mutex<Option<Waker>>>;
+
+const LOCAL_ROUNDS_PER_POLL: usize = 16;
+
+/// Solutions of a [`SolverWorker`] as a [`Stream`]. Polling never blocks, so this can be used from async code.
+///
+/// With [`ExecutionMode::Threads`](crate::solver_new::executor::ExecutionMode::Threads) the search runs on the worker's
+/// thread pool and the stream is woken whenever a solution is ready.
+/// With [`ExecutionMode::SingleThreaded`](crate::solver_new::executor::ExecutionMode::SingleThreaded) nothing else runs the search,
+/// so every poll does a bounded amount of work and then yields to the async executor, asking to be polled again.
+///
+/// Dropping the stream cancels the search without waiting for the worker threads to exit.
+pub struct SolverStream {
+    worker: SolverWorker,
+    waker: WakerSlot,
+}
+
+impl SolverStream {
+    pub fn new<T: Into<Box<dyn ToWorker + Send + 'static>>>(worker: T, cube: Cube333) -> Self {
+        Self::new_with_context(worker, cube, &WorkerContext::default())
+    }
+
+    pub fn new_with_context<T: Into<Box<dyn ToWorker + Send + 'static>>>(worker: T, cube: Cube333, ctx: &WorkerContext) -> Self {
+        let waker: WakerSlot = Arc::new(Mutex::new(None));
+        let task_waker = waker.clone();
+        let worker = SolverWorker::new_with_output_task(worker, cube, ctx, Box::new(move |rc| {
+            let (tx, out) = bounded_channel(1);
+            (out, notify_task(rc, tx, task_waker))
+        }));
+        Self {
+            worker,
+            waker,
+        }
+    }
+}
+
+fn wake(waker: &WakerSlot) {
+    if let Some(waker) = waker.lock().unwrap().take() {
+        waker.wake();
+    }
+}
+
+// Forwards solutions to the stream's receiver and wakes the stream afterwards
+fn notify_task(rc: Receiver<Solution>, tx: Sender<Solution>, waker: WakerSlot) -> Task {
+    Box::pin(#[coroutine] static move || {
+        while let Ok(solution) = recv_or_yield!(&rc) {
+            if send_or_yield!(&tx, solution).is_err() {
+                break;
+            }
+            wake(&waker);
+        }
+        drop(tx);
+        wake(&waker);
+    })
+}
+
+impl Stream for SolverStream {
+    type Item = Solution;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let local = self.worker.is_local();
+        // The pool wakes us through the output task. Register first, otherwise we could miss a solution that arrives
+        // between try_next and returning. Locally the output task runs during our own poll, so waking then would be pointless
+        if !local {
+            *self.waker.lock().unwrap() = Some(cx.waker().clone());
+        }
+        // Locally every call runs one round of work slices
+        let rounds = if local { LOCAL_ROUNDS_PER_POLL } else { 1 };
+        for _ in 0..rounds {
+            match self.worker.try_next() {
+                Ok(solution) => return Poll::Ready(Some(solution)),
+                Err(TryRecvError::Disconnected) => return Poll::Ready(None),
+                Err(TryRecvError::Empty) => {}
+            }
+        }
+        if local {
+            // There is more work to do, but other futures get to run first
+            cx.waker().wake_by_ref();
+        }
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::task::{Context, Poll};
+    use futures::executor::block_on;
+    use futures::StreamExt;
+    use futures::task::{waker, ArcWake};
+    use crate::algs::Algorithm;
+    use crate::defs::StepKind;
+    use crate::solver::solution::Solution;
+    use crate::solver_new::{build_step_tree, build_steps, StepConfigTree};
+    use crate::solver_new::eo::EOStep;
+    use crate::solver_new::executor::ExecutionMode;
+    use crate::solver_new::thread_util::WorkerContext;
+    use crate::steps::step::StepConfig;
+
+    struct WakeCounter(AtomicUsize);
+
+    impl ArcWake for WakeCounter {
+        fn wake_by_ref(arc_self: &Arc<Self>) {
+            arc_self.0.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn test_stream_matches_worker() {
+        let cube = Algorithm::from_str("R' U' F L' B L' B2 U B2 D U2 L2 F2 L2 R2 D' L2 B' F' R' F L' F' U' R' U' F").unwrap().into();
+        let ctx = WorkerContext::default().with_execution(ExecutionMode::SingleThreaded);
+        let step = || EOStep::builder().max_length(5).build();
+        let expected: Vec<Solution> = step().into_worker_with_context(cube, ctx.clone()).collect();
+        let solutions: Vec<Solution> = block_on(step().into_stream_with_context(cube, ctx).collect());
+        assert!(!expected.is_empty());
+        assert!(expected == solutions);
+
+        let first: Vec<Solution> = block_on(step().into_stream(cube).take(2).collect());
+        assert_eq!(2, first.len());
+    }
+
+    #[test]
+    fn test_local_stream_wakes_once_per_pending() {
+        let cube = Algorithm::from_str("R' U' F L' B L' B2 U B2 D U2 L2 F2 L2 R2 D' L2 B' F' R' F L' F' U' R' U' F").unwrap().into();
+        let ctx = WorkerContext::default().with_execution(ExecutionMode::SingleThreaded);
+        let mut stream = build_steps(vec![StepConfig::new(StepKind::EO), StepConfig::new(StepKind::DR)]).unwrap()
+            .into_stream_with_context(cube, ctx);
+        let wakes = Arc::new(WakeCounter(AtomicUsize::new(0)));
+        let waker = waker(wakes.clone());
+        let mut cx = Context::from_waker(&waker);
+        let mut pending = 0;
+        loop {
+            match stream.poll_next_unpin(&mut cx) {
+                Poll::Ready(Some(_)) => break,
+                Poll::Ready(None) => panic!("No solution found"),
+                Poll::Pending => pending += 1,
+            }
+        }
+        assert!(pending > 0);
+        assert_eq!(pending, wakes.0.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn test_local_stream_with_parallel_steps() {
+        let cube = Algorithm::from_str("R' U' F L' B L' B2 U B2 D U2 L2 F2 L2 R2 D' L2 B' F' R' F L' F' U' R' U' F").unwrap().into();
+        let eo = |axis: &str| StepConfigTree::Step(StepConfig {
+            substeps: Some(vec![axis.to_string()]),
+            ..StepConfig::new(StepKind::EO)
+        });
+        let tree = StepConfigTree::Sequence(vec![
+            StepConfigTree::Parallel(vec![eo("ud"), eo("fb")]),
+            StepConfigTree::Step(StepConfig::new(StepKind::DR)),
+        ]);
+        let ctx = WorkerContext::default().with_execution(ExecutionMode::SingleThreaded);
+        let solutions: Vec<Solution> = block_on(build_step_tree(tree).unwrap().into_stream_with_context(cube, ctx).take(2).collect());
+        assert_eq!(2, solutions.len());
+        assert!(solutions.iter().all(|s| s.steps.len() == 2));
+
+        let mismatched = StepConfigTree::Parallel(vec![eo("ud"), StepConfigTree::from(vec![StepConfig::new(StepKind::EO), StepConfig::new(StepKind::DR)])]);
+        assert!(build_step_tree(mismatched).is_err());
+    }
+}
@@ -0,0 +1,406 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+
+use log::debug;
+
+use crate::algs::Algorithm;
+use crate::cube::{Cube333, CornerPosition, EdgePosition, Transformation333, Turn333};
+use crate::cube::turn::{Invertible, TurnableMut};
+use crate::defs::StepVariant;
+use crate::solver::solution::Solution;
+use crate::solver_new::group::StepGroup;
+use crate::solver_new::step::{DFSParameters, MoveSet};
+use crate::solver_new::Step;
+use crate::steps::step::{PostStepCheck, PreStepCheck};
+
+// Distance of states from which the subgroup can't be reached
+const UNREACHABLE: u8 = u8::MAX;
+
+/// What a [`Subgroup`] knows about a single piece.
+///
+/// Pieces with the same label are interchangeable, a label of 0 means we don't care where the piece is.
+/// Edge orientation is measured on the F/B axis, corner orientation on the U/D axis.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct PieceTracking {
+    pub label: u8,
+    pub orientation: bool,
+}
+
+impl PieceTracking {
+    pub const IGNORED: PieceTracking = PieceTracking { label: 0, orientation: false };
+    pub const ORIENTATION: PieceTracking = PieceTracking { label: 0, orientation: true };
+
+    /// Tracks where a piece is, but not how it's oriented. Labels must be between 1 and 15
+    pub const fn position(label: u8) -> PieceTracking {
+        PieceTracking { label, orientation: false }
+    }
+
+    pub const fn piece(label: u8) -> PieceTracking {
+        PieceTracking { label, orientation: true }
+    }
+}
+
+/// The parts of the cube state a [`Subgroup`] looks at. Everything else is ignored by the coordinate.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct PieceMask {
+    pub edges: [PieceTracking; 12],
+    pub corners: [PieceTracking; 8],
+}
+
+impl PieceMask {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the tracking for the edge that belongs in `edge` when the cube is solved
+    pub fn edge(mut self, edge: EdgePosition, tracking: PieceTracking) -> Self {
+        self.edges[edge as usize] = tracking;
+        self
+    }
+
+    pub fn corner(mut self, corner: CornerPosition, tracking: PieceTracking) -> Self {
+        self.corners[corner as usize] = tracking;
+        self
+    }
+
+    pub fn all_edges(mut self, tracking: PieceTracking) -> Self {
+        self.edges = [tracking; 12];
+        self
+    }
+
+    pub fn all_corners(mut self, tracking: PieceTracking) -> Self {
+        self.corners = [tracking; 8];
+        self
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.edges.iter().chain(self.corners.iter()).any(|t| t.label > 15) {
+            Err("Piece labels must be between 0 and 15".to_string())
+        } else {
+            Ok(())
+        }
+    }
+
+    // 5 bits per edge and 6 bits per corner, indexed by position
+    fn key(&self, cube: &Cube333) -> u128 {
+        let mut key = 0u128;
+        for edge in cube.edges.get_edges() {
+            let tracking = self.edges[edge.id as usize];
+            let orientation = tracking.orientation && !edge.oriented_fb;
+            key = (key << 5) | ((tracking.label as u128) << 1) | orientation as u128;
+        }
+        for corner in cube.corners.get_corners() {
+            let tracking = self.corners[corner.id as usize];
+            let orientation = if tracking.orientation { corner.orientation } else { 0 };
+            key = (key << 6) | ((tracking.label as u128) << 2) | orientation as u128;
+        }
+        key
+    }
+}
+
+/// A target subgroup together with the pruning table for reaching it.
+///
+/// The state space of the masked pieces gets enumerated in memory, so this is only feasible
+/// for masks that leave a few million states at most.
+#[derive(Clone)]
+pub struct Subgroup {
+    inner: Arc<SubgroupTable>,
+}
+
+struct SubgroupTable {
+    variant: StepVariant,
+    mask: PieceMask,
+    coords: HashMap<u128, u32>,
+    distances: Vec<u8>,
+    move_set: MoveSet,
+}
+
+pub struct SubgroupBuilder {
+    name: String,
+    mask: PieceMask,
+    moves: Vec<Turn333>,
+    max_states: usize,
+}
+
+impl Subgroup {
+    pub fn builder(name: &str, mask: PieceMask) -> SubgroupBuilder {
+        SubgroupBuilder {
+            name: name.to_string(),
+            mask,
+            moves: Turn333::ALL.to_vec(),
+            max_states: 5_000_000,
+        }
+    }
+
+    pub fn variant(&self) -> StepVariant {
+        self.inner.variant
+    }
+
+    pub fn is_solved(&self, cube: &Cube333) -> bool {
+        self.distance(cube) == Some(0)
+    }
+
+    /// Number of moves needed to get into the subgroup, or `None` if the masked pieces can't be solved with the allowed moves
+    pub fn distance(&self, cube: &Cube333) -> Option<usize> {
+        self.inner.coords.get(&self.inner.mask.key(cube))
+            .map(|coord| self.inner.distances[*coord as usize])
+            .filter(|distance| *distance != UNREACHABLE)
+            .map(|distance| distance as usize)
+    }
+
+    /// Number of states of the masked pieces that are reachable with the allowed moves
+    pub fn size(&self) -> usize {
+        self.inner.distances.len()
+    }
+
+    pub fn step(&self, dfs: DFSParameters) -> StepGroup {
+        self.step_with_transformations(dfs, vec![])
+    }
+
+    /// Like [`Subgroup::step`], but the cube is transformed before checking the subgroup, e.g. to target a different axis
+    pub fn step_with_transformations(&self, dfs: DFSParameters, pre_step_trans: Vec<Transformation333>) -> StepGroup {
+        StepGroup::single(Box::new(SubgroupStep {
+            subgroup: self.clone(),
+            options: dfs,
+            pre_step_trans,
+        }))
+    }
+}
+
+impl SubgroupBuilder {
+    /// The moves the step is allowed to use. All outer layer moves by default
+    pub fn moves(mut self, moves: &[Turn333]) -> Self {
+        self.moves = moves.to_vec();
+        self
+    }
+
+    /// Fail instead of running out of memory for masks that track too many pieces
+    pub fn max_states(mut self, max_states: usize) -> Self {
+        self.max_states = max_states;
+        self
+    }
+
+    /// The subgroup contains all states that can be reached from the solved state with these moves
+    pub fn generators(self, generators: &[Turn333]) -> Result<Subgroup, String> {
+        let space = self.enumerate()?;
+        let mut goals = vec![false; space.transitions.len()];
+        let generators: Vec<usize> = generators.iter()
+            .map(|g| self.moves.iter().position(|m| m == g).ok_or(format!("Generator {g} is not an allowed move")))
+            .collect::<Result<_, _>>()?;
+        goals[0] = true;
+        let mut queue = VecDeque::from([0u32]);
+        while let Some(coord) = queue.pop_front() {
+            for g in generators.iter() {
+                let next = space.transitions[coord as usize][*g];
+                if !goals[next as usize] {
+                    goals[next as usize] = true;
+                    queue.push_back(next);
+                }
+            }
+        }
+        self.build(space, goals)
+    }
+
+    /// The subgroup contains all states that match the predicate. The predicate must only look at pieces tracked by the mask
+    pub fn predicate<F: Fn(&Cube333) -> bool>(self, predicate: F) -> Result<Subgroup, String> {
+        let space = self.enumerate()?;
+        let goals = space.states.iter().map(predicate).collect();
+        self.build(space, goals)
+    }
+
+    // Breadth first search through all states of the masked pieces that are reachable from the solved cube.
+    // The resulting move table has one row per state, the solved state has coordinate 0.
+    fn enumerate(&self) -> Result<StateSpace, String> {
+        self.mask.validate()?;
+        if self.moves.is_empty() {
+            return Err("A subgroup step needs at least one move".to_string());
+        }
+        let mut coords = HashMap::new();
+        let mut states = vec![Cube333::default()];
+        coords.insert(self.mask.key(&states[0]), 0u32);
+        let mut transitions: Vec<Vec<u32>> = vec![];
+        let mut current = 0;
+        while current < states.len() {
+            let mut row = Vec::with_capacity(self.moves.len());
+            for m in self.moves.iter() {
+                let mut cube = states[current];
+                cube.turn(*m);
+                let key = self.mask.key(&cube);
+                let next = match coords.get(&key) {
+                    Some(coord) => *coord,
+                    None => {
+                        if states.len() >= self.max_states {
+                            return Err(format!("Subgroup {} has more than {} states", self.name, self.max_states));
+                        }
+                        let coord = states.len() as u32;
+                        coords.insert(key, coord);
+                        states.push(cube);
+                        coord
+                    }
+                };
+                row.push(next);
+            }
+            transitions.push(row);
+            current += 1;
+        }
+        debug!("Subgroup {} has {} states", self.name, states.len());
+        Ok(StateSpace {
+            coords,
+            states,
+            transitions,
+        })
+    }
+
+    fn build(self, space: StateSpace, goals: Vec<bool>) -> Result<Subgroup, String> {
+        if !goals.iter().any(|g| *g) {
+            return Err(format!("Subgroup {} contains no states", self.name));
+        }
+        // The move set is closed under inverses, so walking away from the goal states gives us the distance towards them
+        let inverse: Vec<usize> = self.moves.iter()
+            .map(|m| self.moves.iter().position(|x| *x == m.invert()).ok_or(format!("The inverse of {m} is not an allowed move")))
+            .collect::<Result<_, _>>()?;
+        let mut distances = vec![UNREACHABLE; goals.len()];
+        let mut queue = VecDeque::new();
+        for (coord, _) in goals.iter().enumerate().filter(|(_, g)| **g) {
+            distances[coord] = 0;
+            queue.push_back(coord);
+        }
+        while let Some(coord) = queue.pop_front() {
+            for m in inverse.iter() {
+                let next = space.transitions[coord][*m] as usize;
+                if distances[next] == UNREACHABLE {
+                    distances[next] = distances[coord] + 1;
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        // Moves that never leave the subgroup can't be the last move of a solution
+        let mut st_moves = vec![];
+        let mut aux_moves = vec![];
+        for (idx, m) in self.moves.iter().enumerate() {
+            let stays_in_subgroup = goals.iter().enumerate()
+                .filter(|(_, g)| **g)
+                .all(|(coord, _)| goals[space.transitions[coord][idx] as usize]);
+            if stays_in_subgroup {
+                aux_moves.push(*m);
+            } else {
+                st_moves.push(*m);
+            }
+        }
+        Ok(Subgroup {
+            inner: Arc::new(SubgroupTable {
+                variant: StepVariant::custom(self.name.as_str()),
+                mask: self.mask,
+                coords: space.coords,
+                distances,
+                move_set: MoveSet::new_interned(&st_moves, &aux_moves),
+            })
+        })
+    }
+}
+
+struct StateSpace {
+    coords: HashMap<u128, u32>,
+    states: Vec<Cube333>,
+    transitions: Vec<Vec<u32>>,
+}
+
+struct SubgroupStep {
+    subgroup: Subgroup,
+    options: DFSParameters,
+    pre_step_trans: Vec<Transformation333>,
+}
+
+impl PreStepCheck for SubgroupStep {
+    fn is_cube_ready(&self, cube: &Cube333, _: Option<&Solution>) -> bool {
+        self.subgroup.distance(cube).is_some()
+    }
+}
+
+impl PostStepCheck for SubgroupStep {
+    fn is_solution_admissible(&self, _: &Cube333, _: &Algorithm) -> bool {
+        true
+    }
+}
+
+impl Step for SubgroupStep {
+    fn get_dfs_parameters(&self) -> DFSParameters {
+        self.options
+    }
+
+    fn get_moveset(&self, _: &Cube333, _: usize) -> &'_ MoveSet {
+        &self.subgroup.inner.move_set
+    }
+
+    fn heuristic(&self, state: &Cube333, can_niss_switch: bool, _: usize) -> usize {
+        match self.subgroup.distance(state) {
+            Some(heuristic) if can_niss_switch => heuristic.min(1),
+            Some(heuristic) => heuristic,
+            None => usize::MAX,
+        }
+    }
+
+    fn pre_step_trans(&self) -> &'_ Vec<Transformation333> {
+        &self.pre_step_trans
+    }
+
+    fn get_variant(&self) -> StepVariant {
+        self.subgroup.variant()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+    use crate::algs::Algorithm;
+    use crate::cube::{Cube333, Turn333};
+    use crate::cube::turn::ApplyAlgorithm;
+    use crate::defs::{NissSwitchType, StepVariant};
+    use crate::solver::solution::Solution;
+    use crate::solver_new::eo::EO_TABLE;
+    use crate::solver_new::step::DFSParameters;
+    use crate::solver_new::subgroup::{PieceMask, PieceTracking, Subgroup};
+    use crate::steps::eo::coords::EOCoordFB;
+
+    #[test]
+    fn test_custom_eo() {
+        let mask = PieceMask::new().all_edges(PieceTracking::ORIENTATION);
+        let by_generators = Subgroup::builder("custom-eo", mask)
+            .generators(&[Turn333::U, Turn333::D, Turn333::R, Turn333::L, Turn333::F2, Turn333::B2])
+            .unwrap();
+        let by_predicate = Subgroup::builder("custom-eo", mask)
+            .predicate(|cube| cube.edges.get_edges().iter().all(|e| e.oriented_fb))
+            .unwrap();
+        assert_eq!(2048, by_generators.size());
+        assert_eq!(StepVariant::custom("custom-eo"), by_predicate.variant());
+
+        let scramble = Algorithm::from_str("R' U' F L' B L' B2 U B2 D U2 L2 F2 L2 R2 D' L2 B' F' R' F L' F' U' R' U' F").unwrap();
+        let mut cube = Cube333::default();
+        cube.apply_alg(&scramble);
+        let expected = EO_TABLE.get(EOCoordFB::from(&cube)) as usize;
+        assert_eq!(Some(expected), by_generators.distance(&cube));
+        assert_eq!(Some(expected), by_predicate.distance(&cube));
+
+        let solution: Solution = by_generators.step(DFSParameters {
+            niss_type: NissSwitchType::Never,
+            min_moves: 0,
+            max_moves: 10,
+            absolute_max_moves: None,
+            ignore_previous_step_restrictions: false,
+        }).into_worker(cube).next().unwrap();
+        assert_eq!(expected, solution.len());
+        cube.apply_alg(&solution.into());
+        assert!(by_generators.is_solved(&cube));
+    }
+
+    #[test]
+    fn test_move_sets_are_shared() {
+        let build = || Subgroup::builder("custom-eo", PieceMask::new().all_edges(PieceTracking::ORIENTATION))
+            .generators(&[Turn333::U, Turn333::D, Turn333::R, Turn333::L, Turn333::F2, Turn333::B2])
+            .unwrap();
+        let (a, b) = (build(), build());
+        assert!(std::ptr::eq(a.inner.move_set.st_moves, b.inner.move_set.st_moves));
+        assert!(std::ptr::eq(a.inner.move_set.aux_moves, b.inner.move_set.aux_moves));
+    }
+}
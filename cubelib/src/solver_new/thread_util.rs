@@ -1,15 +1,20 @@
-use std::{mem, thread};
-use std::thread::JoinHandle;
+use std::mem;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 use crate::cube::Cube333;
+use crate::solver::df_search::CancelToken;
 use crate::solver::solution::Solution;
-use crate::solver_new::{bounded_channel, Receiver, Sender, SendError, TryRecvError};
+use crate::solver_new::{bounded_channel, Receiver, Sender, TryRecvError};
+use crate::solver_new::executor::{ExecutionMode, Executor, Task};
 use crate::solver_new::group::StepPredicate;
-use crate::solver_new::util_steps::FilterDup;
+use crate::solver_new::stats::SolverStats;
+use crate::solver_new::util_steps::{FilterDup, TrackBound};
 
 pub struct SolverWorker {
-    worker: Box<dyn Worker<()> + Send>,
+    executor: Executor,
     receiver: Receiver<Solution>,
+    cancel_token: Arc<CancelToken>,
     state: WorkerState,
 }
 
@@ -18,31 +23,63 @@ impl SolverWorker {
         Self::new_with_predicates(worker, cube, vec![])
     }
 
-    pub fn new_with_predicates<T: Into<Box<dyn ToWorker + Send + 'static>>>(worker: T, cube: Cube333, mut pred: Vec<Box<dyn StepPredicate>>) -> Self {
+    pub fn new_with_predicates<T: Into<Box<dyn ToWorker + Send + 'static>>>(worker: T, cube: Cube333, pred: Vec<Box<dyn StepPredicate>>) -> Self {
+        Self::new_with_context(worker, cube, pred, &WorkerContext::default())
+    }
+
+    pub fn new_with_context<T: Into<Box<dyn ToWorker + Send + 'static>>>(worker: T, cube: Cube333, pred: Vec<Box<dyn StepPredicate>>, ctx: &WorkerContext) -> Self {
+        Self::build(worker, cube, pred, ctx, None)
+    }
+
+    /// Like [`SolverWorker::new_with_context`], but all solutions pass through the task created by `output`
+    /// before they can be received
+    #[cfg(feature = "futures")]
+    pub(crate) fn new_with_output_task<T: Into<Box<dyn ToWorker + Send + 'static>>>(worker: T, cube: Cube333, ctx: &WorkerContext, output: Box<OutputTask>) -> Self {
+        Self::build(worker, cube, vec![], ctx, Some(output))
+    }
+
+    fn build<T: Into<Box<dyn ToWorker + Send + 'static>>>(worker: T, cube: Cube333, mut pred: Vec<Box<dyn StepPredicate>>, ctx: &WorkerContext, output: Option<Box<OutputTask>>) -> Self {
         let (tx0, rc0) = bounded_channel(1);
-        let (tx1, rc1) = bounded_channel(1);
+        let (tx1, mut rc1) = bounded_channel(1);
 
         tx0.send(Solution::new()).unwrap();
         drop(tx0);
         pred.push(FilterDup::new()); // It might be there already, but it's cheap enough, so we don't care
+        if let Some(bound) = ctx.bound.clone() {
+            pred.push(Box::new(TrackBound(bound)));
+        }
 
+        // Every solver gets its own cancel token, so dropping it stops all of its tasks without touching the parent
+        let ctx = WorkerContext {
+            cancel_token: Arc::new(CancelToken::child_of(ctx.cancel_token.clone())),
+            ..ctx.clone()
+        };
+        let mut tasks = worker.into().to_worker_box(cube, rc0, tx1, pred, &ctx);
+        if let Some(output) = output {
+            let (rc, task) = output(rc1);
+            rc1 = rc;
+            tasks.push(task);
+        }
         Self {
-            worker: worker.into().to_worker_box(cube, rc0, tx1, pred),
+            executor: Executor::new(ctx.execution, tasks),
             receiver: rc1,
+            cancel_token: ctx.cancel_token,
             state: WorkerState::Initialized,
         }
     }
 
     pub fn try_next(&mut self) -> Result<Solution, TryRecvError> {
         match self.state {
-            WorkerState::Initialized => {
-                self.worker.start();
-                self.state = WorkerState::Running;
-            },
+            WorkerState::Initialized => self.start(),
             WorkerState::Running => {}
             WorkerState::Finished => return Err(TryRecvError::Disconnected),
         }
 
+        if let Executor::Local(executor) = &mut self.executor {
+            if self.receiver.is_empty() {
+                executor.run_round();
+            }
+        }
         match self.receiver.try_recv() {
             Ok(s) => Ok(s),
             Err(TryRecvError::Empty) => Err(TryRecvError::Empty),
@@ -52,8 +89,24 @@ impl SolverWorker {
             }
         }
     }
+
+    #[cfg(feature = "futures")]
+    pub(crate) fn is_local(&self) -> bool {
+        matches!(self.executor, Executor::Local(_))
+    }
+
+    fn start(&mut self) {
+        if let Executor::Pool(pool) = &mut self.executor {
+            pool.start();
+        }
+        self.state = WorkerState::Running;
+    }
 }
 
+/// Takes the receiver for the solutions of a worker and returns the receiver the worker should read from instead,
+/// together with the task that moves solutions from one to the other
+pub(crate) type OutputTask = dyn FnOnce(Receiver<Solution>) -> (Receiver<Solution>, Task);
+
 #[derive(Copy, Clone)]
 enum WorkerState {
     Initialized,
@@ -66,20 +119,26 @@ impl Iterator for SolverWorker {
 
     fn next(&mut self) -> Option<Self::Item> {
         match self.state {
-            WorkerState::Initialized => {
-                self.worker.start();
-                self.state = WorkerState::Running;
-            },
+            WorkerState::Initialized => self.start(),
             WorkerState::Running => {}
             WorkerState::Finished => return None,
         }
-        match self.receiver.recv() {
-            Ok(s) => Some(s),
-            Err(_) => {
-                self.state = WorkerState::Finished;
-                None
-            }
+        let next = match &mut self.executor {
+            Executor::Pool(_) => self.receiver.recv().ok(),
+            Executor::Local(executor) => loop {
+                match self.receiver.try_recv() {
+                    Ok(s) => break Some(s),
+                    Err(TryRecvError::Disconnected) => break None,
+                    Err(TryRecvError::Empty) => {
+                        executor.run_round();
+                    }
+                }
+            },
+        };
+        if next.is_none() {
+            self.state = WorkerState::Finished;
         }
+        next
     }
 }
 
@@ -89,59 +148,114 @@ impl Drop for SolverWorker {
         mem::swap(&mut self.receiver, &mut rc);
         drop(rc);
         self.state = WorkerState::Finished;
-        if let Some(join_handle) = self.worker.stop() {
-            join_handle.join().unwrap();
+        self.cancel_token.cancel();
+        if let Executor::Pool(pool) = &mut self.executor {
+            pool.stop();
         }
     }
 }
 
-pub enum ThreadState<O> {
-    None,
-    PreStart(Box<dyn Run<O> + Send>),
-    PostStart(JoinHandle<O>),
-}
+pub trait ToWorker: Send {
+    fn to_worker(self: Self, cube_state: Cube333, rc: Receiver<Solution>, tx: Sender<Solution>, additional_predicates: Vec<Box<dyn StepPredicate>>, ctx: &WorkerContext) -> Vec<Task> where Self: Send + 'static + Sized {
+        Box::new(self).to_worker_box(cube_state, rc, tx, additional_predicates, ctx)
+    }
+    fn to_worker_box(self: Box<Self>, cube_state: Cube333, rc: Receiver<Solution>, tx: Sender<Solution>, additional_predicates: Vec<Box<dyn StepPredicate>>, ctx: &WorkerContext) -> Vec<Task> where Self: Send + 'static;
+
+    /// A lower bound for the number of moves this adds to a solution, before moves cancel with the previous step
+    fn min_moves(&self) -> usize {
+        0
+    }
 
-pub type SubmitFunction = Box<dyn FnMut(&Solution) -> Result<(), SendError<Solution>>>;
+    /// An upper bound for the number of moves that can cancel when solutions of this are appended to the previous step
+    fn max_cancellation(&self) -> usize {
+        2 * MAX_CANCELLATION_PER_SIDE
+    }
 
-impl <O> Default for ThreadState<O> {
-    fn default() -> Self {
-        Self::None
+    /// Returns why no solution can be found when starting from this cube, if that's known before searching
+    fn check_cube(&self, _cube: &Cube333) -> Result<(), String> {
+        Ok(())
     }
 }
 
-impl <O: Send + 'static> Worker<O> for ThreadState<O> {
-    fn start(&mut self) {
-        *self = match mem::take(self) {
-            ThreadState::None => ThreadState::None,
-            ThreadState::PreStart(mut runner) => ThreadState::PostStart(thread::spawn(move || runner.run())),
-            ThreadState::PostStart(x) => ThreadState::PostStart(x),
+/// Moves on the same axis cancel across step boundaries, e.g. `L R` followed by `L' R'`.
+/// So on each side of the solution (normal and inverse), a step boundary can remove up to two moves of both steps
+pub(crate) const MAX_CANCELLATION_PER_SIDE: usize = 4;
+
+/// The length of the shortest complete solution found so far. Steps stop searching once the solutions
+/// they could still find are longer than that.
+#[derive(Debug)]
+pub struct SolutionBound {
+    best: AtomicUsize,
+    min_length: usize,
+}
+
+impl SolutionBound {
+    /// Solutions shorter than `min_length` don't count towards the bound
+    pub fn new(min_length: usize) -> Self {
+        Self {
+            best: AtomicUsize::new(usize::MAX),
+            min_length,
         }
     }
 
-    fn stop(&mut self) -> Option<JoinHandle<O>> {
-        match mem::take(self) {
-            ThreadState::None => None,
-            ThreadState::PreStart(x) => {
-                *self = ThreadState::PreStart(x);
-                None
-            }
-            ThreadState::PostStart(jh) => Some(jh),
+    pub fn get(&self) -> Option<usize> {
+        match self.best.load(Ordering::Relaxed) {
+            usize::MAX => None,
+            best => Some(best),
+        }
+    }
+
+    pub(crate) fn record(&self, length: usize) {
+        if length >= self.min_length {
+            self.best.fetch_min(length, Ordering::Relaxed);
         }
     }
-}
 
-pub trait Run<T> {
-    fn run(&mut self) -> T;
+    pub(crate) fn exceeded_by(&self, length: usize) -> bool {
+        length > self.best.load(Ordering::Relaxed)
+    }
 }
 
-pub trait Worker<O: Send + 'static> {
-    fn start(&mut self);
-    fn stop(&mut self) -> Option<JoinHandle<O>>;
+/// Shared settings that are handed down to every worker of a solver
+#[derive(Clone, Default)]
+pub struct WorkerContext {
+    pub stats: Option<Arc<SolverStats>>,
+    pub execution: ExecutionMode,
+    pub(crate) cancel_token: Arc<CancelToken>,
+    pub(crate) bound: Option<Arc<SolutionBound>>,
+    /// Merge the solutions of parallel step groups in a fixed order, see [`StepGroup::parallel`]
+    pub deterministic: bool,
+    // The moves the steps after the current one need at least, and how many moves can cancel when they are added.
+    // Only set for the workers of sequential step groups
+    pub(crate) remaining_min_moves: usize,
+    pub(crate) remaining_cancellation: usize,
 }
 
-pub trait ToWorker: Send {
-    fn to_worker(self: Self, cube_state: Cube333, rc: Receiver<Solution>, tx: Sender<Solution>, additional_predicates: Vec<Box<dyn StepPredicate>>) -> Box<dyn Worker<()> + Send> where Self: Send + 'static + Sized {
-        Box::new(self).to_worker_box(cube_state, rc, tx, additional_predicates)
+impl WorkerContext {
+    pub fn with_stats(mut self, stats: Arc<SolverStats>) -> Self {
+        self.stats = Some(stats);
+        self
+    }
+
+    pub fn with_execution(mut self, execution: ExecutionMode) -> Self {
+        self.execution = execution;
+        self
+    }
+
+    pub fn with_deterministic(mut self, deterministic: bool) -> Self {
+        self.deterministic = deterministic;
+        self
+    }
+
+    /// Workers stop once `cancel_token` is cancelled. Dropping a worker never cancels this token.
+    pub fn with_cancel_token(mut self, cancel_token: Arc<CancelToken>) -> Self {
+        self.cancel_token = cancel_token;
+        self
+    }
+
+    /// Only look for the shortest solutions. Steps stop as soon as they can no longer find a solution that is as short as the best one in `bound`
+    pub fn with_bound(mut self, bound: Arc<SolutionBound>) -> Self {
+        self.bound = Some(bound);
+        self
     }
-    fn to_worker_box(self: Box<Self>, cube_state: Cube333, rc: Receiver<Solution>, tx: Sender<Solution>, additional_predicates: Vec<Box<dyn StepPredicate>>) -> Box<dyn Worker<()> + Send> where Self: Send + 'static;
 }
@@ -0,0 +1,186 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::sync::{Arc, LazyLock, Mutex};
+
+use itertools::Itertools;
+
+use crate::algs::Algorithm;
+use crate::cube::{Cube333, CubeFace, Direction, Turn333};
+use crate::cube::turn::{Invertible, TurnableMut};
+use crate::solver_new::dr::{calc_rzp_state, DRStep};
+use crate::steps::coord::Coord;
+use crate::steps::dr::coords::DRUDEOFBCoord;
+use crate::steps::dr::dr_config::{DR_UD_EO_FB_MOVES, DR_UD_EO_FB_STATE_CHANGE_MOVES};
+
+/// The trigger length used for `triggers=auto`
+pub const DEFAULT_TRIGGER_LENGTH: usize = 4;
+/// The longest triggers `triggers=auto:<length>` accepts. The search grows exponentially with the length and can't be cancelled
+pub const MAX_AUTO_TRIGGER_LENGTH: usize = 6;
+
+/// An RZP state by the number of bad corners and edges relative to DR on UD with EO on FB, e.g. 4c2e
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, Ord, PartialOrd)]
+pub struct RZPCase {
+    pub corners: u8,
+    pub edges: u8,
+}
+
+impl Display for RZPCase {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}c{}e", self.corners, self.edges)
+    }
+}
+
+impl FromStr for RZPCase {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid RZP case '{s}'. Expected bad corners and edges like 4c2e");
+        let lower = s.trim().to_ascii_lowercase();
+        let (corners, edges) = lower.strip_suffix('e')
+            .and_then(|s| s.split_once('c'))
+            .ok_or_else(invalid)?;
+        let corners = u8::from_str(corners).map_err(|_| invalid())?;
+        let edges = u8::from_str(edges).map_err(|_| invalid())?;
+        if corners > 8 || edges > 8 {
+            return Err(invalid());
+        }
+        Ok(RZPCase { corners, edges })
+    }
+}
+
+/// All triggers up to a given length, grouped by the RZP case they solve.
+///
+/// Triggers are written for DR on UD with EO on FB, end with `R` and start with `R` or `L`, like the ones in the `triggers` DR parameter.
+/// Only one orientation of every trigger is kept, as the DR step tries all of them anyway.
+/// A trigger never passes through DR before its last move, otherwise a shorter trigger would do the same.
+pub struct TriggerLibrary {
+    cases: BTreeMap<RZPCase, Vec<Algorithm>>,
+}
+
+impl TriggerLibrary {
+    pub fn generate(max_length: usize) -> Self {
+        let mut found = vec![];
+        if max_length > 0 {
+            // We go backwards from a DR state, so every prefix we add is a potential trigger
+            let mut cube = Cube333::default();
+            let last = Turn333::new(CubeFace::Right, Direction::Clockwise);
+            cube.turn(last.invert());
+            Self::search(cube, &mut vec![last], max_length, &mut found);
+        }
+        found.sort_by_key(|(_, moves)| (moves.len(), moves.iter().map(|m| (m.face as usize, m.dir as usize)).collect_vec()));
+
+        let mut seen: HashSet<Vec<Turn333>> = HashSet::new();
+        let mut cases: BTreeMap<RZPCase, Vec<Algorithm>> = BTreeMap::new();
+        for (case, moves) in found {
+            if seen.contains(&moves) {
+                continue;
+            }
+            let trigger = Algorithm { normal_moves: moves, inverse_moves: vec![] };
+            seen.extend(DRStep::generate_trigger_variations(trigger.clone()));
+            cases.entry(case).or_default().push(trigger);
+        }
+        Self { cases }
+    }
+
+    /// Like [`TriggerLibrary::generate`], but every length is only generated once
+    pub fn cached(max_length: usize) -> Arc<Self> {
+        static LIBRARIES: LazyLock<Mutex<HashMap<usize, Arc<TriggerLibrary>>>> = LazyLock::new(Default::default);
+        LIBRARIES.lock().unwrap()
+            .entry(max_length)
+            .or_insert_with(|| Arc::new(Self::generate(max_length)))
+            .clone()
+    }
+
+    // `moves` is the trigger from the current state on
+    fn search(cube: Cube333, moves: &mut Vec<Turn333>, max_length: usize, found: &mut Vec<(RZPCase, Vec<Turn333>)>) {
+        if DRUDEOFBCoord::from(&cube).val() == 0 {
+            return;
+        }
+        if DR_UD_EO_FB_STATE_CHANGE_MOVES.contains(&moves[0]) {
+            let (corners, edges) = calc_rzp_state(&cube);
+            found.push((RZPCase { corners, edges }, moves.clone()));
+        }
+        if moves.len() >= max_length {
+            return;
+        }
+        for m in DR_UD_EO_FB_STATE_CHANGE_MOVES.iter().chain(DR_UD_EO_FB_MOVES.iter()) {
+            let next = moves[0];
+            // Skip redundant sequences like R R' and only allow one order of moves on opposite faces
+            if m.face == next.face || (m.face == next.face.opposite() && (m.face as usize) > (next.face as usize)) {
+                continue;
+            }
+            let mut cube = cube;
+            cube.turn(m.invert());
+            moves.insert(0, *m);
+            Self::search(cube, moves, max_length, found);
+            moves.remove(0);
+        }
+    }
+
+    pub fn cases(&self) -> impl Iterator<Item = (&RZPCase, &Vec<Algorithm>)> {
+        self.cases.iter()
+    }
+
+    pub fn case(&self, case: RZPCase) -> &[Algorithm] {
+        self.cases.get(&case).map(|t| t.as_slice()).unwrap_or(&[])
+    }
+
+    /// Every trigger of the library, shortest first. Can be passed to [`DRStep::new_with_triggers`]
+    pub fn triggers(&self) -> Vec<Algorithm> {
+        self.cases.values()
+            .flatten()
+            .cloned()
+            .sorted_by_key(|t| t.len())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::str::FromStr;
+    use std::sync::Arc;
+    use crate::algs::Algorithm;
+    use crate::cube::CubeAxis;
+    use crate::defs::StepKind;
+    use crate::solver_new::build_step;
+    use crate::solver_new::trigger::{MAX_AUTO_TRIGGER_LENGTH, RZPCase, TriggerLibrary};
+    use crate::steps::step::StepConfig;
+
+    #[test]
+    fn test_generate_triggers() {
+        let library = TriggerLibrary::generate(3);
+        let r = Algorithm::from_str("R").unwrap();
+        let ru2r = Algorithm::from_str("R U2 R").unwrap();
+        assert_eq!(vec![r], library.case(RZPCase { corners: 4, edges: 4 }).iter().filter(|t| t.len() == 1).cloned().collect::<Vec<_>>());
+        assert!(library.case(RZPCase { corners: 4, edges: 2 }).contains(&ru2r));
+        // R D2 R is the same trigger in another orientation
+        assert!(!library.triggers().contains(&Algorithm::from_str("R D2 R").unwrap()));
+        assert!(library.triggers().iter().all(|t| t.normal_moves.last().unwrap().face.is_on_axis(CubeAxis::LR)));
+    }
+
+    #[test]
+    fn test_auto_triggers() {
+        assert!(Arc::ptr_eq(&TriggerLibrary::cached(2), &TriggerLibrary::cached(2)));
+
+        let eo = StepConfig::new(StepKind::EO);
+        let dr = |triggers: &str| StepConfig {
+            params: HashMap::from([("triggers".to_string(), triggers.to_string())]),
+            ..StepConfig::new(StepKind::DR)
+        };
+        assert!(build_step(Some(&eo), dr("auto:2")).is_ok());
+        assert!(build_step(Some(&eo), dr(&format!("auto:{MAX_AUTO_TRIGGER_LENGTH}"))).is_ok());
+        assert!(build_step(Some(&eo), dr(&format!("auto:{}", MAX_AUTO_TRIGGER_LENGTH + 1))).is_err());
+        assert!(build_step(Some(&eo), dr("auto:12")).is_err());
+    }
+
+    #[test]
+    fn test_parse_case() {
+        assert_eq!(Ok(RZPCase { corners: 4, edges: 2 }), RZPCase::from_str("4c2e"));
+        assert_eq!(Ok(RZPCase { corners: 0, edges: 8 }), RZPCase::from_str("0C8E"));
+        assert!(RZPCase::from_str("bogus").is_err());
+        assert!(RZPCase::from_str("4c").is_err());
+        assert!(RZPCase::from_str("9c2e").is_err());
+    }
+}
@@ -1,5 +1,6 @@
 use std::cell::RefCell;
 use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use crate::algs::Algorithm;
 use crate::cube::{Cube333, Turn333};
@@ -7,6 +8,7 @@ use crate::cube::turn::Direction;
 use crate::defs::StepKind;
 use crate::solver::solution::Solution;
 use crate::solver_new::group::{StepPredicate, StepPredicateResult};
+use crate::solver_new::thread_util::SolutionBound;
 
 pub struct FilterDup(RefCell<HashSet<Algorithm>>);
 pub struct FilterLastMoveNotPrime;
@@ -19,6 +21,8 @@ pub struct FilterFirstNStepVariant {
 }
 
 pub struct FilterExcluded(HashSet<Algorithm>);
+// Lowers the bound for complete solutions. Solutions come in ordered by length, so once one exceeds the bound, all others do too
+pub(crate) struct TrackBound(pub(crate) Arc<SolutionBound>);
 
 impl FilterExcluded {
     pub fn new(excluded: HashSet<Algorithm>) -> Box<dyn StepPredicate> {
@@ -54,6 +58,17 @@ impl StepPredicate for FilterExcluded {
     }
 }
 
+impl StepPredicate for TrackBound {
+    fn check_solution(&self, solution: &Solution) -> StepPredicateResult {
+        let len = solution.len();
+        if self.0.exceeded_by(len) {
+            return StepPredicateResult::Closed;
+        }
+        self.0.record(len);
+        StepPredicateResult::Accepted
+    }
+}
+
 impl FilterDup {
     pub fn new() -> Box<dyn StepPredicate> {
         Box::new(Self(RefCell::new(Default::default())))
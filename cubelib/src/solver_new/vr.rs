@@ -1,21 +1,23 @@
 #[cfg(feature = "multi-path-channel-solver")]
 pub use implementation::VRStep;
-use crate::cube::Direction;
 use std::collections::HashMap;
+use crate::cube::CubeAxis;
 use crate::defs::StepVariant;
-use crate::cube::Transformation333;
+use crate::solver::solution::SliceInsertion;
 
+/// The insertions of a VR per step, and the slice they were found for
 #[derive(Debug, Clone, Eq, PartialEq)]
 #[cfg_attr(feature = "serde_support", derive(serde::Serialize, serde::Deserialize))]
 pub struct VRInsertions(
-    pub(crate) HashMap<StepVariant, Vec<(usize, Direction)>>,
-    pub(crate) Option<Transformation333>);
+    pub(crate) HashMap<StepVariant, Vec<(usize, SliceInsertion)>>,
+    pub(crate) CubeAxis);
 
 #[cfg(feature = "multi-path-channel-solver")]
 mod implementation {
     use crate::solver_new::vr::VRInsertions;
     use std::collections::{HashMap};
     use std::fmt::{Debug, Display, Formatter};
+    use std::ops::Coroutine;
     use std::simd::prelude::*;
     use std::sync::Arc;
     use itertools::Itertools;
@@ -25,12 +27,14 @@ mod implementation {
     use crate::cube::turn::{ApplyAlgorithm, CubeAxis, CubeTransformation, Direction, Invertible, Transformable, TransformableMut, TurnableMut};
     use crate::defs::{NissSwitchType, StepKind, StepVariant};
     use crate::solver::df_search::CancelToken;
-    use crate::solver::solution::{Solution, SolutionStep};
+    use crate::solver::solution::{SliceInsertion, Solution, SolutionStep};
     use crate::solver_new::finish::DRFinishStep;
     use crate::solver_new::{Receiver, Sender, SendError};
-    use crate::solver_new::group::{StepGroup, StepPredicate, StepPredicateResult};
-    use crate::solver_new::step::{DFSParameters, StepWorker};
-    use crate::solver_new::thread_util::{Run, ThreadState, ToWorker, Worker};
+    use crate::solver_new::group::{check_predicates, StepGroup, StepPredicate, StepPredicateResult};
+    use crate::solver_new::stats::SolverStats;
+    use crate::solver_new::executor::{recv_or_yield, send_or_yield, yield_from, ExecutionMode, SelfContained, Task};
+    use crate::solver_new::step::DFSParameters;
+    use crate::solver_new::thread_util::{ToWorker, WorkerContext};
     use crate::solver_new::util_cube::CubeState;
     use crate::steps::coord::Coord;
     use crate::steps::dr::coords::DRUDEOFBCoord;
@@ -55,82 +59,89 @@ mod implementation {
         cube_state: Cube333,
         current_length: usize,
         predicates: Vec<Box<dyn StepPredicate>>,
+        stats: Option<Arc<SolverStats>>,
         step: Box<VRStep>,
     }
 
     impl ToWorker for VRStep {
-        fn to_worker_box(self: Box<Self>, cube_state: Cube333, rc: Receiver<Solution>, tx: Sender<Solution>, additional_predicates: Vec<Box<dyn StepPredicate>>) -> Box<dyn Worker<()> + Send> {
-            let cancel_token = Arc::new(CancelToken::default());
-            Box::new(StepWorker {
-                join_handle: None,
-                cancel_token: cancel_token.clone(),
-                step_runner: ThreadState::PreStart(Box::new(VRStepRunner {
-                    rc: Some(rc),
-                    tx: Some(tx),
-                    output_buffer: HashMap::new(),
-                    current_length: 0,
-                    step: self.into(),
-                    cancel_token: cancel_token.clone(),
-                    predicates: additional_predicates,
-                    cube_state
-                })),
-            })
+        fn to_worker_box(self: Box<Self>, cube_state: Cube333, rc: Receiver<Solution>, tx: Sender<Solution>, additional_predicates: Vec<Box<dyn StepPredicate>>, ctx: &WorkerContext) -> Vec<Task> {
+            vec![VRStepRunner {
+                rc: Some(rc),
+                tx: Some(tx),
+                output_buffer: HashMap::new(),
+                current_length: 0,
+                step: self,
+                cancel_token: ctx.cancel_token.clone(),
+                predicates: additional_predicates,
+                stats: ctx.stats.clone(),
+                cube_state
+            }.into_task()]
         }
     }
 
-    impl Run<()> for VRStepRunner {
-        fn run(&mut self) -> () {
-            if let Some(rc) = self.rc.take() {
-                trace!("[vr] Started");
-                self.run_internal(rc);
-                trace!("[vr] Terminated");
-            }
-            drop(self.tx.take());
+    impl VRStepRunner {
+        fn into_task(self) -> Task {
+            // Safety: no value that isn't Send lives across a yield in this coroutine or the ones it yields from
+            let task = unsafe {
+                SelfContained::new(self, |mut runner| {
+                    #[coroutine] static move || {
+                        if let Some(rc) = runner.rc.take() {
+                            trace!("[vr] Started");
+                            yield_from!(runner.run_internal(rc));
+                            trace!("[vr] Terminated");
+                        }
+                        drop(runner.tx.take());
+                    }
+                })
+            };
+            Box::pin(task)
         }
-    }
 
-    impl VRStepRunner {
-        fn run_internal(&mut self, rc: Receiver<Solution>) {
-            while !self.cancel_token.is_cancelled() {
-                match rc.recv() {
-                    Ok(next) => {
-                        let len = next.len();
-                        if let Some(sol) = solve_slice(&self.cube_state, &next, self.step.vr_in, self.step.to_solved) {
-                            self.output_buffer.entry(sol.len())
-                                .or_default()
-                                .push(sol);
+        fn run_internal(&mut self, rc: Receiver<Solution>) -> impl Coroutine<Yield = (), Return = ()> + '_ {
+            #[coroutine] static move || {
+                while !self.cancel_token.is_cancelled() {
+                    match recv_or_yield!(&rc) {
+                        Ok(next) => {
+                            let len = next.len();
+                            if let Some(sol) = solve_slice(&self.cube_state, &next, self.step.vr_in, self.step.to_solved) {
+                                self.output_buffer.entry(sol.len())
+                                    .or_default()
+                                    .push(sol);
+                            }
+                            if yield_from!(self.send_up_to(len)).is_err() {
+                                break
+                            }
                         }
-                        if self.send_up_to(len).is_err() {
+                        Err(_) => {
+                            let _ = yield_from!(self.send_up_to(usize::MAX));
                             break
                         }
                     }
-                    Err(_) => {
-                        let _ = self.send_up_to(usize::MAX);
-                        break
-                    }
                 }
             }
         }
 
-        fn send_up_to(&mut self, max_len: usize) -> Result<(), SendError<Solution>> {
-            while !self.output_buffer.is_empty() {
-                for sol in self.output_buffer.remove(&self.current_length)
-                    .into_iter()
-                    .flat_map(|x|x) {
-                    self.process_solution(sol)?;
-                }
-                if self.current_length >= max_len {
-                    return Ok(())
-                } else {
-                    self.current_length += 1;
+        fn send_up_to(&mut self, max_len: usize) -> impl Coroutine<Yield = (), Return = Result<(), SendError<Solution>>> + '_ {
+            #[coroutine] static move || {
+                while !self.output_buffer.is_empty() {
+                    for sol in self.output_buffer.remove(&self.current_length)
+                        .into_iter()
+                        .flat_map(|x|x) {
+                        yield_from!(self.process_solution(sol))?;
+                    }
+                    if self.current_length >= max_len {
+                        return Ok(())
+                    } else {
+                        self.current_length += 1;
+                    }
                 }
+                Ok(())
             }
-            Ok(())
         }
 
-        fn process_solution(&self, input: Solution) -> Result<(), SendError<Solution>>{
-            for p in self.predicates.iter() {
-                match p.check_solution(&input) {
+        fn process_solution(&self, input: Solution) -> impl Coroutine<Yield = (), Return = Result<(), SendError<Solution>>> + '_ {
+            #[coroutine] static move || {
+                match check_predicates(&self.predicates, &input, self.stats.as_deref()) {
                     StepPredicateResult::Accepted => {}
                     StepPredicateResult::Rejected => {
                         return Ok(())
@@ -139,12 +150,11 @@ mod implementation {
                         return Err(crossbeam::channel::SendError(input))
                     }
                 }
-            }
-            if let Some(tx) = self.tx.as_ref() {
-                let x = tx.send(input);
-                x
-            } else {
-                Err(crossbeam::channel::SendError(input))
+                if let Some(tx) = self.tx.as_ref() {
+                    send_or_yield!(tx, input)
+                } else {
+                    Err(crossbeam::channel::SendError(input))
+                }
             }
         }
     }
@@ -207,12 +217,18 @@ mod implementation {
     }
 
     impl VRSolution {
-        pub(crate) fn vr_step_insertions(self, steps: &Vec<&SolutionStep>, trans: Option<Transformation333>) -> VRInsertions {
-            let mut e_insertions: HashMap<StepVariant, Vec<(usize, Direction)>> = HashMap::new();
+        // The insertions are found on a cube transformed so that the slice is E, so we have to map them back onto the actual slice
+        pub(crate) fn vr_step_insertions(self, steps: &Vec<&SolutionStep>, slice: CubeAxis) -> VRInsertions {
+            let mut insertions: HashMap<StepVariant, Vec<(usize, SliceInsertion)>> = HashMap::new();
             let normal_length = steps.iter()
                 .map(|x|x.alg.normal_moves.len())
                 .sum::<usize>();
             for (index, direction) in self.e_insertions.iter() {
+                let insertion = if slice == CubeAxis::UD {
+                    SliceInsertion::new(slice, *direction)
+                } else {
+                    SliceInsertion::new(slice, direction.invert())
+                };
                 let mut offset = 0;
                 if *index <= normal_length && normal_length > 0 {
                     for step in steps {
@@ -220,9 +236,9 @@ mod implementation {
                             continue
                         }
                         if index - offset <= step.alg.normal_moves.len() {
-                            e_insertions.entry(step.variant.clone())
+                            insertions.entry(step.variant.clone())
                                 .or_default()
-                                .push((index - offset, *direction));
+                                .push((index - offset, insertion));
                             break
                         }
                         offset += step.alg.normal_moves.len();
@@ -234,16 +250,16 @@ mod implementation {
                             continue
                         }
                         if index - offset <= step.alg.inverse_moves.len() {
-                            e_insertions.entry(step.variant.clone())
+                            insertions.entry(step.variant.clone())
                                 .or_default()
-                                .push((index - offset, *direction));
+                                .push((index - offset, insertion));
                             break
                         }
                         offset += step.alg.inverse_moves.len();
                     }
                 }
             }
-            VRInsertions(e_insertions, trans.or(Some(Transformation333::Y)))
+            VRInsertions(insertions, slice)
         }
     }
 
@@ -350,14 +366,7 @@ mod implementation {
                     e_insertions: x.e_insertions,
                     length: 0,
                 })
-                .map(|mut x|{
-                    if axis != CubeAxis::UD {
-                        x.e_insertions.values_mut().for_each(|x|{
-                            *x = x.invert();
-                        });
-                    }
-                    x.vr_step_insertions(&post_dr_steps, transformations)
-                })
+                .map(|x|x.vr_step_insertions(&post_dr_steps, axis))
                 .map(|x|{
                     let mut solution = solution.clone();
                     solution.set_vr_insertions(x);
@@ -365,7 +374,7 @@ mod implementation {
                 })
                 .min_by(|a, b|{
                     fn insertion_count(sol: &Solution) -> usize {
-                        sol.steps.iter().map(|x|x.e_insertions.len()).sum()
+                        sol.steps.iter().map(|x|x.slice_insertions.len()).sum()
                     }
                     a.len().cmp(&b.len())
                         .then(insertion_count(&a).cmp(&insertion_count(&b)))
@@ -373,14 +382,7 @@ mod implementation {
         } else {
             aggregated.find_vrs(target_number).into_iter()
                 .filter_map(|vr|vr.solve(&dr_cube))
-                .map(|mut x|{
-                    if axis != CubeAxis::UD {
-                        x.e_insertions.values_mut().for_each(|x|{
-                            *x = x.invert();
-                        });
-                    }
-                    x.vr_step_insertions(&post_dr_steps, transformations)
-                })
+                .map(|x|x.vr_step_insertions(&post_dr_steps, axis))
                 .map(|x|{
                     let mut solution = solution.clone();
                     solution.set_vr_insertions(x);
@@ -388,7 +390,7 @@ mod implementation {
                 })
                 .min_by(|a, b|{
                     fn insertion_count(sol: &Solution) -> usize {
-                        sol.steps.iter().map(|x|x.e_insertions.len()).sum()
+                        sol.steps.iter().map(|x|x.slice_insertions.len()).sum()
                     }
                     a.len().cmp(&b.len())
                         .then(insertion_count(&a).cmp(&insertion_count(&b)))
@@ -873,7 +875,8 @@ mod implementation {
             for t in turns.iter().rev() {
                 dr_cube.turn(t.invert());
             }
-            dr_step.into_worker(dr_cube)
+            // We are already running inside of a task, so there is no point in spinning up another pool
+            dr_step.into_worker_with_context(dr_cube, WorkerContext::default().with_execution(ExecutionMode::SingleThreaded))
                 .next()
                 .expect("Cube has to be solvable")
                 .into()
@@ -325,7 +325,7 @@ pub fn next_step<
                                 alg: step_alg,
                                 variant,
                                 comment: String::default(),
-                                e_insertions: Default::default(),
+                                slice_insertions: vec![],
                             };
                             sol.add_step(sol_step);
                         }
@@ -33,6 +33,8 @@ use crate::solver::lookup_table::{DepthEstimate, InMemoryIndexTable, InMemoryNis
 #[cfg(feature = "fs")]
 use crate::steps::coord::Coord;
 
+/// Cloning copies all loaded tables
+#[derive(Clone)]
 pub struct PruningTables333 {
     #[cfg(feature = "333eo")]
     eo: Option<EOPruningTable>,
@@ -1,68 +1,114 @@
 #[cfg(feature = "wasm_solver")]
 pub mod worker {
-    use cubelib::cubie::CubieCube;
-    use cubelib::lookup_table::TableSource;
-    use cubelib::solution::Solution;
-    use cubelib::solver::gen_tables;
-    use cubelib::steps::step::StepConfig;
-    use cubelib::tables::PruningTables;
+    use std::sync::Arc;
+    use std::task::Poll;
+
+    use cubelib::cube::Cube333;
+    use cubelib::solver::df_search::CancelToken;
+    use cubelib::solver::lookup_table::{preload_table, take_generated_tables};
+    use cubelib::solver::solution::Solution;
+    use cubelib::solver_new::{build_step_tree, StepConfigTree};
+    use cubelib::solver_new::executor::ExecutionMode;
+    use cubelib::solver_new::thread_util::WorkerContext;
+    use futures::StreamExt;
+    use gloo_timers::future::TimeoutFuture;
     use gloo_worker::{HandlerId, Worker, WorkerScope};
     use leptos::spawn_local;
     use serde::{Deserialize, Serialize};
 
-    use idb::{Error, Factory, IndexParams, KeyPath, ObjectStore, ObjectStoreParams, TransactionMode};
+    use idb::{Factory, IndexParams, KeyPath, ObjectStore, ObjectStoreParams, TransactionMode};
     use wasm_bindgen::JsValue;
 
-    use crate::worker::WorkerResponse::{InvalidStepConfig, NoSolution, Solved, UnknownError};
+    use crate::worker::WorkerResponse::{Done, InvalidStepConfig, NoSolution, Solved};
+
+    const DB_NAME: &str = "maillard";
+    const DB_VERSION: u32 = 2;
+    const TABLE_STORE: &str = "pruning_tables";
 
     pub struct FMCSolver {
-        cancel: bool,
+        cancel_token: Arc<CancelToken>,
+        tables_preloaded: bool,
+    }
+
+    #[derive(Clone, Serialize, Deserialize)]
+    pub struct SolveRequest {
+        pub cube: Cube333,
+        pub steps: StepConfigTree,
+        pub solution_count: usize,
     }
 
+    /// Every solution is sent as soon as it is found. The last response for a request is always one of
+    /// `Done`, `NoSolution`, `InvalidStepConfig` or `UnknownError`.
     #[derive(Clone, Serialize, Deserialize)]
     pub enum WorkerResponse {
         Solved(Solution),
+        Done,
         NoSolution,
         InvalidStepConfig,
         UnknownError,
     }
 
     impl Worker for FMCSolver {
-        type Input = (CubieCube, Vec<StepConfig>);
+        type Input = SolveRequest;
         type Message = ();
         type Output = WorkerResponse;
 
         fn create(_scope: &WorkerScope<Self>) -> Self {
             Self {
-                cancel: false,
+                cancel_token: Arc::new(CancelToken::default()),
+                tables_preloaded: false,
             }
         }
 
         fn update(&mut self, _scope: &WorkerScope<Self>, _msg: Self::Message) {
-            self.cancel = true;
+            self.cancel_token.cancel();
         }
 
         fn received(&mut self, scope: &WorkerScope<Self>, msg: Self::Input, id: HandlerId) {
-            let (cube, steps_config) = msg;
             let scope = scope.clone();
+            self.cancel_token = Arc::new(CancelToken::default());
+            // Web workers don't have threads, so all steps share this one and take turns
+            let ctx = WorkerContext::default()
+                .with_execution(ExecutionMode::SingleThreaded)
+                .with_cancel_token(self.cancel_token.clone());
+            // Tables are only loaded once per worker, so there is no need to read them again afterwards
+            let preload = !self.tables_preloaded;
+            self.tables_preloaded = true;
             spawn_local(async move {
-                match load_pt(&steps_config).await {
-                    Ok(tables) => {
-                        if let Some(steps) = cubelib::solver::build_steps(steps_config, &tables).ok() {
-                            let solution = cubelib::solver::solve_steps(cube, &steps).next()
-                                .map_or(NoSolution, |s| Solved(s));
-                            scope.respond(id, solution);
-                        } else {
-                            scope.respond(id, InvalidStepConfig);
-                        };
+                if preload {
+                    if let Err(e) = load_tables().await {
+                        log::warn!("Failed to load pruning tables: '{e}'");
+                    }
+                }
+                // Building the steps generates all tables they need that weren't loaded
+                let steps = match build_step_tree(msg.steps) {
+                    Ok(steps) => steps,
+                    Err(e) => {
+                        log::error!("Invalid step config: '{e}'");
+                        scope.respond(id, InvalidStepConfig);
+                        return;
                     }
-                    Err(s) => {
-                        log::error!("Error loading pruning table: '{s}'");
-                        scope.respond(id, UnknownError);
+                };
+                if let Err(e) = save_generated_tables().await {
+                    log::warn!("Failed to save pruning tables: '{e}'");
+                }
+
+                let mut solutions = steps.into_stream_with_context(msg.cube, ctx);
+                let mut found = 0;
+                while found < msg.solution_count {
+                    match futures::poll!(solutions.next()) {
+                        Poll::Ready(Some(solution)) => {
+                            found += 1;
+                            scope.respond(id, Solved(solution));
+                        }
+                        Poll::Ready(None) => break,
+                        // The stream wakes itself again right away, which only queues a microtask.
+                        // Waiting for a timeout lets the worker handle messages (like cancellation) in between work slices.
+                        Poll::Pending => TimeoutFuture::new(0).await,
                     }
                 }
+                scope.respond(id, if found == 0 { NoSolution } else { Done });
             })
-
         }
     }
 
@@ -72,86 +118,54 @@ pub mod worker {
         data: serde_bytes::ByteBuf,
     }
 
-    async fn load_pt(steps: &Vec<StepConfig>) -> Result<PruningTables, String> {
+    async fn open_db() -> Result<idb::Database, String> {
         let factory = Factory::new().map_err(|e| e.to_string())?;
-        let mut open_req = factory.open("maillard", Some(PruningTables::VERSION)).map_err(|e| e.to_string())?;
+        let mut open_req = factory.open(DB_NAME, Some(DB_VERSION)).map_err(|e| e.to_string())?;
         open_req.on_upgrade_needed(|event| {
             let db = event.database().unwrap();
-            let mut params = ObjectStoreParams::new();
-            // params.key_path(Some(KeyPath::new_single("id")));
-            let store = db.create_object_store("pruning_tables", params).unwrap();
+            let params = ObjectStoreParams::new();
+            let store = db.create_object_store(TABLE_STORE, params).unwrap();
 
             let mut index_params = IndexParams::new();
             index_params.unique(true);
             store.create_index("entry", KeyPath::new_single("id"), Some(index_params)).unwrap();
         });
-        let db = open_req.await.map_err(|e| e.to_string()).map_err(|e| e.to_string())?;
-        let transaction = db.transaction(&["pruning_tables"], TransactionMode::ReadOnly).map_err(|e| e.to_string())?;
-        let store = transaction.object_store("pruning_tables").map_err(|e| e.to_string())?;
+        open_req.await.map_err(|e| e.to_string())
+    }
 
-        let mut pt = PruningTables::new();
+    async fn load_tables() -> Result<(), String> {
+        let db = open_db().await?;
+        let transaction = db.transaction(&[TABLE_STORE], TransactionMode::ReadOnly).map_err(|e| e.to_string())?;
+        let store = transaction.object_store(TABLE_STORE).map_err(|e| e.to_string())?;
         for val in store.get_all(None, None).await.map_err(|e| e.to_string())? {
             let entry: PtEntry = serde_wasm_bindgen::from_value(val).map_err(|e| e.to_string())?;
-            pt.load(entry.id.as_str(), entry.data.into_vec())
+            preload_table(entry.id.as_str(), entry.data.into_vec());
         }
         transaction.done().await.map_err(|e| e.to_string())?;
-        gen_tables(steps, &mut pt);
-        let transaction = db.transaction(&["pruning_tables"], TransactionMode::ReadWrite).map_err(|e| e.to_string())?;
-        let store = transaction.object_store("pruning_tables").map_err(|e| e.to_string())?;
-        if let Some(eo) = pt.eo() {
-            if eo.get_source() == TableSource::Generated {
-                crate::worker::store(&store, &PtEntry {
-                    id: "eo".to_string(),
-                    data: serde_bytes::ByteBuf::from(eo.get_bytes())
-                }).await?;
-            }
-        }
-        if let Some(dr) = pt.dr() {
-            if dr.get_source() == TableSource::Generated {
-                crate::worker::store(&store, &PtEntry {
-                    id: "dr".to_string(),
-                    data: serde_bytes::ByteBuf::from(dr.get_bytes())
-                }).await?;
-            }
-        }
-        if let Some(htr) = pt.htr() {
-            if htr.get_source() == TableSource::Generated {
-                crate::worker::store(&store, &PtEntry {
-                    id: "htr".to_string(),
-                    data: serde_bytes::ByteBuf::from(htr.get_bytes())
-                }).await?;
-            }
-        }
-        if let Some(fr) = pt.fr() {
-            if fr.get_source() == TableSource::Generated {
-                crate::worker::store(&store, &PtEntry {
-                    id: "fr".to_string(),
-                    data: serde_bytes::ByteBuf::from(fr.get_bytes())
-                }).await?;
-            }
-        }
-        if let Some(frls) = pt.fr_leave_slice() {
-            if frls.get_source() == TableSource::Generated {
-                crate::worker::store(&store, &PtEntry {
-                    id: "frls".to_string(),
-                    data: serde_bytes::ByteBuf::from(frls.get_bytes())
-                }).await?;
-            }
+        Ok(())
+    }
+
+    async fn save_generated_tables() -> Result<(), String> {
+        let generated = take_generated_tables();
+        if generated.is_empty() {
+            return Ok(());
         }
-        if let Some(frfin) = pt.fr_finish() {
-            if frfin.get_source() == TableSource::Generated {
-                crate::worker::store(&store, &PtEntry {
-                    id: "frfin".to_string(),
-                    data: serde_bytes::ByteBuf::from(frfin.get_bytes())
-                }).await?;
-            }
+        let db = open_db().await?;
+        let transaction = db.transaction(&[TABLE_STORE], TransactionMode::ReadWrite).map_err(|e| e.to_string())?;
+        let store = transaction.object_store(TABLE_STORE).map_err(|e| e.to_string())?;
+        for (id, data) in generated {
+            // Replaces tables that failed to load, e.g. because they were saved by an older version
+            crate::worker::store(&store, &PtEntry {
+                id,
+                data: serde_bytes::ByteBuf::from(data),
+            }).await?;
         }
         transaction.done().await.map_err(|e| e.to_string())?;
-        Ok(pt)
+        Ok(())
     }
 
     async fn store(store: &ObjectStore, entry: &PtEntry) -> Result<(), String> {
         let val = serde_wasm_bindgen::to_value(entry).map_err(|e| e.to_string())?;
-        store.add(&val, Some(&JsValue::from(entry.id.clone()))).await.map_err(|e| format!("{} {e}", entry.id).to_string()).map(|_|())
+        store.put(&val, Some(&JsValue::from(entry.id.clone()))).await.map_err(|e| format!("{} {e}", entry.id).to_string()).map(|_|())
     }
 }
@@ -1,4 +1,5 @@
 use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
 use std::iter::Map;
 use cubelib::algs::Algorithm;
 use cubelib::defs::StepKind;
@@ -6,7 +7,7 @@ use cubelib::solver::solution::Solution;
 use cubelib::steps::step::StepConfig;
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Clone, Eq, PartialEq)]
+#[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Debug)]
 pub struct SolverRequest {
     pub scramble: String,
     pub steps: Vec<StepConfig>,
@@ -14,6 +15,304 @@ pub struct SolverRequest {
 
 #[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Debug)]
 pub struct SolverResponse {
+    /// Slice insertions are sent as `slice_insertions` per step, together with `vr` on the solution.
+    /// Older versions sent `e_insertions` and `insertion_direction` instead. Solutions in that format are still accepted
     pub solution: Option<Solution>,
     pub done: bool,
+    /// Set while the request waits for other solves to finish. 0 means it is next
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub queue_position: Option<usize>,
+    /// Set if the solve failed after the response started. It is followed by the done message
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<ApiError>,
+}
+
+/// Errors of the backend. HTTP endpoints return them as [`ErrorResponse`] with a matching status,
+/// streams send them as part of their messages
+#[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Debug)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ApiError {
+    /// `position` is the character offset of the first move that couldn't be parsed
+    InvalidScramble {
+        position: usize,
+        token: String,
+    },
+    InvalidSolution {
+        position: usize,
+        token: String,
+    },
+    InvalidStepConfig {
+        message: String,
+    },
+    UnsupportedStepOrder {
+        message: String,
+    },
+    /// Any other malformed request, e.g. invalid JSON or query parameters
+    InvalidRequest {
+        message: String,
+    },
+    NotFound {
+        message: String,
+    },
+    /// Missing or wrong admin token
+    Unauthorized {
+        message: String,
+    },
+    /// Too many requests are waiting. Retrying later might work
+    Overloaded {
+        message: String,
+    },
+    Internal,
+}
+
+impl Display for ApiError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ApiError::InvalidScramble { position, token } => write!(f, "Invalid scramble. Unexpected '{token}' at position {position}"),
+            ApiError::InvalidSolution { position, token } => write!(f, "Invalid solution. Unexpected '{token}' at position {position}"),
+            ApiError::InvalidStepConfig { message } => write!(f, "Invalid step configuration. {message}"),
+            ApiError::UnsupportedStepOrder { message } => write!(f, "{message}"),
+            ApiError::InvalidRequest { message } => write!(f, "Invalid request. {message}"),
+            ApiError::NotFound { message } => write!(f, "{message}"),
+            ApiError::Unauthorized { message } => write!(f, "{message}"),
+            ApiError::Overloaded { message } => write!(f, "{message}"),
+            ApiError::Internal => write!(f, "Internal server error"),
+        }
+    }
+}
+
+/// The body of HTTP error responses. `message` describes the error for humans
+#[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Debug)]
+pub struct ErrorResponse {
+    pub error: ApiError,
+    pub message: String,
+}
+
+impl From<ApiError> for ErrorResponse {
+    fn from(error: ApiError) -> Self {
+        Self {
+            message: error.to_string(),
+            error,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Debug)]
+pub struct AnalyzeRequest {
+    pub scramble: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Debug)]
+pub struct CubeAnalysis {
+    /// The furthest solved step. One of scrambled, eo, dr, triple-dr, htr, fr or solved
+    pub state: String,
+    /// The number of bad edges for every EO axis
+    pub eo: Vec<EOAnalysis>,
+    /// The axes DR is solved on
+    pub dr_axes: Vec<String>,
+    /// The DR subset, e.g. 4a1 2e. Only set if DR is solved
+    pub dr_subset: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Debug)]
+pub struct EOAnalysis {
+    pub axis: String,
+    pub bad_edges: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Debug)]
+pub struct VerifyRequest {
+    pub scramble: String,
+    pub solution: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Debug)]
+pub struct VerifyResponse {
+    pub solved: bool,
+    pub length: usize,
+    /// The state after applying the solution, so partial solutions like EO > DR can be checked too
+    pub analysis: CubeAnalysis,
+}
+
+#[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Debug)]
+pub struct ScrambleResponse {
+    pub scrambles: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Debug)]
+pub struct TablesResponse {
+    pub tables: Vec<TableStatus>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Debug)]
+pub struct TableStatus {
+    pub name: String,
+    pub loaded: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Debug)]
+pub struct HealthResponse {
+    /// True if the server can handle solve requests
+    pub ready: bool,
+    pub database: bool,
+    pub tables: bool,
+}
+
+/// Statistics over the recorded requests. All counts only include requests in the selected date range
+#[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Debug)]
+pub struct StatsResponse {
+    pub total_requests: usize,
+    pub requests_per_day: Vec<DayCount>,
+    /// Most requested first. States that are the same up to rotation, mirroring and inversion count as one
+    pub popular_scrambles: Vec<ScrambleCount>,
+    /// Most requested first
+    pub popular_steps: Vec<StepsCount>,
+    /// The length of the shortest solution found for each request, by length
+    pub solution_lengths: Vec<LengthCount>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Debug)]
+pub struct DayCount {
+    /// YYYY-MM-DD in UTC
+    pub date: String,
+    pub requests: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Debug)]
+pub struct ScrambleCount {
+    pub canonical_scramble_id: String,
+    /// One of the requested scrambles for this state
+    pub scramble: String,
+    pub requests: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Debug)]
+pub struct StepsCount {
+    /// The step kinds and substeps, e.g. `EO[ud] > DR > HTR > FIN`. Other settings are ignored
+    pub steps: String,
+    pub requests: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Debug)]
+pub struct LengthCount {
+    pub length: usize,
+    pub requests: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Default, Debug, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SolverBackend {
+    #[default]
+    IterStream,
+    MultiPathChannel,
+}
+
+/// Limits of a solve started over the WebSocket. They can be changed while the solve is running
+#[derive(Serialize, Deserialize, Clone, Copy, Default, Debug, Eq, PartialEq)]
+pub struct SolveLimits {
+    /// Solutions longer than this are not sent
+    #[serde(default)]
+    pub max_length: Option<usize>,
+    /// The solve stops once this many solutions were sent
+    #[serde(default)]
+    pub solution_count: Option<usize>,
+}
+
+/// Messages sent by the client over the `/solve_ws` WebSocket. Solves are identified by an id chosen by the client
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    Solve {
+        id: u64,
+        request: SolverRequest,
+        #[serde(default)]
+        backend: SolverBackend,
+        #[serde(default)]
+        rank_by: Option<String>,
+        #[serde(default)]
+        limits: SolveLimits,
+    },
+    UpdateLimits {
+        id: u64,
+        limits: SolveLimits,
+    },
+    Cancel {
+        id: u64,
+    },
+}
+
+/// Messages sent by the server over the `/solve_ws` WebSocket
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    /// A solution that ranks better than all previous solutions of this solve
+    Solution {
+        id: u64,
+        solution: Solution,
+    },
+    /// The solve waits for other solves to finish. Sent whenever the position changes, 0 means it is next
+    Queued {
+        id: u64,
+        position: usize,
+    },
+    /// The solve left the queue and the search begins
+    Started {
+        id: u64,
+    },
+    /// The search with the given quality is done. The next search uses twice the quality
+    Progress {
+        id: u64,
+        quality: usize,
+        solutions: usize,
+    },
+    /// No more messages are sent for this solve
+    Done {
+        id: u64,
+        cancelled: bool,
+    },
+    /// Errors that belong to a solve have its id. If the solve failed to start, no done message follows
+    Error {
+        id: Option<u64>,
+        error: ApiError,
+        message: String,
+    },
+}
+
+/// A solve that runs in the background, see `/jobs`
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct JobRequest {
+    pub request: SolverRequest,
+    #[serde(default)]
+    pub backend: SolverBackend,
+    #[serde(default)]
+    pub rank_by: Option<String>,
+    #[serde(default)]
+    pub limits: SolveLimits,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    Queued,
+    Running,
+    Done,
+    Cancelled,
+    Failed,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct JobStatus {
+    pub id: u64,
+    pub state: JobState,
+    /// Set while the job waits for other solves to finish. 0 means it is next
+    pub queue_position: Option<usize>,
+    /// The highest quality that was searched completely
+    pub quality: Option<usize>,
+    /// Every solution ranks better than the ones before it, so the last one is the best so far
+    pub solutions: Vec<Solution>,
+    /// Set if the job failed
+    pub error: Option<ApiError>,
+    /// Unix timestamps in seconds. The job and its results are deleted once it expired
+    pub created: u64,
+    pub expires: u64,
 }